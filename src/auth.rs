@@ -0,0 +1,474 @@
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+use subtle::ConstantTimeEq;
+use tracing::{info, warn};
+
+use crate::domains::DomainPolicy;
+use crate::error::{AppError, AppResult};
+use crate::ratelimit::KeyLimits;
+use crate::scheduler::TenantPolicy;
+
+/// ID of the key built from the legacy single `API_KEY`
+const LEGACY_KEY_ID: &str = "default";
+
+/// What a key may be used for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Scope {
+    /// Single-page endpoints: `/scrape`, `/scrape/stream`, `/scrape/pdf`, `/jobs`
+    Scrape,
+    /// Any request carrying an `analysis_request`
+    Analysis,
+    /// Multi-page endpoints: `/scrape/batch`, `/crawl`, `/sitemap`
+    Batch,
+}
+
+impl std::fmt::Display for Scope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Scope::Scrape => "scrape",
+            Scope::Analysis => "analysis",
+            Scope::Batch => "batch",
+        };
+        f.write_str(name)
+    }
+}
+
+#[derive(Debug)]
+pub struct ApiKey {
+    pub id: String,
+    pub label: String,
+    /// Lowercase hex SHA-256 of the key
+    key_sha256: String,
+    scopes: HashSet<Scope>,
+    /// Unix epoch seconds
+    expires_at: Option<u64>,
+    disabled: bool,
+    pub limits: KeyLimits,
+    /// Share of browser slots when they are contended
+    pub scheduling: TenantPolicy,
+    /// Hosts this key may scrape
+    pub domains: DomainPolicy,
+}
+
+impl ApiKey {
+    pub fn allows(&self, scope: Scope) -> bool {
+        self.scopes.contains(&scope)
+    }
+
+    fn is_active(&self, now_secs: u64) -> bool {
+        !self.disabled && self.expires_at.is_none_or(|at| now_secs < at)
+    }
+}
+
+/// Key file / `API_KEYS` JSON
+#[derive(Debug, Deserialize)]
+struct KeyFile {
+    keys: Vec<KeyConfig>,
+}
+
+#[derive(Debug, Deserialize)]
+struct KeyConfig {
+    id: String,
+    #[serde(default)]
+    label: String,
+    key_sha256: String,
+    scopes: Vec<Scope>,
+    #[serde(default)]
+    expires_at: Option<u64>,
+    #[serde(default)]
+    disabled: bool,
+    #[serde(default)]
+    limits: KeyLimits,
+    #[serde(default)]
+    scheduling: TenantPolicy,
+    #[serde(default)]
+    domains: DomainPolicy,
+}
+
+/// Where keys come from; only a file can change between reloads
+#[derive(Debug, Clone)]
+pub enum KeySource {
+    File(PathBuf),
+    Json(String),
+    /// Legacy plaintext `API_KEY`, granted every scope
+    Single(String),
+}
+
+/// API keys, swapped atomically on reload
+pub struct KeyRegistry {
+    source: KeySource,
+    keys: RwLock<Arc<Vec<ApiKey>>>,
+}
+
+impl KeyRegistry {
+    pub fn load(source: KeySource) -> AppResult<Self> {
+        let keys = read_keys(&source)?;
+        Ok(Self {
+            source,
+            keys: RwLock::new(Arc::new(keys)),
+        })
+    }
+
+    /// `API_KEYS_FILE` (path), then `API_KEYS` (inline JSON), then the single `API_KEY`
+    pub fn from_env() -> AppResult<Self> {
+        let source = if let Ok(path) = std::env::var("API_KEYS_FILE") {
+            KeySource::File(PathBuf::from(path))
+        } else if let Ok(json) = std::env::var("API_KEYS") {
+            KeySource::Json(json)
+        } else {
+            KeySource::Single(std::env::var("API_KEY").unwrap_or_else(|_| {
+                warn!("API_KEY not set, using default");
+                "changeme".to_string()
+            }))
+        };
+        Self::load(source)
+    }
+
+    /// Re-reads the source; on error the current keys stay in place
+    pub fn reload(&self) -> AppResult<usize> {
+        let keys = read_keys(&self.source)?;
+        let count = keys.len();
+        *self.keys.write().unwrap() = Arc::new(keys);
+        Ok(count)
+    }
+
+    pub fn count(&self) -> usize {
+        self.keys.read().unwrap().len()
+    }
+
+    /// Finds the active key matching `provided` and checks it grants every scope in `required`
+    pub fn authenticate(&self, provided: &str, required: &[Scope]) -> AppResult<ApiKeyRef> {
+        let keys = self.keys.read().unwrap().clone();
+        let provided_hash = hash_key(provided);
+
+        // Compare against every key so timing doesn't reveal which one matched
+        let mut matched = None;
+        for (index, key) in keys.iter().enumerate() {
+            if secure_compare(&provided_hash, &key.key_sha256) {
+                matched = Some(index);
+            }
+        }
+
+        let index = matched.ok_or(AppError::Unauthorized)?;
+        let key = &keys[index];
+        if !key.is_active(now_secs()) {
+            warn!(key_id = %key.id, "Disabled or expired API key");
+            return Err(AppError::Unauthorized);
+        }
+        if let Some(scope) = required.iter().find(|s| !key.allows(**s)) {
+            warn!(key_id = %key.id, %scope, "API key lacks scope");
+            return Err(AppError::Forbidden(format!(
+                "API key '{}' lacks the '{}' scope",
+                key.id, scope
+            )));
+        }
+
+        Ok(ApiKeyRef { keys, index })
+    }
+}
+
+/// Authenticated key; stays valid even if the registry is reloaded meanwhile
+#[derive(Debug, Clone)]
+pub struct ApiKeyRef {
+    keys: Arc<Vec<ApiKey>>,
+    index: usize,
+}
+
+impl std::ops::Deref for ApiKeyRef {
+    type Target = ApiKey;
+
+    fn deref(&self) -> &ApiKey {
+        &self.keys[self.index]
+    }
+}
+
+/// Reloads keys whenever the process receives SIGHUP
+#[cfg(unix)]
+pub fn spawn_reload_on_sighup(registry: Arc<KeyRegistry>) {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut hangup = match signal(SignalKind::hangup()) {
+        Ok(hangup) => hangup,
+        Err(e) => {
+            warn!(error = %e, "Failed to install SIGHUP handler, key reload disabled");
+            return;
+        }
+    };
+    tokio::spawn(async move {
+        while hangup.recv().await.is_some() {
+            match registry.reload() {
+                Ok(count) => info!(keys = count, "API keys reloaded"),
+                Err(e) => warn!(error = %e, "API key reload failed, keeping previous keys"),
+            }
+        }
+    });
+}
+
+#[cfg(not(unix))]
+pub fn spawn_reload_on_sighup(_registry: Arc<KeyRegistry>) {}
+
+fn read_keys(source: &KeySource) -> AppResult<Vec<ApiKey>> {
+    let json = match source {
+        KeySource::Single(key) => {
+            return Ok(vec![ApiKey {
+                id: LEGACY_KEY_ID.to_string(),
+                label: "API_KEY".to_string(),
+                key_sha256: hash_key(key),
+                scopes: HashSet::from([Scope::Scrape, Scope::Analysis, Scope::Batch]),
+                expires_at: None,
+                disabled: false,
+                limits: KeyLimits::default(),
+                scheduling: TenantPolicy::default(),
+                domains: DomainPolicy::default(),
+            }]);
+        }
+        KeySource::File(path) => std::fs::read_to_string(path).map_err(|e| {
+            AppError::Internal(format!("Failed to read {}: {}", path.display(), e))
+        })?,
+        KeySource::Json(json) => json.clone(),
+    };
+    parse_keys(&json)
+}
+
+fn parse_keys(json: &str) -> AppResult<Vec<ApiKey>> {
+    let file: KeyFile = serde_json::from_str(json)
+        .map_err(|e| AppError::Internal(format!("Invalid key config: {}", e)))?;
+
+    let mut ids = HashSet::new();
+    file.keys
+        .into_iter()
+        .map(|config| {
+            if !ids.insert(config.id.clone()) {
+                return Err(AppError::Internal(format!("Duplicate key id '{}'", config.id)));
+            }
+            let key_sha256 = config.key_sha256.to_lowercase();
+            if key_sha256.len() != 64 || !key_sha256.bytes().all(|b| b.is_ascii_hexdigit()) {
+                return Err(AppError::Internal(format!(
+                    "Key '{}': key_sha256 must be 64 hex characters",
+                    config.id
+                )));
+            }
+            Ok(ApiKey {
+                id: config.id,
+                label: config.label,
+                key_sha256,
+                scopes: config.scopes.into_iter().collect(),
+                expires_at: config.expires_at,
+                disabled: config.disabled,
+                limits: config.limits,
+                scheduling: config.scheduling,
+                domains: config.domains,
+            })
+        })
+        .collect()
+}
+
+fn hash_key(key: &str) -> String {
+    hex::encode(Sha256::digest(key.as_bytes()))
+}
+
+/// Constant-time comparison to prevent timing attacks
+fn secure_compare(a: &str, b: &str) -> bool {
+    a.as_bytes().ct_eq(b.as_bytes()).into()
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key_json(id: &str, key: &str, extra: &str) -> String {
+        format!(
+            r#"{{"id": "{}", "label": "Team {}", "key_sha256": "{}", {}}}"#,
+            id,
+            id,
+            hash_key(key),
+            extra
+        )
+    }
+
+    fn registry(keys: &[String]) -> KeyRegistry {
+        KeyRegistry::load(KeySource::Json(format!(r#"{{"keys": [{}]}}"#, keys.join(",")))).unwrap()
+    }
+
+    // ==================== secure_compare ====================
+
+    #[test]
+    fn secure_compare_equal() {
+        assert!(secure_compare("secret", "secret"));
+    }
+
+    #[test]
+    fn secure_compare_different() {
+        assert!(!secure_compare("secret", "wrong"));
+    }
+
+    #[test]
+    fn secure_compare_empty() {
+        assert!(secure_compare("", ""));
+        assert!(!secure_compare("", "x"));
+    }
+
+    #[test]
+    fn secure_compare_different_length() {
+        assert!(!secure_compare("short", "longer_string"));
+    }
+
+    // ==================== authenticate ====================
+
+    #[test]
+    fn authenticate_matches_hashed_key() {
+        let registry = registry(&[
+            key_json("a", "key-a", r#""scopes": ["scrape"]"#),
+            key_json("b", "key-b", r#""scopes": ["scrape", "batch"]"#),
+        ]);
+
+        let key = registry.authenticate("key-b", &[Scope::Batch]).unwrap();
+        assert_eq!(key.id, "b");
+        assert_eq!(key.label, "Team b");
+    }
+
+    #[test]
+    fn authenticate_unknown_key() {
+        let registry = registry(&[key_json("a", "key-a", r#""scopes": ["scrape"]"#)]);
+        let err = registry.authenticate("nope", &[]).unwrap_err();
+        assert_eq!(err.code(), "UNAUTHORIZED");
+    }
+
+    #[test]
+    fn authenticate_missing_scope() {
+        let registry = registry(&[key_json("a", "key-a", r#""scopes": ["scrape"]"#)]);
+        let err = registry.authenticate("key-a", &[Scope::Scrape, Scope::Analysis]).unwrap_err();
+        assert_eq!(err.code(), "FORBIDDEN");
+        assert!(err.to_string().contains("'analysis'"));
+    }
+
+    #[test]
+    fn authenticate_disabled_and_expired() {
+        let registry = registry(&[
+            key_json("off", "key-off", r#""scopes": ["scrape"], "disabled": true"#),
+            key_json("old", "key-old", r#""scopes": ["scrape"], "expires_at": 1000"#),
+            key_json("new", "key-new", r#""scopes": ["scrape"], "expires_at": 32503680000"#),
+        ]);
+
+        assert_eq!(registry.authenticate("key-off", &[]).unwrap_err().code(), "UNAUTHORIZED");
+        assert_eq!(registry.authenticate("key-old", &[]).unwrap_err().code(), "UNAUTHORIZED");
+        assert!(registry.authenticate("key-new", &[Scope::Scrape]).is_ok());
+    }
+
+    #[test]
+    fn parse_limits() {
+        let registry = registry(&[key_json(
+            "a",
+            "key-a",
+            r#""scopes": ["scrape"], "limits": {"scrape": {"per_minute": 10, "daily": 100}}"#,
+        )]);
+        let key = registry.authenticate("key-a", &[]).unwrap();
+        assert_eq!(key.limits.scrape.per_minute, Some(10));
+        assert_eq!(key.limits.analysis.per_minute, None);
+    }
+
+    #[test]
+    fn parse_scheduling() {
+        let registry = registry(&[
+            key_json(
+                "bulk",
+                "key-bulk",
+                r#""scopes": ["scrape"], "scheduling": {"max_concurrent": 2, "weight": 3}"#,
+            ),
+            key_json("plain", "key-plain", r#""scopes": ["scrape"]"#),
+        ]);
+        let bulk = registry.authenticate("key-bulk", &[]).unwrap();
+        assert_eq!(bulk.scheduling.max_concurrent, Some(2));
+        assert_eq!(bulk.scheduling.weight, 3);
+        let plain = registry.authenticate("key-plain", &[]).unwrap();
+        assert_eq!(plain.scheduling.max_concurrent, None);
+        assert_eq!(plain.scheduling.weight, 1);
+    }
+
+    #[test]
+    fn parse_domains() {
+        let registry = registry(&[key_json(
+            "tenant",
+            "key-tenant",
+            r#""scopes": ["scrape"], "domains": {"allow": ["acme.com", "*.acme.com"], "max_url_length": 512}"#,
+        )]);
+        let key = registry.authenticate("key-tenant", &[]).unwrap();
+        assert_eq!(key.domains.allow.len(), 2);
+        assert_eq!(key.domains.max_url_length, Some(512));
+
+        let bad = format!(
+            r#"{{"keys": [{}]}}"#,
+            key_json("bad", "key-bad", r#""scopes": ["scrape"], "domains": {"deny": ["regex:("]}"#)
+        );
+        assert!(KeyRegistry::load(KeySource::Json(bad)).is_err());
+    }
+
+    #[test]
+    fn legacy_single_key_has_all_scopes() {
+        let registry = KeyRegistry::load(KeySource::Single("secret".to_string())).unwrap();
+        let key = registry
+            .authenticate("secret", &[Scope::Scrape, Scope::Analysis, Scope::Batch])
+            .unwrap();
+        assert_eq!(key.id, LEGACY_KEY_ID);
+    }
+
+    // ==================== parse / reload ====================
+
+    #[test]
+    fn parse_rejects_plaintext_and_duplicates() {
+        let plaintext = r#"{"keys": [{"id": "a", "key_sha256": "secret", "scopes": []}]}"#;
+        assert!(parse_keys(plaintext).unwrap_err().to_string().contains("64 hex"));
+
+        let key = key_json("a", "k", r#""scopes": []"#);
+        let duplicate = format!(r#"{{"keys": [{}, {}]}}"#, key, key);
+        assert!(parse_keys(&duplicate).unwrap_err().to_string().contains("Duplicate"));
+    }
+
+    #[test]
+    fn parse_rejects_unknown_scope() {
+        let json = format!(r#"{{"keys": [{}]}}"#, key_json("a", "k", r#""scopes": ["admin"]"#));
+        assert!(parse_keys(&json).is_err());
+    }
+
+    #[test]
+    fn reload_from_file() {
+        let path = std::env::temp_dir().join(format!("distill-keys-{}.json", uuid::Uuid::new_v4()));
+        let write = |keys: &str| std::fs::write(&path, format!(r#"{{"keys": [{}]}}"#, keys)).unwrap();
+
+        write(&key_json("a", "key-a", r#""scopes": ["scrape"]"#));
+        let registry = KeyRegistry::load(KeySource::File(path.clone())).unwrap();
+        assert!(registry.authenticate("key-a", &[]).is_ok());
+
+        write(&key_json("b", "key-b", r#""scopes": ["scrape"]"#));
+        assert_eq!(registry.reload().unwrap(), 1);
+        assert!(registry.authenticate("key-a", &[]).is_err());
+        assert!(registry.authenticate("key-b", &[]).is_ok());
+
+        // A broken file keeps the previous keys
+        std::fs::write(&path, "{").unwrap();
+        assert!(registry.reload().is_err());
+        assert!(registry.authenticate("key-b", &[]).is_ok());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn key_ref_survives_reload() {
+        let registry = registry(&[key_json("a", "key-a", r#""scopes": ["scrape"]"#)]);
+        let key = registry.authenticate("key-a", &[]).unwrap();
+        *registry.keys.write().unwrap() = Arc::new(Vec::new());
+        assert_eq!(key.id, "a");
+    }
+}
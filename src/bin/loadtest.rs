@@ -205,9 +205,9 @@ async fn main() -> anyhow::Result<()> {
                     };
 
                     println!(
-                        "[{:3}] {}  {:>6.2}s  {}\n      └─ {}",
+                        "[{:3}] {:<7}  {:>6.2}s  {}\n      └─ {}",
                         i + 1,
-                        format!("{:<7}", error_kind),
+                        error_kind,
                         elapsed.as_secs_f64(),
                         url,
                         e
@@ -1,16 +1,211 @@
+use base64::Engine;
+use headless_chrome::browser::tab::{EventListener, RequestPausedDecision};
+use headless_chrome::browser::transport::{SessionId, Transport};
+use headless_chrome::protocol::cdp::Fetch::FailRequest;
+use headless_chrome::protocol::cdp::Fetch::events::RequestPausedEvent;
+use headless_chrome::protocol::cdp::types::Event;
+use headless_chrome::protocol::cdp::{Network, Page};
+use headless_chrome::types::PrintToPdfOptions;
 use headless_chrome::{Browser, LaunchOptions, Tab};
-use std::sync::Arc;
-use std::time::Duration;
-use tokio::sync::{Mutex, OwnedSemaphorePermit, RwLock, Semaphore};
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsStr;
+use std::future::Future;
+use std::sync::{Arc, Mutex as StdMutex, Weak};
+use std::time::{Duration, Instant};
+use tokio::sync::{Mutex, RwLock};
 use tokio::time::timeout;
 use tracing::{debug, error, info, warn};
+use url::Url;
 use uuid::Uuid;
 
-use crate::error::{AppError, AppResult};
-use crate::models::{OutputFormat, PageExtractResult, PageMetadata};
+use crate::config::ScrapeTimeouts;
+use crate::domains::DomainPolicy;
+use crate::error::{AppError, AppResult, TimeoutPhase};
+use crate::extract;
+use crate::models::{
+    ArticleInfo, ExtractMode, ImageFormat, OutputFormat, PageExtractResult, PageImage,
+    PageMetadata, PdfOptions, ScrapeRequest, Screenshot, ScreenshotOptions, SelectorMatches, WaitFor,
+};
+use crate::proxy::EgressProxy;
+use crate::scheduler::{SchedulerStats, SlotClaim, SlotPermit, SlotScheduler};
+use crate::ssrf::{HostVerdicts, UrlGuard};
 
 const IDLE_TAB_TIMEOUT_SECS: u64 = 1;
-const BROWSER_TIMEOUT_SECS: u64 = 10;
+const WAIT_POLL_INTERVAL_MS: u64 = 100;
+
+/// Page-context extraction script; `__OPTIONS__` is replaced with a JSON object
+const EXTRACT_SCRIPT: &str = r#"
+    JSON.stringify((() => {
+        const options = __OPTIONS__;
+        const og = {};
+        const metaTags = document.querySelectorAll('meta[property^="og:"]');
+        for (let i = 0; i < metaTags.length; i++) {
+            const tag = metaTags[i];
+            const prop = tag.getAttribute('property');
+            const content = tag.getAttribute('content');
+            if (prop && content) {
+                og[prop] = content;
+            }
+        }
+
+        const twitter = {};
+        const twitterTags = document.querySelectorAll('meta[name^="twitter:"], meta[property^="twitter:"]');
+        for (let i = 0; i < twitterTags.length; i++) {
+            const tag = twitterTags[i];
+            const name = tag.getAttribute('name') || tag.getAttribute('property');
+            const content = tag.getAttribute('content');
+            if (name && content) {
+                twitter[name] = content;
+            }
+        }
+
+        const metaContent = (selector) => {
+            const tag = document.querySelector(selector);
+            const content = tag ? (tag.getAttribute('content') || '').trim() : '';
+            return content || null;
+        };
+
+        const canonical = document.querySelector('link[rel~="canonical"][href]');
+
+        const favicons = [];
+        const iconLinks = document.querySelectorAll('link[rel~="icon"][href], link[rel="apple-touch-icon"][href]');
+        for (let i = 0; i < iconLinks.length; i++) {
+            const icon = iconLinks[i];
+            favicons.push({
+                url: icon.href,
+                rel: icon.getAttribute('rel') || '',
+                sizes: icon.getAttribute('sizes'),
+                mime_type: icon.getAttribute('type')
+            });
+        }
+
+        const jsonLd = [];
+        const ldScripts = document.querySelectorAll('script[type="application/ld+json"]');
+        for (let i = 0; i < ldScripts.length; i++) {
+            try {
+                jsonLd.push(JSON.parse(ldScripts[i].textContent));
+            } catch (e) {
+                // Skip malformed blocks
+            }
+        }
+
+        const links = [];
+        const anchors = document.querySelectorAll('a[href]');
+        for (let i = 0; i < anchors.length; i++) {
+            const a = anchors[i];
+            links.push({
+                url: a.href,
+                text: (a.innerText || a.textContent || '').replace(/\s+/g, ' ').trim(),
+                rel: (a.getAttribute('rel') || '').split(/\s+/).filter(Boolean)
+            });
+        }
+
+        const images = [];
+        const imageSources = [];
+        if (options.include_images) {
+            const seen = new Set();
+            const inViewport = (el) => {
+                const r = el.getBoundingClientRect();
+                return r.width > 0 && r.height > 0 && r.bottom > 0 && r.right > 0
+                    && r.top < window.innerHeight && r.left < window.innerWidth;
+            };
+            const addImage = (url, img, loaded) => {
+                if (!url || seen.has(url)) {
+                    return;
+                }
+                seen.add(url);
+                images.push({
+                    url,
+                    alt: img ? img.getAttribute('alt') : null,
+                    width: loaded && img.naturalWidth ? img.naturalWidth : null,
+                    height: loaded && img.naturalHeight ? img.naturalHeight : null,
+                    in_viewport: img ? inViewport(img) : false
+                });
+            };
+
+            const imgs = document.querySelectorAll('img');
+            for (let i = 0; i < imgs.length; i++) {
+                const img = imgs[i];
+                addImage(img.currentSrc || img.src, img, true);
+            }
+
+            // Alternate <picture> sources, parsed server-side; dimensions are only known for the chosen one
+            const sources = document.querySelectorAll('picture source[srcset]');
+            for (let i = 0; i < sources.length; i++) {
+                const img = sources[i].parentElement.querySelector('img');
+                imageSources.push({
+                    srcset: sources[i].getAttribute('srcset'),
+                    alt: img ? img.getAttribute('alt') : null,
+                    in_viewport: img ? inViewport(img) : false
+                });
+            }
+        }
+
+        const invalid = [];
+        const queryAll = (root, selector) => {
+            try {
+                return Array.from(root.querySelectorAll(selector));
+            } catch (e) {
+                invalid.push(selector);
+                return [];
+            }
+        };
+
+        const root = document.body
+            ? document.body.cloneNode(true)
+            : document.createElement('body');
+
+        const exclude = {};
+        for (const selector of options.exclude_selectors) {
+            const nodes = queryAll(root, selector);
+            exclude[selector] = nodes.length;
+            nodes.forEach((node) => node.remove());
+        }
+
+        let bodyHtml = root.outerHTML;
+        const include = {};
+        if (options.include_selectors.length > 0) {
+            const picked = new Set();
+            for (const selector of options.include_selectors) {
+                const nodes = queryAll(root, selector);
+                include[selector] = nodes.length;
+                nodes.forEach((node) => picked.add(node));
+            }
+            // Keep outermost matches only, in document order
+            const outermost = Array.from(picked)
+                .filter((node) => !Array.from(picked).some((other) => other !== node && other.contains(node)))
+                .sort((a, b) => (a.compareDocumentPosition(b) & Node.DOCUMENT_POSITION_FOLLOWING) ? -1 : 1);
+            bodyHtml = '<body>' + outermost.map((node) => node.outerHTML).join('\n') + '</body>';
+        }
+
+        return {
+            title: document.title || '',
+            og_tags: og,
+            twitter_tags: twitter,
+            description: metaContent('meta[name="description"]'),
+            canonical_url: canonical ? canonical.href : null,
+            language: document.documentElement.lang || null,
+            published_time: metaContent('meta[property="article:published_time"]'),
+            favicons,
+            json_ld: jsonLd,
+            links,
+            images,
+            image_sources: imageSources,
+            base_url: document.baseURI,
+            body_html: bodyHtml,
+            selector_matches: { include, exclude },
+            invalid_selectors: invalid
+        };
+    })())
+"#;
+
+/// Pipeline milestones reported by `scrape_page`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScrapeProgress {
+    TabAcquired,
+    Navigated,
+}
 
 struct IdleTab {
     id: Uuid,
@@ -20,9 +215,11 @@ struct IdleTab {
 /// Browser lifecycle manager with tab pooling and auto-restart
 pub struct BrowserManager {
     browser: RwLock<Arc<Browser>>,
-    semaphore: Arc<Semaphore>,
+    slots: SlotScheduler,
     idle_tabs: Arc<Mutex<Vec<IdleTab>>>,
     max_concurrent_tabs: usize,
+    url_guard: UrlGuard,
+    proxy: EgressProxy,
 }
 
 /// RAII guard for tab cleanup
@@ -30,7 +227,7 @@ pub struct TabGuard {
     tab: Arc<Tab>,
     tab_id: Uuid,
     idle_tabs: Arc<Mutex<Vec<IdleTab>>>,
-    _permit: OwnedSemaphorePermit,
+    _permit: SlotPermit,
 }
 
 impl TabGuard {
@@ -39,6 +236,69 @@ impl TabGuard {
     }
 }
 
+/// In-flight request tracking for network-idle waits
+struct NetworkActivity {
+    state: StdMutex<NetworkState>,
+}
+
+struct NetworkState {
+    in_flight: HashSet<String>,
+    last_change: Instant,
+}
+
+impl NetworkActivity {
+    fn new() -> Self {
+        Self {
+            state: StdMutex::new(NetworkState {
+                in_flight: HashSet::new(),
+                last_change: Instant::now(),
+            }),
+        }
+    }
+
+    fn on_event(&self, event: &Event) {
+        let mut state = self.state.lock().unwrap();
+        let changed = match event {
+            Event::NetworkRequestWillBeSent(ev) => {
+                state.in_flight.insert(ev.params.request_id.clone())
+            }
+            Event::NetworkLoadingFinished(ev) => state.in_flight.remove(&ev.params.request_id),
+            Event::NetworkLoadingFailed(ev) => state.in_flight.remove(&ev.params.request_id),
+            _ => false,
+        };
+        if changed {
+            state.last_change = Instant::now();
+        }
+    }
+
+    /// Blocks until no request has been in flight for `idle`, or fails after `limit`
+    fn wait_idle(&self, idle: Duration, limit: Duration) -> AppResult<()> {
+        let deadline = Instant::now() + limit;
+        loop {
+            {
+                let state = self.state.lock().unwrap();
+                if state.in_flight.is_empty() && state.last_change.elapsed() >= idle {
+                    return Ok(());
+                }
+                if Instant::now() >= deadline {
+                    return Err(AppError::WaitCondition(format!(
+                        "Network not idle for {}ms within {}ms ({} requests in flight)",
+                        idle.as_millis(),
+                        limit.as_millis(),
+                        state.in_flight.len()
+                    )));
+                }
+            }
+            std::thread::sleep(Duration::from_millis(WAIT_POLL_INTERVAL_MS));
+        }
+    }
+}
+
+/// `requested_ms`, cut short so it ends by `deadline`
+fn within(deadline: Instant, requested_ms: u64) -> Duration {
+    Duration::from_millis(requested_ms).min(deadline.saturating_duration_since(Instant::now()))
+}
+
 impl Drop for TabGuard {
     fn drop(&mut self) {
         let tab = self.tab.clone();
@@ -63,25 +323,37 @@ impl Drop for TabGuard {
 }
 
 impl BrowserManager {
-    pub fn new(max_concurrent_tabs: usize) -> AppResult<Self> {
-        let browser = Self::launch_browser()?;
+    pub fn new(max_concurrent_tabs: usize, url_guard: UrlGuard) -> AppResult<Self> {
+        let proxy = EgressProxy::start(url_guard.clone())?;
+        let browser = Self::launch_browser(&proxy)?;
 
         Ok(Self {
             browser: RwLock::new(Arc::new(browser)),
-            semaphore: Arc::new(Semaphore::new(max_concurrent_tabs)),
+            slots: SlotScheduler::new(max_concurrent_tabs),
             idle_tabs: Arc::new(Mutex::new(Vec::new())),
             max_concurrent_tabs,
+            url_guard,
+            proxy,
         })
     }
 
-    fn launch_browser() -> AppResult<Browser> {
+    fn launch_browser(proxy: &EgressProxy) -> AppResult<Browser> {
         let sandbox = std::env::var("CHROME_NO_SANDBOX").is_err();
+        let proxy_server = format!("--proxy-server=http://{}", proxy.addr());
 
         let mut builder = LaunchOptions::default_builder();
         builder
             .headless(true)
             .sandbox(sandbox)
-            .idle_browser_timeout(Duration::from_secs(600));
+            .idle_browser_timeout(Duration::from_secs(600))
+            .args(vec![
+                // Keeps cross-site iframes in the page's own target, where request interception sees them
+                OsStr::new("--disable-site-isolation-trials"),
+                // Every connection, WebSockets included, goes through the pinning egress proxy
+                OsStr::new(&proxy_server),
+                OsStr::new("--proxy-bypass-list=<-loopback>"),
+                OsStr::new("--force-webrtc-ip-handling-policy=disable_non_proxied_udp"),
+            ]);
 
         if let Ok(path) = std::env::var("CHROME_PATH") {
             builder.path(Some(std::path::PathBuf::from(path)));
@@ -103,7 +375,7 @@ impl BrowserManager {
             idle_tabs.clear();
         }
 
-        let new_browser = Self::launch_browser()?;
+        let new_browser = Self::launch_browser(&self.proxy)?;
 
         {
             let mut browser = self.browser.write().await;
@@ -143,13 +415,9 @@ impl BrowserManager {
             .map_err(|e| AppError::Browser(format!("Tab creation failed after restart: {}", e)))
     }
 
-    pub async fn acquire_tab(&self) -> AppResult<TabGuard> {
-        let permit = self
-            .semaphore
-            .clone()
-            .acquire_owned()
-            .await
-            .map_err(|e| AppError::Browser(format!("Semaphore error: {}", e)))?;
+    /// Waits for a fair-share slot for `claim`, then reuses an idle tab or opens one
+    pub async fn acquire_tab(&self, claim: &SlotClaim) -> AppResult<TabGuard> {
+        let permit = self.slots.acquire(claim).await?;
 
         // Try reusing idle tab
         {
@@ -179,83 +447,157 @@ impl BrowserManager {
         })
     }
 
+    /// Acquires a tab, navigates and extracts, reporting each finished phase to `on_progress`.
+    /// Redirects of the page itself must stay within `domains`
     pub async fn scrape_page(
         &self,
         url: &str,
-        output_format: OutputFormat,
-    ) -> AppResult<(PageMetadata, String)> {
-        let tab_guard = self.acquire_tab().await?;
+        request: &ScrapeRequest,
+        domains: &DomainPolicy,
+        claim: &SlotClaim,
+        timeouts: ScrapeTimeouts,
+        on_progress: impl Fn(ScrapeProgress),
+    ) -> AppResult<ScrapedPage> {
+        let tab_guard =
+            Self::run_phase(TimeoutPhase::TabAcquisition, timeouts.tab_acquire, url, self.acquire_tab(claim))
+                .await?;
         let tab = tab_guard.tab();
+        on_progress(ScrapeProgress::TabAcquired);
 
-        let result = timeout(
-            Duration::from_secs(BROWSER_TIMEOUT_SECS),
-            self.do_scrape(tab, url, output_format),
+        Self::run_phase(
+            TimeoutPhase::Navigation,
+            timeouts.navigation,
+            url,
+            self.navigate(tab, url, domains, request.wait_for.as_ref(), timeouts.navigation),
         )
-        .await;
+        .await?;
+        on_progress(ScrapeProgress::Navigated);
+
+        Self::run_phase(
+            TimeoutPhase::Extraction,
+            timeouts.extraction,
+            url,
+            self.extract(tab, url, request),
+        )
+        .await
+    }
+
+    /// Navigates and prints the page to PDF, skipping content extraction
+    pub async fn print_page(
+        &self,
+        url: &str,
+        request: &ScrapeRequest,
+        domains: &DomainPolicy,
+        claim: &SlotClaim,
+        timeouts: ScrapeTimeouts,
+    ) -> AppResult<Vec<u8>> {
+        let tab_guard =
+            Self::run_phase(TimeoutPhase::TabAcquisition, timeouts.tab_acquire, url, self.acquire_tab(claim))
+                .await?;
+        let tab = tab_guard.tab();
+
+        Self::run_phase(
+            TimeoutPhase::Navigation,
+            timeouts.navigation,
+            url,
+            self.navigate(tab, url, domains, request.wait_for.as_ref(), timeouts.navigation),
+        )
+        .await?;
+
+        let tab_clone = tab.clone();
+        let options = request.pdf.clone().unwrap_or_default();
+        let print = async move {
+            tokio::task::spawn_blocking(move || Self::print_pdf(&tab_clone, &options))
+                .await
+                .map_err(|e| AppError::Internal(format!("Task join error: {}", e)))?
+        };
+        let bytes = Self::run_phase(TimeoutPhase::Extraction, timeouts.extraction, url, print).await?;
+
+        info!(url, len = bytes.len(), "Printed PDF");
+        Ok(bytes)
+    }
 
-        match result {
+    async fn run_phase<T>(
+        phase: TimeoutPhase,
+        limit: Duration,
+        url: &str,
+        future: impl Future<Output = AppResult<T>>,
+    ) -> AppResult<T> {
+        match timeout(limit, future).await {
             Ok(inner_result) => inner_result,
             Err(_) => {
-                error!(url, timeout = BROWSER_TIMEOUT_SECS, "Page load timeout");
-                Err(AppError::Timeout(format!(
-                    "Timeout after {}s: {}",
-                    BROWSER_TIMEOUT_SECS, url
-                )))
+                let timeout_ms = limit.as_millis();
+                error!(url, %phase, timeout_ms, "Phase timeout");
+                Err(AppError::Timeout(
+                    phase,
+                    format!("Timeout after {}ms: {}", timeout_ms, url),
+                ))
             }
         }
     }
 
-    async fn do_scrape(
+    async fn navigate(
         &self,
         tab: &Arc<Tab>,
         url: &str,
-        output_format: OutputFormat,
-    ) -> AppResult<(PageMetadata, String)> {
+        domains: &DomainPolicy,
+        wait_for: Option<&WaitFor>,
+        budget: Duration,
+    ) -> AppResult<()> {
         let tab_clone = tab.clone();
+        let domains = domains.clone();
         let url_owned = url.to_string();
+        let wait_for = wait_for.cloned().unwrap_or_default();
+        let verdicts = Arc::new(HostVerdicts::new(self.url_guard.clone()));
 
         tokio::task::spawn_blocking(move || {
-            tab_clone
-                .navigate_to(&url_owned)
-                .map_err(|e| AppError::Browser(format!("Navigation failed: {}", e)))?;
+            // The phase timeout cannot stop this thread, so every wait below stops here too
+            let deadline = Instant::now() + budget;
+            let parsed = Url::parse(&url_owned)
+                .map_err(|e| AppError::InvalidRequest(format!("Invalid URL: {}", e)))?;
+            verdicts.check(&parsed)?;
+            let blocked = RequestFirewall::install(&tab_clone, verdicts, domains)?;
 
-            tab_clone
-                .wait_until_navigated()
-                .map_err(|e| AppError::Browser(format!("Navigation wait failed: {}", e)))?;
+            // Network tracking must start before navigation to see the initial requests
+            let network = match wait_for.network_idle {
+                Some(_) => Some(NetworkListener::attach(&tab_clone)?),
+                None => None,
+            };
+
+            let navigated = tab_clone
+                .navigate_to(&url_owned)
+                .map_err(|e| AppError::Browser(format!("Navigation failed: {}", e)))
+                .and_then(|tab| {
+                    tab.wait_until_navigated()
+                        .map_err(|e| AppError::Browser(format!("Navigation wait failed: {}", e)))
+                });
+            // A blocked redirect surfaces as a generic navigation error; report why instead
+            if let Some(reason) = blocked.take() {
+                return Err(reason);
+            }
+            navigated?;
 
             tab_clone
                 .wait_for_element_with_custom_timeout("body", Duration::from_secs(5))
                 .map_err(|e| AppError::Browser(format!("Body element wait failed: {}", e)))?;
 
-            Ok::<_, AppError>(())
+            Self::apply_wait_conditions(&tab_clone, &wait_for, network.as_ref(), deadline)
         })
         .await
-        .map_err(|e| AppError::Internal(format!("Task join error: {}", e)))??;
+        .map_err(|e| AppError::Internal(format!("Task join error: {}", e)))?
+    }
 
+    async fn extract(
+        &self,
+        tab: &Arc<Tab>,
+        url: &str,
+        request: &ScrapeRequest,
+    ) -> AppResult<ScrapedPage> {
         let tab_clone = tab.clone();
-        let extract_result: PageExtractResult = tokio::task::spawn_blocking(move || {
-            let js_code = r#"
-                JSON.stringify((() => {
-                    const og = {};
-                    const metaTags = document.querySelectorAll('meta[property^="og:"]');
-                    for (let i = 0; i < metaTags.length; i++) {
-                        const tag = metaTags[i];
-                        const prop = tag.getAttribute('property');
-                        const content = tag.getAttribute('content');
-                        if (prop && content) {
-                            og[prop] = content;
-                        }
-                    }
-                    return {
-                        title: document.title || '',
-                        og_tags: og,
-                        body_html: document.body ? document.body.outerHTML : '<body></body>'
-                    };
-                })())
-            "#;
-
+        let js_code = build_extract_script(request);
+        let mut extract_result: PageExtractResult = tokio::task::spawn_blocking(move || {
             let result = tab_clone
-                .evaluate(js_code, false)
+                .evaluate(&js_code, false)
                 .map_err(|e| AppError::Browser(format!("JS evaluation failed: {}", e)))?;
 
             let json_string = result
@@ -272,39 +614,479 @@ impl BrowserManager {
         .await
         .map_err(|e| AppError::Internal(format!("Task join error: {}", e)))??;
 
+        if !extract_result.invalid_selectors.is_empty() {
+            return Err(AppError::InvalidRequest(format!(
+                "Invalid CSS selector: {}",
+                extract_result.invalid_selectors.join(", ")
+            )));
+        }
+
+        // HTML parsing and conversion are CPU-bound, so they stay off the async workers
+        let body_html = std::mem::take(&mut extract_result.body_html);
+        let og_tags = extract_result.og_tags.clone();
+        let page_url = url.to_string();
+        let (extract_mode, output_format) = (request.extract_mode, request.output_format);
+        let (content, article) = tokio::task::spawn_blocking(move || {
+            render_content(body_html, &page_url, &og_tags, extract_mode, output_format)
+        })
+        .await
+        .map_err(|e| AppError::Internal(format!("Task join error: {}", e)))??;
+        let article = article.map(|mut article| {
+            if article.published_date.is_none() {
+                article.published_date = extract_result.published_time.clone();
+            }
+            article
+        });
+
         let metadata = PageMetadata {
             title: extract_result.title,
+            description: extract_result.description,
+            canonical_url: extract_result.canonical_url,
+            language: extract_result.language,
+            published_time: extract_result.published_time,
             og_tags: extract_result.og_tags,
+            twitter_tags: extract_result.twitter_tags,
+            favicons: extract_result.favicons,
+            json_ld: extract_result.json_ld,
+            links: extract::classify_links(extract_result.links, url),
+        };
+
+        let has_selectors =
+            !request.include_selectors.is_empty() || !request.exclude_selectors.is_empty();
+        let selector_matches = has_selectors.then_some(extract_result.selector_matches);
+        let images = request.include_images.then(|| {
+            extract::merge_image_sources(
+                extract_result.images,
+                extract_result.image_sources,
+                extract_result.base_url.as_deref().unwrap_or(url),
+            )
+        });
+
+        let screenshot = match request.screenshot.clone() {
+            Some(options) => {
+                let tab_clone = tab.clone();
+                let screenshot = tokio::task::spawn_blocking(move || {
+                    Self::capture_screenshot(&tab_clone, &options)
+                })
+                .await
+                .map_err(|e| AppError::Internal(format!("Task join error: {}", e)))??;
+                Some(screenshot)
+            }
+            None => None,
         };
 
-        let content = match output_format {
-            OutputFormat::Html => extract_result.body_html,
-            OutputFormat::Markdown => htmd::convert(&extract_result.body_html)
-                .map_err(|e| AppError::Internal(format!("Markdown conversion failed: {}", e)))?,
+        let pdf = match request.pdf.clone() {
+            Some(options) => {
+                let tab_clone = tab.clone();
+                let bytes = tokio::task::spawn_blocking(move || Self::print_pdf(&tab_clone, &options))
+                    .await
+                    .map_err(|e| AppError::Internal(format!("Task join error: {}", e)))??;
+                Some(base64::engine::general_purpose::STANDARD.encode(bytes))
+            }
+            None => None,
         };
 
         info!(url, title = %metadata.title, len = content.len(), "Scraped");
 
-        Ok((metadata, content))
+        Ok(ScrapedPage {
+            metadata,
+            content,
+            selector_matches,
+            article,
+            images,
+            screenshot,
+            pdf,
+        })
+    }
+
+    /// Blocking: captures the viewport, full page or a single element as base64
+    fn capture_screenshot(tab: &Tab, options: &ScreenshotOptions) -> AppResult<Screenshot> {
+        let clip = match &options.clip_selector {
+            Some(selector) => Some(Self::element_clip(tab, selector)?),
+            None if options.full_page => {
+                let metrics = tab
+                    .call_method(Page::GetLayoutMetrics(None))
+                    .map_err(|e| AppError::Browser(format!("Layout metrics failed: {}", e)))?;
+                let size = metrics.css_content_size;
+                Some(Page::Viewport {
+                    x: 0.0,
+                    y: 0.0,
+                    width: size.width,
+                    height: size.height,
+                    scale: 1.0,
+                })
+            }
+            None => None,
+        };
+
+        let (format, quality) = match options.format {
+            ImageFormat::Png => (Page::CaptureScreenshotFormatOption::Png, None),
+            ImageFormat::Jpeg => (
+                Page::CaptureScreenshotFormatOption::Jpeg,
+                options.quality.map(|q| q.min(100)),
+            ),
+        };
+
+        let capture_beyond_viewport = clip.is_some();
+        let data = tab
+            .call_method(Page::CaptureScreenshot {
+                format: Some(format),
+                quality,
+                clip,
+                from_surface: Some(true),
+                capture_beyond_viewport: Some(capture_beyond_viewport),
+                optimize_for_speed: None,
+            })
+            .map_err(|e| AppError::Browser(format!("Screenshot failed: {}", e)))?
+            .data;
+
+        Ok(Screenshot {
+            mime_type: options.format.mime_type().to_string(),
+            data,
+        })
+    }
+
+    /// Blocking: prints the current page with the requested paper settings
+    fn print_pdf(tab: &Tab, options: &PdfOptions) -> AppResult<Vec<u8>> {
+        let (paper_width, paper_height) = options.paper_size.dimensions_in();
+        tab.print_to_pdf(Some(PrintToPdfOptions {
+            landscape: Some(options.landscape),
+            print_background: Some(options.print_background),
+            paper_width: Some(paper_width),
+            paper_height: Some(paper_height),
+            margin_top: Some(options.margins.top),
+            margin_bottom: Some(options.margins.bottom),
+            margin_left: Some(options.margins.left),
+            margin_right: Some(options.margins.right),
+            ..Default::default()
+        }))
+        .map_err(|e| AppError::Browser(format!("PDF rendering failed: {}", e)))
+    }
+
+    /// Page-relative bounding box of the first element matching `selector`
+    fn element_clip(tab: &Tab, selector: &str) -> AppResult<Page::Viewport> {
+        let selector_json = serde_json::to_string(selector)
+            .map_err(|e| AppError::Internal(format!("Selector encoding failed: {}", e)))?;
+        let js_code = format!(
+            r#"(() => {{
+                const el = document.querySelector({});
+                if (!el) return null;
+                const r = el.getBoundingClientRect();
+                return JSON.stringify({{
+                    x: r.left + window.scrollX,
+                    y: r.top + window.scrollY,
+                    width: r.width,
+                    height: r.height
+                }});
+            }})()"#,
+            selector_json
+        );
+
+        let result = tab.evaluate(&js_code, false).map_err(|e| {
+            AppError::InvalidRequest(format!("Invalid clip selector '{}': {}", selector, e))
+        })?;
+
+        let rect: ClipRect = result
+            .value
+            .as_ref()
+            .and_then(|v| v.as_str())
+            .and_then(|s| serde_json::from_str(s).ok())
+            .filter(|r: &ClipRect| r.width > 0.0 && r.height > 0.0)
+            .ok_or_else(|| {
+                AppError::InvalidRequest(format!(
+                    "Clip selector '{}' matched no visible element",
+                    selector
+                ))
+            })?;
+
+        Ok(Page::Viewport {
+            x: rect.x,
+            y: rect.y,
+            width: rect.width,
+            height: rect.height,
+            scale: 1.0,
+        })
+    }
+
+    /// Blocking: runs each configured wait condition in turn
+    fn apply_wait_conditions(
+        tab: &Tab,
+        wait_for: &WaitFor,
+        network: Option<&NetworkListener>,
+        deadline: Instant,
+    ) -> AppResult<()> {
+        if let Some(wait) = &wait_for.selector {
+            let limit = within(deadline, wait.timeout_ms);
+            tab.wait_for_element_with_custom_timeout(&wait.selector, limit)
+                .map_err(|e| {
+                    AppError::WaitCondition(format!(
+                        "Selector '{}' not found within {}ms: {}",
+                        wait.selector,
+                        limit.as_millis(),
+                        e
+                    ))
+                })?;
+        }
+
+        if let (Some(wait), Some(network)) = (&wait_for.network_idle, network) {
+            network.activity.wait_idle(
+                Duration::from_millis(wait.idle_ms),
+                within(deadline, wait.timeout_ms),
+            )?;
+        }
+
+        if let Some(wait) = &wait_for.js {
+            let script = format!("Promise.resolve(({})).then(Boolean)", wait.expression);
+            let limit = within(deadline, wait.timeout_ms);
+            let deadline = Instant::now() + limit;
+            loop {
+                let result = tab.evaluate(&script, true).map_err(|e| {
+                    AppError::WaitCondition(format!("JS predicate failed: {}", e))
+                })?;
+                if result.value.and_then(|v| v.as_bool()).unwrap_or(false) {
+                    break;
+                }
+                if Instant::now() >= deadline {
+                    return Err(AppError::WaitCondition(format!(
+                        "JS predicate not satisfied within {}ms: {}",
+                        limit.as_millis(),
+                        wait.expression
+                    )));
+                }
+                std::thread::sleep(Duration::from_millis(WAIT_POLL_INTERVAL_MS));
+            }
+        }
+
+        if let Some(delay_ms) = wait_for.delay_ms {
+            std::thread::sleep(within(deadline, delay_ms));
+        }
+
+        Ok(())
     }
 
     pub async fn stats(&self) -> BrowserStats {
         let idle_count = self.idle_tabs.lock().await.len();
-        let available_permits = self.semaphore.available_permits();
+        let slots = self.slots.stats();
 
         BrowserStats {
             max_concurrent: self.max_concurrent_tabs,
-            available_slots: available_permits,
+            available_slots: slots.capacity - slots.active,
             idle_tabs: idle_count,
-            active_tabs: self.max_concurrent_tabs - available_permits,
+            active_tabs: slots.active,
+            slots,
         }
     }
 }
 
+/// Network event subscription, detached from the (reusable) tab on drop
+struct NetworkListener {
+    tab: Arc<Tab>,
+    activity: Arc<NetworkActivity>,
+    listener: Weak<dyn EventListener<Event> + Send + Sync>,
+}
+
+impl NetworkListener {
+    fn attach(tab: &Arc<Tab>) -> AppResult<Self> {
+        let activity = Arc::new(NetworkActivity::new());
+        let tracked = activity.clone();
+        let listener = tab
+            .add_event_listener(Arc::new(move |event: &Event| tracked.on_event(event)))
+            .map_err(|e| AppError::Browser(format!("Event listener failed: {}", e)))?;
+
+        tab.call_method(Network::Enable {
+            max_total_buffer_size: None,
+            max_resource_buffer_size: None,
+            max_post_data_size: None,
+            report_direct_socket_traffic: None,
+            enable_durable_messages: None,
+        })
+        .map_err(|e| AppError::Browser(format!("Network enable failed: {}", e)))?;
+
+        Ok(Self {
+            tab: tab.clone(),
+            activity,
+            listener,
+        })
+    }
+}
+
+impl Drop for NetworkListener {
+    fn drop(&mut self) {
+        let _ = self.tab.remove_event_listener(&self.listener);
+        let _ = self.tab.call_method(Network::Disable(None));
+    }
+}
+
+/// CDP Fetch interception that fails every request the page makes to a private
+/// address: the document and its redirects, subresources and iframes. Main-frame
+/// documents, including redirects, must also pass the key's domain policy. The egress
+/// proxy enforces the same rule on the actual connection; this layer is what turns
+/// a blocked main document into a clear error instead of a generic navigation failure
+struct RequestFirewall {
+    /// Why the main document was blocked, if it was
+    blocked: Arc<StdMutex<Option<AppError>>>,
+}
+
+impl RequestFirewall {
+    /// Replaces the tab's previous interceptor, so a reused tab checks against `verdicts`
+    /// and `domains` of the current request
+    fn install(tab: &Arc<Tab>, verdicts: Arc<HostVerdicts>, domains: DomainPolicy) -> AppResult<Self> {
+        let blocked = Arc::new(StdMutex::new(None));
+        let main_frame = tab.get_target_id().clone();
+        let slot = blocked.clone();
+
+        tab.enable_request_interception(Arc::new(
+            move |_transport: Arc<Transport>, _session: SessionId, event: RequestPausedEvent| {
+                let params = event.params;
+                let url = match Url::parse(&params.request.url) {
+                    Ok(url) if matches!(url.scheme(), "http" | "https") => url,
+                    _ => return RequestPausedDecision::Continue(None),
+                };
+                let document = params.frame_id == main_frame
+                    && params.resource_Type == Network::ResourceType::Document;
+                let verdict = verdicts
+                    .check(&url)
+                    .and_then(|_| if document { domains.check(&url) } else { Ok(()) });
+                let Err(e) = verdict else {
+                    return RequestPausedDecision::Continue(None);
+                };
+
+                warn!(url = %url, error = %e, "Blocked browser request");
+                if document {
+                    *slot.lock().unwrap() = Some(e);
+                }
+                RequestPausedDecision::Fail(FailRequest {
+                    request_id: params.request_id,
+                    error_reason: Network::ErrorReason::BlockedByClient,
+                })
+            },
+        ))
+        .map_err(|e| AppError::Browser(format!("Request interception failed: {}", e)))?;
+
+        tab.enable_fetch(None, None)
+            .map_err(|e| AppError::Browser(format!("Fetch enable failed: {}", e)))?;
+
+        Ok(Self { blocked })
+    }
+
+    fn take(&self) -> Option<AppError> {
+        self.blocked.lock().unwrap().take()
+    }
+}
+
+/// Extracted page content plus extraction details
+#[derive(Debug)]
+pub struct ScrapedPage {
+    pub metadata: PageMetadata,
+    pub content: String,
+    pub selector_matches: Option<SelectorMatches>,
+    pub article: Option<ArticleInfo>,
+    pub images: Option<Vec<PageImage>>,
+    pub screenshot: Option<Screenshot>,
+    pub pdf: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ClipRect {
+    x: f64,
+    y: f64,
+    width: f64,
+    height: f64,
+}
+
+/// Narrows the page body per `extract_mode` and converts it to `output_format`
+fn render_content(
+    body_html: String,
+    page_url: &str,
+    og_tags: &HashMap<String, String>,
+    extract_mode: ExtractMode,
+    output_format: OutputFormat,
+) -> AppResult<(String, Option<ArticleInfo>)> {
+    let (body_html, article) = match extract_mode {
+        ExtractMode::Full => (body_html, None),
+        ExtractMode::MainContent => {
+            let main = extract::extract_main_content(&body_html, page_url, og_tags)?;
+            (main.html, Some(main.article))
+        }
+    };
+
+    let content = match output_format {
+        OutputFormat::Html => body_html,
+        OutputFormat::Markdown => htmd::convert(&body_html)
+            .map_err(|e| AppError::Internal(format!("Markdown conversion failed: {}", e)))?,
+        OutputFormat::Text => extract::html_to_text(&body_html),
+    };
+    Ok((content, article))
+}
+
+fn build_extract_script(request: &ScrapeRequest) -> String {
+    let options = serde_json::json!({
+        "include_selectors": request.include_selectors,
+        "exclude_selectors": request.exclude_selectors,
+        "include_images": request.include_images,
+    });
+    EXTRACT_SCRIPT.replace("__OPTIONS__", &options.to_string())
+}
+
 #[derive(Debug)]
 pub struct BrowserStats {
     pub max_concurrent: usize,
     pub available_slots: usize,
     pub idle_tabs: usize,
     pub active_tabs: usize,
+    pub slots: SchedulerStats,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_request(json: &str) -> ScrapeRequest {
+        serde_json::from_str(json).unwrap()
+    }
+
+    // ==================== within ====================
+
+    #[test]
+    fn within_keeps_short_waits() {
+        let deadline = Instant::now() + Duration::from_secs(60);
+        assert_eq!(within(deadline, 250), Duration::from_millis(250));
+    }
+
+    #[test]
+    fn within_cuts_waits_at_deadline() {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        assert!(within(deadline, 1_000_000_000_000) <= Duration::from_secs(10));
+        assert_eq!(within(Instant::now(), 5_000), Duration::ZERO);
+    }
+
+    // ==================== build_extract_script ====================
+
+    #[test]
+    fn extract_script_injects_options() {
+        let request = make_request(
+            r#"{"url": "https://example.com", "include_selectors": ["article", "main"], "exclude_selectors": [".ads"]}"#,
+        );
+        let script = build_extract_script(&request);
+        assert!(!script.contains("__OPTIONS__"));
+        assert!(script.contains(r#""include_selectors":["article","main"]"#));
+        assert!(script.contains(r#""exclude_selectors":[".ads"]"#));
+        assert!(script.contains(r#""include_images":false"#));
+    }
+
+    #[test]
+    fn extract_script_include_images() {
+        let request = make_request(r#"{"url": "https://example.com", "include_images": true}"#);
+        let script = build_extract_script(&request);
+        assert!(script.contains(r#""include_images":true"#));
+    }
+
+    #[test]
+    fn extract_script_escapes_selectors() {
+        let request = make_request(
+            r#"{"url": "https://example.com", "exclude_selectors": ["a[href=\"x\"]"]}"#,
+        );
+        let script = build_extract_script(&request);
+        assert!(script.contains(r#"["a[href=\"x\"]"]"#));
+    }
 }
@@ -0,0 +1,160 @@
+use crate::error::{AppError, AppResult};
+use std::time::Duration;
+
+const DEFAULT_NAVIGATION_TIMEOUT_MS: u64 = 10_000;
+const DEFAULT_MAX_NAVIGATION_TIMEOUT_MS: u64 = 60_000;
+const DEFAULT_ANALYSIS_TIMEOUT_MS: u64 = 60_000;
+const DEFAULT_MAX_ANALYSIS_TIMEOUT_MS: u64 = 300_000;
+const DEFAULT_TAB_ACQUIRE_TIMEOUT_MS: u64 = 30_000;
+const EXTRACTION_TIMEOUT_MS: u64 = 10_000;
+/// Smallest per-request navigation or analysis timeout; shorter ones fail before any work starts
+const MIN_REQUEST_TIMEOUT_MS: u64 = 1_000;
+
+/// Server-side timeout defaults and caps for per-request overrides
+#[derive(Debug, Clone)]
+pub struct TimeoutConfig {
+    pub tab_acquire: Duration,
+    pub extraction: Duration,
+    pub default_navigation: Duration,
+    pub max_navigation: Duration,
+    pub default_analysis: Duration,
+    pub max_analysis: Duration,
+}
+
+/// Effective browser-side timeouts for a single scrape
+#[derive(Debug, Clone, Copy)]
+pub struct ScrapeTimeouts {
+    pub tab_acquire: Duration,
+    pub navigation: Duration,
+    pub extraction: Duration,
+}
+
+impl TimeoutConfig {
+    pub fn from_env() -> Self {
+        Self {
+            tab_acquire: env_millis("TAB_ACQUIRE_TIMEOUT_MS", DEFAULT_TAB_ACQUIRE_TIMEOUT_MS),
+            extraction: Duration::from_millis(EXTRACTION_TIMEOUT_MS),
+            default_navigation: Duration::from_millis(DEFAULT_NAVIGATION_TIMEOUT_MS),
+            max_navigation: env_millis("MAX_NAVIGATION_TIMEOUT_MS", DEFAULT_MAX_NAVIGATION_TIMEOUT_MS),
+            default_analysis: Duration::from_millis(DEFAULT_ANALYSIS_TIMEOUT_MS),
+            max_analysis: env_millis("MAX_ANALYSIS_TIMEOUT_MS", DEFAULT_MAX_ANALYSIS_TIMEOUT_MS),
+        }
+    }
+
+    pub fn scrape(&self, navigation_timeout_ms: Option<u64>) -> AppResult<ScrapeTimeouts> {
+        Ok(ScrapeTimeouts {
+            tab_acquire: self.tab_acquire,
+            navigation: capped(
+                "navigation_timeout_ms",
+                navigation_timeout_ms,
+                self.default_navigation,
+                self.max_navigation,
+            )?,
+            extraction: self.extraction,
+        })
+    }
+
+    pub fn analysis(&self, analysis_timeout_ms: Option<u64>) -> AppResult<Duration> {
+        capped("analysis_timeout_ms", analysis_timeout_ms, self.default_analysis, self.max_analysis)
+    }
+}
+
+fn capped(field: &str, requested_ms: Option<u64>, default: Duration, max: Duration) -> AppResult<Duration> {
+    if let Some(ms) = requested_ms.filter(|&ms| ms < MIN_REQUEST_TIMEOUT_MS) {
+        return Err(AppError::InvalidRequest(format!(
+            "{} must be at least {} ms, got {}",
+            field, MIN_REQUEST_TIMEOUT_MS, ms
+        )));
+    }
+    Ok(requested_ms
+        .map(Duration::from_millis)
+        .unwrap_or(default)
+        .min(max))
+}
+
+fn env_millis(name: &str, default_ms: u64) -> Duration {
+    let ms = std::env::var(name)
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(default_ms);
+    Duration::from_millis(ms)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_config() -> TimeoutConfig {
+        TimeoutConfig {
+            tab_acquire: Duration::from_secs(30),
+            extraction: Duration::from_secs(10),
+            default_navigation: Duration::from_secs(10),
+            max_navigation: Duration::from_secs(60),
+            default_analysis: Duration::from_secs(60),
+            max_analysis: Duration::from_secs(300),
+        }
+    }
+
+    // ==================== navigation ====================
+
+    #[test]
+    fn navigation_uses_default() {
+        let timeouts = make_config().scrape(None).unwrap();
+        assert_eq!(timeouts.navigation, Duration::from_secs(10));
+    }
+
+    #[test]
+    fn navigation_uses_requested() {
+        let timeouts = make_config().scrape(Some(2_500)).unwrap();
+        assert_eq!(timeouts.navigation, Duration::from_millis(2_500));
+    }
+
+    #[test]
+    fn navigation_capped_by_max() {
+        let timeouts = make_config().scrape(Some(120_000)).unwrap();
+        assert_eq!(timeouts.navigation, Duration::from_secs(60));
+    }
+
+    #[test]
+    fn navigation_default_capped_by_max() {
+        let config = TimeoutConfig {
+            max_navigation: Duration::from_secs(5),
+            ..make_config()
+        };
+        assert_eq!(config.scrape(None).unwrap().navigation, Duration::from_secs(5));
+    }
+
+    #[test]
+    fn navigation_below_minimum_rejected() {
+        for ms in [0, 999] {
+            match make_config().scrape(Some(ms)) {
+                Err(AppError::InvalidRequest(message)) => assert!(message.contains("navigation_timeout_ms")),
+                other => panic!("expected InvalidRequest, got {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn scrape_keeps_server_side_phases() {
+        let timeouts = make_config().scrape(Some(1_000)).unwrap();
+        assert_eq!(timeouts.tab_acquire, Duration::from_secs(30));
+        assert_eq!(timeouts.extraction, Duration::from_secs(10));
+    }
+
+    // ==================== analysis ====================
+
+    #[test]
+    fn analysis_uses_default() {
+        assert_eq!(make_config().analysis(None).unwrap(), Duration::from_secs(60));
+    }
+
+    #[test]
+    fn analysis_capped_by_max() {
+        assert_eq!(make_config().analysis(Some(600_000)).unwrap(), Duration::from_secs(300));
+    }
+
+    #[test]
+    fn analysis_below_minimum_rejected() {
+        assert!(matches!(make_config().analysis(Some(0)), Err(AppError::InvalidRequest(_))));
+    }
+}
@@ -0,0 +1,354 @@
+use std::collections::{HashSet, VecDeque};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tokio::task::JoinSet;
+use tracing::{info, warn};
+use url::Url;
+
+use crate::auth::ApiKeyRef;
+use crate::error::AppError;
+use crate::handlers::{run_scrape, AppState};
+use crate::models::{CrawlEvent, CrawlRequest, ScrapeData, ScrapeResponse};
+use crate::robots::CrawlPacer;
+
+/// Pages scraped in parallel per crawl; the browser slot scheduler still bounds the total
+const CRAWL_CONCURRENCY: usize = 4;
+
+/// Which discovered links a crawl may follow
+pub struct CrawlScope {
+    origin: url::Origin,
+    include: Vec<String>,
+    exclude: Vec<String>,
+}
+
+impl CrawlScope {
+    pub fn new(seed: &Url, include: Vec<String>, exclude: Vec<String>) -> Self {
+        Self {
+            origin: seed.origin(),
+            include,
+            exclude,
+        }
+    }
+
+    pub fn allows(&self, url: &Url) -> bool {
+        if url.origin() != self.origin {
+            return false;
+        }
+        if self.exclude.iter().any(|p| pattern_matches(p, url)) {
+            return false;
+        }
+        self.include.is_empty() || self.include.iter().any(|p| pattern_matches(p, url))
+    }
+}
+
+/// Dedup key: no fragment, sorted query pairs, no empty query
+pub fn normalize_url(url: &Url) -> String {
+    let mut normalized = url.clone();
+    normalized.set_fragment(None);
+
+    let mut pairs: Vec<(String, String)> = url
+        .query_pairs()
+        .map(|(k, v)| (k.into_owned(), v.into_owned()))
+        .collect();
+    if pairs.is_empty() {
+        normalized.set_query(None);
+    } else {
+        pairs.sort();
+        normalized.query_pairs_mut().clear().extend_pairs(pairs);
+    }
+
+    normalized.to_string()
+}
+
+fn pattern_matches(pattern: &str, url: &Url) -> bool {
+    if pattern.starts_with('/') {
+        let target = match url.query() {
+            Some(query) => format!("{}?{}", url.path(), query),
+            None => url.path().to_string(),
+        };
+        glob_match(pattern, &target)
+    } else {
+        glob_match(pattern, url.as_str())
+    }
+}
+
+/// `*` matches any run of characters, `?` exactly one
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if let Some((star, matched)) = backtrack {
+            p = star + 1;
+            t = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// In-scope, not yet seen links of a scraped page
+fn discover_links(
+    data: &ScrapeData,
+    scope: &CrawlScope,
+    seen: &mut HashSet<String>,
+) -> Vec<Url> {
+    data.metadata
+        .links
+        .iter()
+        .filter(|link| link.internal)
+        .filter_map(|link| Url::parse(&link.url).ok())
+        .map(|mut url| {
+            url.set_fragment(None);
+            url
+        })
+        .filter(|url| scope.allows(url))
+        .filter(|url| seen.insert(normalize_url(url)))
+        .collect()
+}
+
+/// Breadth-first crawl from the seed, sending each page as soon as it finishes.
+/// Stops early when the receiver is dropped (client disconnected).
+pub async fn crawl(
+    state: Arc<AppState>,
+    key: ApiKeyRef,
+    seed: Url,
+    crawl_request: CrawlRequest,
+    max_pages: usize,
+    events: mpsc::Sender<CrawlEvent>,
+) {
+    let CrawlRequest {
+        request: template,
+        max_depth,
+        include_patterns,
+        exclude_patterns,
+        ..
+    } = crawl_request;
+    let template = Arc::new(template);
+    let scope = CrawlScope::new(&seed, include_patterns, exclude_patterns);
+    let pacer = Arc::new(CrawlPacer::new());
+
+    let mut seen = HashSet::from([normalize_url(&seed)]);
+    let mut frontier = VecDeque::from([(seed, 0u32)]);
+    let mut in_flight = JoinSet::new();
+    let mut scheduled = 0;
+    let mut failed = 0;
+    let mut rate_limited = false;
+
+    loop {
+        while in_flight.len() < CRAWL_CONCURRENCY && scheduled < max_pages && !rate_limited {
+            let Some((url, depth)) = frontier.pop_front() else {
+                break;
+            };
+            scheduled += 1;
+
+            let state = state.clone();
+            let key = key.clone();
+            let pacer = pacer.clone();
+            let mut request = (*template).clone();
+            request.url = url.to_string();
+            in_flight.spawn(async move {
+                let result = run_scrape(&state, &key, &request, Some(&pacer)).await;
+                (request.url, depth, result)
+            });
+        }
+
+        let Some(joined) = in_flight.join_next().await else {
+            break;
+        };
+        let (url, depth, result) = match joined {
+            Ok(page) => page,
+            Err(e) => {
+                warn!(error = %e, "Crawl task panicked");
+                failed += 1;
+                continue;
+            }
+        };
+
+        let result = match result {
+            Ok(data) => {
+                if depth < max_depth {
+                    let links = discover_links(&data, &scope, &mut seen);
+                    frontier.extend(links.into_iter().map(|link| (link, depth + 1)));
+                }
+                ScrapeResponse::success(data)
+            }
+            Err(e) => {
+                warn!(url = %url, error = %e, "Crawl page failed");
+                failed += 1;
+                // Every further page would be rejected too
+                if matches!(e, AppError::RateLimited(..)) {
+                    rate_limited = true;
+                }
+                ScrapeResponse::error(e.code(), &e.to_string())
+            }
+        };
+
+        let event = CrawlEvent::Page {
+            url,
+            depth,
+            result: Box::new(result),
+        };
+        if events.send(event).await.is_err() {
+            info!("Crawl client disconnected");
+            in_flight.abort_all();
+            return;
+        }
+    }
+
+    info!(pages = scheduled, failed, "Crawl finished");
+    let _ = events
+        .send(CrawlEvent::Done {
+            pages: scheduled,
+            failed,
+        })
+        .await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{PageLink, PageMetadata};
+
+    fn url(s: &str) -> Url {
+        Url::parse(s).unwrap()
+    }
+
+    fn make_data(links: &[(&str, bool)]) -> ScrapeData {
+        ScrapeData {
+            metadata: PageMetadata {
+                links: links
+                    .iter()
+                    .map(|(u, internal)| PageLink {
+                        url: u.to_string(),
+                        text: String::new(),
+                        rel: vec![],
+                        internal: *internal,
+                    })
+                    .collect(),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    // ==================== glob_match ====================
+
+    #[test]
+    fn glob_literal() {
+        assert!(glob_match("/docs", "/docs"));
+        assert!(!glob_match("/docs", "/docs/intro"));
+    }
+
+    #[test]
+    fn glob_star() {
+        assert!(glob_match("/docs/*", "/docs/intro"));
+        assert!(glob_match("/docs/*", "/docs/"));
+        assert!(glob_match("*.pdf", "https://example.com/a/b.pdf"));
+        assert!(!glob_match("*.pdf", "https://example.com/a/b.pdfx"));
+    }
+
+    #[test]
+    fn glob_star_backtracks() {
+        assert!(glob_match("*/blog/*/comments", "/en/blog/2024/post/comments"));
+        assert!(!glob_match("*/blog/*/comments", "/en/blog/2024/post"));
+    }
+
+    #[test]
+    fn glob_question_mark() {
+        assert!(glob_match("/v?/api", "/v2/api"));
+        assert!(!glob_match("/v?/api", "/v10/api"));
+    }
+
+    // ==================== normalize_url ====================
+
+    #[test]
+    fn normalize_drops_fragment() {
+        assert_eq!(
+            normalize_url(&url("https://example.com/a#section")),
+            "https://example.com/a"
+        );
+    }
+
+    #[test]
+    fn normalize_sorts_query() {
+        assert_eq!(
+            normalize_url(&url("https://example.com/a?b=2&a=1")),
+            normalize_url(&url("https://example.com/a?a=1&b=2"))
+        );
+    }
+
+    #[test]
+    fn normalize_drops_empty_query_and_default_port() {
+        assert_eq!(
+            normalize_url(&url("HTTPS://Example.com:443/a?")),
+            "https://example.com/a"
+        );
+    }
+
+    // ==================== CrawlScope ====================
+
+    #[test]
+    fn scope_same_origin_only() {
+        let scope = CrawlScope::new(&url("https://example.com/"), vec![], vec![]);
+        assert!(scope.allows(&url("https://example.com/about")));
+        assert!(!scope.allows(&url("http://example.com/about")));
+        assert!(!scope.allows(&url("https://blog.example.com/")));
+        assert!(!scope.allows(&url("https://example.com:8443/")));
+    }
+
+    #[test]
+    fn scope_include_and_exclude() {
+        let scope = CrawlScope::new(
+            &url("https://example.com/docs"),
+            vec!["/docs/*".to_string()],
+            vec!["/docs/archive/*".to_string()],
+        );
+        assert!(scope.allows(&url("https://example.com/docs/intro")));
+        assert!(!scope.allows(&url("https://example.com/blog/post")));
+        assert!(!scope.allows(&url("https://example.com/docs/archive/old")));
+    }
+
+    #[test]
+    fn scope_path_pattern_sees_query() {
+        let scope = CrawlScope::new(
+            &url("https://example.com/"),
+            vec![],
+            vec!["*?page=*".to_string(), "/search?*".to_string()],
+        );
+        assert!(!scope.allows(&url("https://example.com/search?q=rust")));
+        assert!(!scope.allows(&url("https://example.com/list?page=2")));
+        assert!(scope.allows(&url("https://example.com/list")));
+    }
+
+    // ==================== discover_links ====================
+
+    #[test]
+    fn discover_links_filters_and_dedups() {
+        let scope = CrawlScope::new(&url("https://example.com/"), vec![], vec![]);
+        let mut seen = HashSet::from([normalize_url(&url("https://example.com/"))]);
+        let data = make_data(&[
+            ("https://example.com/", true),
+            ("https://example.com/a#top", true),
+            ("https://example.com/a", true),
+            ("https://other.com/", false),
+            ("http://example.com/b", true),
+        ]);
+
+        let links = discover_links(&data, &scope, &mut seen);
+        assert_eq!(links, vec![url("https://example.com/a")]);
+        assert!(discover_links(&data, &scope, &mut seen).is_empty());
+    }
+}
@@ -0,0 +1,242 @@
+use regex::Regex;
+use serde::Deserialize;
+use std::fmt;
+use url::Url;
+
+use crate::error::{AppError, AppResult};
+
+static UNRESTRICTED: DomainPolicy = DomainPolicy {
+    allow: Vec::new(),
+    deny: Vec::new(),
+    max_url_length: None,
+};
+
+/// One host pattern from a key's `domains` config
+#[derive(Debug, Clone, Deserialize)]
+#[serde(try_from = "String")]
+pub enum HostRule {
+    /// `example.com`: that host only
+    Exact(String),
+    /// `*.example.com`: any subdomain, not `example.com` itself
+    Subdomain(String),
+    /// `regex:<pattern>`: must match the whole host
+    Regex(Regex),
+}
+
+impl HostRule {
+    fn matches(&self, host: &str) -> bool {
+        match self {
+            HostRule::Exact(name) => host == name,
+            HostRule::Subdomain(parent) => host
+                .strip_suffix(parent.as_str())
+                .is_some_and(|prefix| prefix.len() > 1 && prefix.ends_with('.')),
+            HostRule::Regex(regex) => regex.is_match(host),
+        }
+    }
+}
+
+impl TryFrom<String> for HostRule {
+    type Error = String;
+
+    fn try_from(rule: String) -> Result<Self, Self::Error> {
+        if let Some(pattern) = rule.strip_prefix("regex:") {
+            return Regex::new(&format!("^(?:{})$", pattern))
+                .map(HostRule::Regex)
+                .map_err(|e| format!("invalid host regex `{}`: {}", pattern, e));
+        }
+
+        let rule = normalize_host(&rule);
+        if let Some(parent) = rule.strip_prefix("*.") {
+            if parent.is_empty() || parent.contains('*') {
+                return Err(format!("invalid wildcard rule `{}`", rule));
+            }
+            return Ok(HostRule::Subdomain(parent.to_string()));
+        }
+        if rule.is_empty() || rule.contains('*') {
+            return Err(format!("invalid host rule `{}`; wildcards must be a leading `*.`", rule));
+        }
+        Ok(HostRule::Exact(rule))
+    }
+}
+
+/// Shown in error messages in the same form as the config
+impl fmt::Display for HostRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HostRule::Exact(name) => f.write_str(name),
+            HostRule::Subdomain(parent) => write!(f, "*.{}", parent),
+            HostRule::Regex(regex) => {
+                let pattern = regex.as_str();
+                let inner = pattern
+                    .strip_prefix("^(?:")
+                    .and_then(|p| p.strip_suffix(")$"))
+                    .unwrap_or(pattern);
+                write!(f, "regex:{}", inner)
+            }
+        }
+    }
+}
+
+/// Which hosts a key may scrape. Deny rules win; a non-empty allow list rejects
+/// every host it does not match.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct DomainPolicy {
+    #[serde(default)]
+    pub allow: Vec<HostRule>,
+    #[serde(default)]
+    pub deny: Vec<HostRule>,
+    /// Longest accepted URL, in bytes after normalization
+    #[serde(default)]
+    pub max_url_length: Option<usize>,
+}
+
+impl DomainPolicy {
+    /// Policy for URLs that are not scraped on a key's behalf, e.g. webhook callbacks
+    pub fn unrestricted() -> &'static DomainPolicy {
+        &UNRESTRICTED
+    }
+
+    /// Rejects `url` naming the rule that decided it
+    pub fn check(&self, url: &Url) -> AppResult<()> {
+        if let Some(max) = self.max_url_length {
+            let len = url.as_str().len();
+            if len > max {
+                return Err(AppError::InvalidRequest(format!(
+                    "URL is {} characters long, over the max_url_length rule ({})",
+                    len, max
+                )));
+            }
+        }
+
+        if self.allow.is_empty() && self.deny.is_empty() {
+            return Ok(());
+        }
+        let host = normalize_host(url.host_str().unwrap_or_default());
+
+        if let Some(rule) = self.deny.iter().find(|rule| rule.matches(&host)) {
+            return Err(AppError::InvalidRequest(format!(
+                "Host {} matches deny rule `{}`",
+                host, rule
+            )));
+        }
+        if !self.allow.is_empty() && !self.allow.iter().any(|rule| rule.matches(&host)) {
+            let rules: Vec<String> = self.allow.iter().map(|rule| format!("`{}`", rule)).collect();
+            return Err(AppError::InvalidRequest(format!(
+                "Host {} matches no allow rule ({})",
+                host,
+                rules.join(", ")
+            )));
+        }
+        Ok(())
+    }
+}
+
+fn normalize_host(host: &str) -> String {
+    host.trim().trim_end_matches('.').to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(json: &str) -> DomainPolicy {
+        serde_json::from_str(json).unwrap()
+    }
+
+    fn message(policy: &DomainPolicy, url: &str) -> Option<String> {
+        match policy.check(&Url::parse(url).unwrap()) {
+            Ok(()) => None,
+            Err(AppError::InvalidRequest(message)) => Some(message),
+            Err(other) => panic!("expected InvalidRequest, got {:?}", other),
+        }
+    }
+
+    // ==================== HostRule ====================
+
+    #[test]
+    fn rule_kinds() {
+        let cases = [
+            // (rule, host, matches)
+            ("example.com", "example.com", true),
+            ("Example.COM.", "example.com", true),
+            ("example.com", "www.example.com", false),
+            ("*.example.com", "www.example.com", true),
+            ("*.example.com", "a.b.example.com", true),
+            ("*.example.com", "example.com", false),
+            ("*.example.com", "badexample.com", false),
+            ("regex:docs\\.example\\.(com|org)", "docs.example.org", true),
+            ("regex:docs\\.example\\.(com|org)", "docs.example.org.evil.com", false),
+            ("regex:[a-z]+-\\d+\\.cdn\\.net", "img-42.cdn.net", true),
+        ];
+        for (rule, host, matches) in cases {
+            let parsed = HostRule::try_from(rule.to_string()).unwrap();
+            assert_eq!(parsed.matches(host), matches, "{} vs {}", rule, host);
+        }
+    }
+
+    #[test]
+    fn invalid_rules_rejected() {
+        for rule in ["", "*", "*.", "foo.*.com", "regex:("] {
+            assert!(HostRule::try_from(rule.to_string()).is_err(), "{}", rule);
+        }
+    }
+
+    #[test]
+    fn rule_display_round_trips() {
+        for rule in ["example.com", "*.example.com", "regex:[a-z]+\\.io"] {
+            assert_eq!(HostRule::try_from(rule.to_string()).unwrap().to_string(), rule);
+        }
+    }
+
+    // ==================== DomainPolicy ====================
+
+    #[test]
+    fn unrestricted_by_default() {
+        assert!(message(&DomainPolicy::default(), "https://anything.example").is_none());
+        assert!(message(DomainPolicy::unrestricted(), "https://anything.example").is_none());
+    }
+
+    #[test]
+    fn allow_list_names_rules() {
+        let policy = policy(r#"{"allow": ["acme.com", "*.acme.com"]}"#);
+        assert!(message(&policy, "https://acme.com/pricing").is_none());
+        assert!(message(&policy, "https://blog.acme.com/").is_none());
+        assert_eq!(
+            message(&policy, "https://example.org/").unwrap(),
+            "Host example.org matches no allow rule (`acme.com`, `*.acme.com`)"
+        );
+    }
+
+    #[test]
+    fn deny_wins_over_allow() {
+        let policy = policy(r#"{"allow": ["*.acme.com"], "deny": ["regex:(admin|internal)\\..*"]}"#);
+        assert!(message(&policy, "https://shop.acme.com/").is_none());
+        assert_eq!(
+            message(&policy, "https://admin.acme.com/").unwrap(),
+            "Host admin.acme.com matches deny rule `regex:(admin|internal)\\..*`"
+        );
+    }
+
+    #[test]
+    fn deny_only_policy() {
+        let policy = policy(r#"{"deny": ["*.example.net"]}"#);
+        assert!(message(&policy, "https://example.com/").is_none());
+        assert!(message(&policy, "https://WWW.Example.NET/").unwrap().contains("`*.example.net`"));
+    }
+
+    #[test]
+    fn max_url_length_rule() {
+        let policy = policy(r#"{"max_url_length": 30}"#);
+        assert!(message(&policy, "https://example.com/short").is_none());
+        assert_eq!(
+            message(&policy, "https://example.com/a/much/longer/path").unwrap(),
+            "URL is 38 characters long, over the max_url_length rule (30)"
+        );
+    }
+
+    #[test]
+    fn bad_rule_fails_to_parse() {
+        let err = serde_json::from_str::<DomainPolicy>(r#"{"allow": ["regex:("]}"#).unwrap_err();
+        assert!(err.to_string().contains("invalid host regex"));
+    }
+}
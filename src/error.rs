@@ -1,32 +1,76 @@
 use axum::{
-    http::StatusCode,
+    http::{header, HeaderValue, StatusCode},
     response::{IntoResponse, Response},
     Json,
 };
+use std::fmt;
 use thiserror::Error;
 
-use crate::models::ScrapeResponse;
+use crate::models::{AnalysisError, SchemaViolation, ScrapeResponse};
+
+/// Pipeline phase that ran out of time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeoutPhase {
+    TabAcquisition,
+    Navigation,
+    Extraction,
+    Analysis,
+}
+
+impl fmt::Display for TimeoutPhase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            TimeoutPhase::TabAcquisition => "tab acquisition",
+            TimeoutPhase::Navigation => "navigation",
+            TimeoutPhase::Extraction => "extraction",
+            TimeoutPhase::Analysis => "analysis",
+        };
+        f.write_str(name)
+    }
+}
 
 #[derive(Debug, Error)]
 pub enum AppError {
-    #[error("Timeout exceeded: {0}")]
-    Timeout(String),
+    #[error("Timeout exceeded during {0}: {1}")]
+    Timeout(TimeoutPhase, String),
 
     #[error("Browser error: {0}")]
     Browser(String),
 
+    #[error("Wait condition failed: {0}")]
+    WaitCondition(String),
+
     #[error("Unauthorized: Invalid or missing API key")]
     Unauthorized,
 
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
+
+    /// Message and seconds until the call would be allowed
+    #[error("Rate limit exceeded: {0} (retry after {1}s)")]
+    RateLimited(String, u64),
+
     #[error("Gemini API key not configured")]
     GeminiKeyNotConfigured,
 
+    #[error("LLM provider not configured: {0}")]
+    LlmProviderNotConfigured(String),
+
     #[error("LLM provider error: {0}")]
     LlmProvider(String),
 
+    #[error("LLM output does not match response_schema ({} violation(s))", .0.len())]
+    SchemaValidation(Vec<SchemaViolation>),
+
     #[error("Invalid request: {0}")]
     InvalidRequest(String),
 
+    #[error("Not found: {0}")]
+    NotFound(String),
+
+    #[error("Disallowed by robots.txt: {0}")]
+    RobotsDisallowed(String),
+
     #[error("Internal error: {0}")]
     Internal(String),
 }
@@ -34,24 +78,38 @@ pub enum AppError {
 impl AppError {
     pub fn code(&self) -> &'static str {
         match self {
-            AppError::Timeout(_) => "TIMEOUT_EXCEEDED",
+            AppError::Timeout(..) => "TIMEOUT_EXCEEDED",
             AppError::Browser(_) => "BROWSER_ERROR",
+            AppError::WaitCondition(_) => "WAIT_CONDITION_FAILED",
             AppError::Unauthorized => "UNAUTHORIZED",
+            AppError::Forbidden(_) => "FORBIDDEN",
+            AppError::RateLimited(..) => "RATE_LIMITED",
             AppError::GeminiKeyNotConfigured => "GEMINI_KEY_NOT_CONFIGURED",
+            AppError::LlmProviderNotConfigured(_) => "LLM_PROVIDER_NOT_CONFIGURED",
             AppError::LlmProvider(_) => "LLM_PROVIDER_ERROR",
+            AppError::SchemaValidation(_) => "SCHEMA_VALIDATION_FAILED",
             AppError::InvalidRequest(_) => "INVALID_REQUEST",
+            AppError::NotFound(_) => "NOT_FOUND",
+            AppError::RobotsDisallowed(_) => "ROBOTS_DISALLOWED",
             AppError::Internal(_) => "INTERNAL_ERROR",
         }
     }
 
     pub fn status_code(&self) -> StatusCode {
         match self {
-            AppError::Timeout(_) => StatusCode::REQUEST_TIMEOUT,
+            AppError::Timeout(..) => StatusCode::REQUEST_TIMEOUT,
             AppError::Browser(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            AppError::WaitCondition(_) => StatusCode::UNPROCESSABLE_ENTITY,
             AppError::Unauthorized => StatusCode::UNAUTHORIZED,
+            AppError::Forbidden(_) => StatusCode::FORBIDDEN,
+            AppError::RateLimited(..) => StatusCode::TOO_MANY_REQUESTS,
             AppError::GeminiKeyNotConfigured => StatusCode::SERVICE_UNAVAILABLE,
+            AppError::LlmProviderNotConfigured(_) => StatusCode::SERVICE_UNAVAILABLE,
             AppError::LlmProvider(_) => StatusCode::BAD_GATEWAY,
+            AppError::SchemaValidation(_) => StatusCode::BAD_GATEWAY,
             AppError::InvalidRequest(_) => StatusCode::BAD_REQUEST,
+            AppError::NotFound(_) => StatusCode::NOT_FOUND,
+            AppError::RobotsDisallowed(_) => StatusCode::FORBIDDEN,
             AppError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
@@ -61,7 +119,27 @@ impl IntoResponse for AppError {
     fn into_response(self) -> Response {
         let status = self.status_code();
         let response = ScrapeResponse::error(self.code(), &self.to_string());
-        (status, Json(response)).into_response()
+        let mut http_response = (status, Json(response)).into_response();
+        if let AppError::RateLimited(_, retry_after) = self {
+            http_response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, HeaderValue::from(retry_after));
+        }
+        http_response
+    }
+}
+
+impl From<&AppError> for AnalysisError {
+    fn from(error: &AppError) -> Self {
+        let validation_errors = match error {
+            AppError::SchemaValidation(violations) => violations.clone(),
+            _ => Vec::new(),
+        };
+        AnalysisError {
+            code: error.code().to_string(),
+            message: error.to_string(),
+            validation_errors,
+        }
     }
 }
 
@@ -75,7 +153,7 @@ mod tests {
 
     #[test]
     fn error_code_timeout() {
-        assert_eq!(AppError::Timeout("test".to_string()).code(), "TIMEOUT_EXCEEDED");
+        assert_eq!(AppError::Timeout(TimeoutPhase::Navigation, "test".to_string()).code(), "TIMEOUT_EXCEEDED");
     }
 
     #[test]
@@ -83,26 +161,61 @@ mod tests {
         assert_eq!(AppError::Browser("test".to_string()).code(), "BROWSER_ERROR");
     }
 
+    #[test]
+    fn error_code_wait_condition() {
+        assert_eq!(AppError::WaitCondition("test".to_string()).code(), "WAIT_CONDITION_FAILED");
+    }
+
     #[test]
     fn error_code_unauthorized() {
         assert_eq!(AppError::Unauthorized.code(), "UNAUTHORIZED");
     }
 
+    #[test]
+    fn error_code_forbidden() {
+        assert_eq!(AppError::Forbidden("test".to_string()).code(), "FORBIDDEN");
+    }
+
+    #[test]
+    fn error_code_rate_limited() {
+        assert_eq!(AppError::RateLimited("test".to_string(), 5).code(), "RATE_LIMITED");
+    }
+
     #[test]
     fn error_code_gemini_not_configured() {
         assert_eq!(AppError::GeminiKeyNotConfigured.code(), "GEMINI_KEY_NOT_CONFIGURED");
     }
 
+    #[test]
+    fn error_code_llm_provider_not_configured() {
+        assert_eq!(AppError::LlmProviderNotConfigured("openai".to_string()).code(), "LLM_PROVIDER_NOT_CONFIGURED");
+    }
+
     #[test]
     fn error_code_llm_provider() {
         assert_eq!(AppError::LlmProvider("test".to_string()).code(), "LLM_PROVIDER_ERROR");
     }
 
+    #[test]
+    fn error_code_schema_validation() {
+        assert_eq!(AppError::SchemaValidation(Vec::new()).code(), "SCHEMA_VALIDATION_FAILED");
+    }
+
     #[test]
     fn error_code_invalid_request() {
         assert_eq!(AppError::InvalidRequest("test".to_string()).code(), "INVALID_REQUEST");
     }
 
+    #[test]
+    fn error_code_not_found() {
+        assert_eq!(AppError::NotFound("test".to_string()).code(), "NOT_FOUND");
+    }
+
+    #[test]
+    fn error_code_robots_disallowed() {
+        assert_eq!(AppError::RobotsDisallowed("test".to_string()).code(), "ROBOTS_DISALLOWED");
+    }
+
     #[test]
     fn error_code_internal() {
         assert_eq!(AppError::Internal("test".to_string()).code(), "INTERNAL_ERROR");
@@ -112,7 +225,7 @@ mod tests {
 
     #[test]
     fn status_code_timeout() {
-        assert_eq!(AppError::Timeout("test".to_string()).status_code(), StatusCode::REQUEST_TIMEOUT);
+        assert_eq!(AppError::Timeout(TimeoutPhase::Navigation, "test".to_string()).status_code(), StatusCode::REQUEST_TIMEOUT);
     }
 
     #[test]
@@ -120,26 +233,61 @@ mod tests {
         assert_eq!(AppError::Browser("test".to_string()).status_code(), StatusCode::INTERNAL_SERVER_ERROR);
     }
 
+    #[test]
+    fn status_code_wait_condition() {
+        assert_eq!(AppError::WaitCondition("test".to_string()).status_code(), StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
     #[test]
     fn status_code_unauthorized() {
         assert_eq!(AppError::Unauthorized.status_code(), StatusCode::UNAUTHORIZED);
     }
 
+    #[test]
+    fn status_code_forbidden() {
+        assert_eq!(AppError::Forbidden("test".to_string()).status_code(), StatusCode::FORBIDDEN);
+    }
+
+    #[test]
+    fn status_code_rate_limited() {
+        assert_eq!(AppError::RateLimited("test".to_string(), 5).status_code(), StatusCode::TOO_MANY_REQUESTS);
+    }
+
     #[test]
     fn status_code_gemini_not_configured() {
         assert_eq!(AppError::GeminiKeyNotConfigured.status_code(), StatusCode::SERVICE_UNAVAILABLE);
     }
 
+    #[test]
+    fn status_code_llm_provider_not_configured() {
+        assert_eq!(AppError::LlmProviderNotConfigured("openai".to_string()).status_code(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+
     #[test]
     fn status_code_llm_provider() {
         assert_eq!(AppError::LlmProvider("test".to_string()).status_code(), StatusCode::BAD_GATEWAY);
     }
 
+    #[test]
+    fn status_code_schema_validation() {
+        assert_eq!(AppError::SchemaValidation(Vec::new()).status_code(), StatusCode::BAD_GATEWAY);
+    }
+
     #[test]
     fn status_code_invalid_request() {
         assert_eq!(AppError::InvalidRequest("test".to_string()).status_code(), StatusCode::BAD_REQUEST);
     }
 
+    #[test]
+    fn status_code_not_found() {
+        assert_eq!(AppError::NotFound("test".to_string()).status_code(), StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn status_code_robots_disallowed() {
+        assert_eq!(AppError::RobotsDisallowed("test".to_string()).status_code(), StatusCode::FORBIDDEN);
+    }
+
     #[test]
     fn status_code_internal() {
         assert_eq!(AppError::Internal("test".to_string()).status_code(), StatusCode::INTERNAL_SERVER_ERROR);
@@ -149,16 +297,36 @@ mod tests {
 
     #[test]
     fn display_timeout() {
-        let err = AppError::Timeout("10s".to_string());
+        let err = AppError::Timeout(TimeoutPhase::Navigation, "10s".to_string());
         assert!(err.to_string().contains("10s"));
     }
 
+    #[test]
+    fn display_timeout_phase() {
+        let cases = [
+            (TimeoutPhase::TabAcquisition, "tab acquisition"),
+            (TimeoutPhase::Navigation, "navigation"),
+            (TimeoutPhase::Extraction, "extraction"),
+            (TimeoutPhase::Analysis, "analysis"),
+        ];
+        for (phase, name) in cases {
+            let err = AppError::Timeout(phase, "10s".to_string());
+            assert!(err.to_string().contains(&format!("during {}", name)));
+        }
+    }
+
     #[test]
     fn display_browser() {
         let err = AppError::Browser("crash".to_string());
         assert!(err.to_string().contains("crash"));
     }
 
+    #[test]
+    fn display_wait_condition() {
+        let err = AppError::WaitCondition("selector '#app'".to_string());
+        assert!(err.to_string().contains("#app"));
+    }
+
     #[test]
     fn display_unauthorized() {
         let err = AppError::Unauthorized;
@@ -170,4 +338,38 @@ mod tests {
         let err = AppError::InvalidRequest("bad url".to_string());
         assert!(err.to_string().contains("bad url"));
     }
+
+    #[test]
+    fn rate_limited_sets_retry_after() {
+        let response = AppError::RateLimited("scrape limit".to_string(), 42).into_response();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()[header::RETRY_AFTER], "42");
+
+        let response = AppError::Unauthorized.into_response();
+        assert!(response.headers().get(header::RETRY_AFTER).is_none());
+    }
+
+    // ==================== AnalysisError ====================
+
+    #[test]
+    fn analysis_error_carries_violations() {
+        let violation = SchemaViolation {
+            path: "$.title".to_string(),
+            message: "expected string, got number".to_string(),
+        };
+        let analysis_error = AnalysisError::from(&AppError::SchemaValidation(vec![violation.clone()]));
+
+        assert_eq!(analysis_error.code, "SCHEMA_VALIDATION_FAILED");
+        assert!(analysis_error.message.contains("1 violation"));
+        assert_eq!(analysis_error.validation_errors, vec![violation]);
+    }
+
+    #[test]
+    fn analysis_error_from_other_errors() {
+        let analysis_error = AnalysisError::from(&AppError::LlmProvider("gemini: boom".to_string()));
+
+        assert_eq!(analysis_error.code, "LLM_PROVIDER_ERROR");
+        assert!(analysis_error.message.contains("boom"));
+        assert!(analysis_error.validation_errors.is_empty());
+    }
 }
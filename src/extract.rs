@@ -0,0 +1,828 @@
+use html5ever::serialize::{serialize, SerializeOpts, TraversalScope};
+use html5ever::tendril::TendrilSink;
+use html5ever::parse_document;
+use markup5ever_rcdom::{Handle, Node, NodeData, RcDom, SerializableHandle};
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+use url::Url;
+
+use crate::error::{AppError, AppResult};
+use crate::models::{ArticleInfo, PageImage, PageLink, RawImageSource, RawLink};
+
+const MIN_PARAGRAPH_LEN: usize = 25;
+const MAX_BYLINE_LEN: usize = 100;
+
+/// Elements that never hold article content
+const UNLIKELY_TAGS: &[&str] = &[
+    "script", "style", "noscript", "template", "nav", "footer", "aside", "form", "iframe",
+    "svg", "button", "select", "input", "dialog",
+];
+
+/// Structural elements exempt from class/id based removal
+const PROTECTED_TAGS: &[&str] = &["html", "body", "article", "main"];
+
+const NEGATIVE_HINTS: &[&str] = &[
+    "comment", "footer", "nav", "sidebar", "menu", "banner", "cookie", "consent", "promo",
+    "share", "social", "related", "popup", "modal", "advert", "sponsor", "breadcrumb",
+    "newsletter", "subscribe",
+];
+
+const POSITIVE_HINTS: &[&str] = &[
+    "article", "content", "main", "post", "entry", "story", "text", "blog",
+];
+
+const BLOCK_TAGS: &[&str] = &[
+    "address", "article", "aside", "blockquote", "div", "dl", "fieldset", "figure", "footer",
+    "form", "h1", "h2", "h3", "h4", "h5", "h6", "header", "hr", "li", "main", "nav", "ol", "p",
+    "pre", "section", "table", "ul",
+];
+
+/// Block elements rendered as a single line break rather than a paragraph break
+const LINE_TAGS: &[&str] = &["li", "tr", "dt", "dd", "figcaption", "caption", "summary"];
+
+/// Elements without visible text
+const INVISIBLE_TAGS: &[&str] = &[
+    "head", "title", "script", "style", "noscript", "template", "svg", "canvas", "iframe",
+    "object", "select",
+];
+
+/// Readability result: the article body plus byline details
+#[derive(Debug)]
+pub struct MainContent {
+    pub html: String,
+    pub article: ArticleInfo,
+}
+
+/// Readability-style heuristic over the page body: scores paragraph containers by text
+/// density, link density and semantic tags, and keeps only the best one
+pub fn extract_main_content(
+    html: &str,
+    page_url: &str,
+    og_tags: &HashMap<String, String>,
+) -> AppResult<MainContent> {
+    let dom = parse_html(html);
+
+    let byline = find_byline(&dom.document);
+    let published_date = find_published_date(&dom.document);
+
+    remove_unlikely(&dom.document);
+
+    let body = find_first(&dom.document, &["body"]).unwrap_or_else(|| dom.document.clone());
+    let candidate = best_candidate(&body)
+        .or_else(|| find_first(&body, &["article", "main"]))
+        .unwrap_or(body);
+
+    let lead_image = og_tags
+        .get("og:image")
+        .cloned()
+        .or_else(|| find_first(&candidate, &["img"]).and_then(|img| attr(&img, "src")))
+        .map(|src| absolutize(page_url, &src));
+
+    Ok(MainContent {
+        html: serialize_node(&candidate)?,
+        article: ArticleInfo {
+            byline,
+            published_date,
+            lead_image,
+        },
+    })
+}
+
+/// Visible text with paragraph breaks kept and all markup (including link URLs) dropped
+pub fn html_to_text(html: &str) -> String {
+    let dom = parse_html(html);
+    let mut raw = String::new();
+    write_text(&dom.document, &mut raw);
+
+    let mut text = String::new();
+    let mut blank_pending = false;
+    for line in raw.lines() {
+        let line = normalize_whitespace(line).replace(PRESERVED_SPACE, " ");
+        let line = line.trim_end();
+        if line.is_empty() {
+            blank_pending = !text.is_empty();
+            continue;
+        }
+        if !text.is_empty() {
+            text.push_str(if blank_pending { "\n\n" } else { "\n" });
+        }
+        text.push_str(line);
+        blank_pending = false;
+    }
+    text
+}
+
+/// Stands in for spaces inside `<pre>` so line normalization keeps code indentation;
+/// html5ever drops NUL from body text, so it never occurs in the page itself
+const PRESERVED_SPACE: char = '\0';
+
+/// Step of an iterative DOM walk: page HTML can nest deeper than the thread stack allows
+enum TextStep {
+    Enter(Handle, bool),
+    /// Closes an element opened with `breaks` surrounding newlines
+    Leave { breaks: usize, cell: bool },
+}
+
+/// Text nodes have their whitespace flattened, so every newline in `out` is structural
+fn write_text(root: &Handle, out: &mut String) {
+    let mut stack = vec![TextStep::Enter(root.clone(), false)];
+
+    while let Some(step) = stack.pop() {
+        let (node, preformatted) = match step {
+            TextStep::Enter(node, preformatted) => (node, preformatted),
+            TextStep::Leave { breaks, cell } => {
+                push_breaks(out, breaks);
+                if cell {
+                    out.push(' ');
+                }
+                continue;
+            }
+        };
+
+        match &node.data {
+            NodeData::Text { contents } => {
+                let contents = contents.borrow();
+                if preformatted {
+                    out.extend(contents.chars().map(|c| if matches!(c, ' ' | '\t') { PRESERVED_SPACE } else { c }));
+                } else {
+                    out.extend(contents.chars().map(|c| if c.is_whitespace() { ' ' } else { c }));
+                }
+            }
+            NodeData::Document => {
+                for child in node.children.borrow().iter().rev() {
+                    stack.push(TextStep::Enter(child.clone(), preformatted));
+                }
+            }
+            NodeData::Element { name, .. } => {
+                let tag = name.local.as_ref();
+                if INVISIBLE_TAGS.contains(&tag) {
+                    continue;
+                }
+                if tag == "br" {
+                    out.push('\n');
+                    continue;
+                }
+
+                let breaks = if LINE_TAGS.contains(&tag) {
+                    1
+                } else if BLOCK_TAGS.contains(&tag) {
+                    2
+                } else {
+                    0
+                };
+
+                push_breaks(out, breaks);
+                stack.push(TextStep::Leave {
+                    breaks,
+                    cell: matches!(tag, "td" | "th"),
+                });
+                let preformatted = preformatted || tag == "pre";
+                for child in node.children.borrow().iter().rev() {
+                    stack.push(TextStep::Enter(child.clone(), preformatted));
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Ends `out` with at least `count` newlines; adjacent block boundaries merge instead of stacking
+fn push_breaks(out: &mut String, count: usize) {
+    if count == 0 {
+        return;
+    }
+    out.truncate(out.trim_end_matches(' ').len());
+    let existing = out.len() - out.trim_end_matches('\n').len();
+    for _ in existing..count {
+        out.push('\n');
+    }
+}
+
+/// Keeps http(s) anchors and flags those on the scraped host as internal
+pub fn classify_links(links: Vec<RawLink>, page_url: &str) -> Vec<PageLink> {
+    let page_host = Url::parse(page_url)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_lowercase));
+
+    links
+        .into_iter()
+        .filter_map(|link| {
+            let url = Url::parse(&link.url).ok()?;
+            if !matches!(url.scheme(), "http" | "https") {
+                return None;
+            }
+            let internal = url.host_str().map(str::to_lowercase) == page_host;
+            Some(PageLink {
+                url: url.to_string(),
+                text: link.text,
+                rel: link.rel,
+                internal,
+            })
+        })
+        .collect()
+}
+
+/// Appends each `<picture>` candidate not already listed; their dimensions are unknown
+/// because the browser only loads the candidate it picks
+pub fn merge_image_sources(
+    mut images: Vec<PageImage>,
+    sources: Vec<RawImageSource>,
+    base_url: &str,
+) -> Vec<PageImage> {
+    let Ok(base) = Url::parse(base_url) else {
+        return images;
+    };
+    let mut seen: HashSet<String> = images.iter().map(|image| image.url.clone()).collect();
+
+    for source in sources {
+        for url in srcset_urls(&source.srcset, &base) {
+            if seen.insert(url.clone()) {
+                images.push(PageImage {
+                    url,
+                    alt: source.alt.clone(),
+                    width: None,
+                    height: None,
+                    in_viewport: source.in_viewport,
+                });
+            }
+        }
+    }
+    images
+}
+
+/// Candidate URLs of a `srcset`, resolved against `base`; unparseable candidates are skipped
+fn srcset_urls(srcset: &str, base: &Url) -> Vec<String> {
+    srcset
+        .split(',')
+        .filter_map(|candidate| candidate.split_whitespace().next())
+        .filter_map(|src| base.join(src).ok())
+        .map(String::from)
+        .collect()
+}
+
+pub fn parse_html(html: &str) -> RcDom {
+    parse_document(RcDom::default(), Default::default()).one(html)
+}
+
+fn serialize_node(node: &Handle) -> AppResult<String> {
+    let mut out = Vec::new();
+    let handle: SerializableHandle = node.clone().into();
+    let opts = SerializeOpts {
+        traversal_scope: TraversalScope::IncludeNode,
+        ..Default::default()
+    };
+    serialize(&mut out, &handle, opts)
+        .map_err(|e| AppError::Internal(format!("HTML serialization failed: {}", e)))?;
+    Ok(String::from_utf8_lossy(&out).into_owned())
+}
+
+fn tag_name(node: &Handle) -> Option<&str> {
+    match &node.data {
+        NodeData::Element { name, .. } => Some(name.local.as_ref()),
+        _ => None,
+    }
+}
+
+fn attr(node: &Handle, name: &str) -> Option<String> {
+    match &node.data {
+        NodeData::Element { attrs, .. } => attrs
+            .borrow()
+            .iter()
+            .find(|a| a.name.local.as_ref() == name)
+            .map(|a| a.value.to_string()),
+        _ => None,
+    }
+}
+
+/// Lowercased class and id, used for content hints
+fn class_and_id(node: &Handle) -> String {
+    let class = attr(node, "class").unwrap_or_default();
+    let id = attr(node, "id").unwrap_or_default();
+    format!("{} {}", class, id).to_lowercase()
+}
+
+fn text_content(node: &Handle) -> String {
+    let mut text = String::new();
+    collect_text(node, &mut text);
+    text
+}
+
+fn collect_text(root: &Handle, out: &mut String) {
+    let mut stack = vec![root.clone()];
+    while let Some(node) = stack.pop() {
+        match &node.data {
+            NodeData::Text { contents } => out.push_str(&contents.borrow()),
+            NodeData::Element { .. } | NodeData::Document => {
+                stack.extend(node.children.borrow().iter().rev().cloned());
+            }
+            _ => {}
+        }
+    }
+}
+
+fn normalize_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn find_first(node: &Handle, tags: &[&str]) -> Option<Handle> {
+    find_node(node, &|n| tag_name(n).is_some_and(|t| tags.contains(&t)))
+}
+
+/// First descendant of `root` in document order matching `predicate`
+fn find_node(root: &Handle, predicate: &dyn Fn(&Handle) -> bool) -> Option<Handle> {
+    find_map_node(root, &|n| predicate(n).then(|| n.clone()))
+}
+
+/// First `Some` that `f` returns for the descendants of `root`, in document order
+fn find_map_node<T>(root: &Handle, f: &dyn Fn(&Handle) -> Option<T>) -> Option<T> {
+    let mut stack: Vec<Handle> = root.children.borrow().iter().rev().cloned().collect();
+    while let Some(node) = stack.pop() {
+        if let Some(found) = f(&node) {
+            return Some(found);
+        }
+        stack.extend(node.children.borrow().iter().rev().cloned());
+    }
+    None
+}
+
+/// Text of the first author-hinted element short enough to be a byline
+fn find_byline(document: &Handle) -> Option<String> {
+    find_map_node(document, &|n| {
+        let is_author = tag_name(n).is_some()
+            && (attr(n, "rel").as_deref() == Some("author")
+                || attr(n, "itemprop").as_deref() == Some("author")
+                || {
+                    let hints = class_and_id(n);
+                    hints.contains("byline") || hints.contains("author")
+                });
+        if !is_author {
+            return None;
+        }
+
+        let text = normalize_whitespace(&text_content(n));
+        (!text.is_empty() && text.chars().count() <= MAX_BYLINE_LEN).then_some(text)
+    })
+}
+
+fn find_published_date(document: &Handle) -> Option<String> {
+    let date_node = find_node(document, &|n| {
+        (tag_name(n) == Some("time") && attr(n, "datetime").is_some())
+            || attr(n, "itemprop").as_deref() == Some("datePublished")
+            || attr(n, "property").as_deref() == Some("article:published_time")
+    })?;
+
+    attr(&date_node, "datetime")
+        .or_else(|| attr(&date_node, "content"))
+        .or_else(|| Some(normalize_whitespace(&text_content(&date_node))))
+        .filter(|date| !date.is_empty())
+}
+
+fn is_unlikely(node: &Handle) -> bool {
+    let Some(tag) = tag_name(node) else {
+        return matches!(node.data, NodeData::Comment { .. });
+    };
+    if UNLIKELY_TAGS.contains(&tag) {
+        return true;
+    }
+    if PROTECTED_TAGS.contains(&tag) {
+        return false;
+    }
+    let hints = class_and_id(node);
+    NEGATIVE_HINTS.iter().any(|h| hints.contains(h)) && !POSITIVE_HINTS.iter().any(|h| hints.contains(h))
+}
+
+fn remove_unlikely(root: &Handle) {
+    let mut stack = vec![root.clone()];
+    while let Some(node) = stack.pop() {
+        node.children.borrow_mut().retain(|child| !is_unlikely(child));
+        stack.extend(node.children.borrow().iter().cloned());
+    }
+}
+
+fn class_weight(node: &Handle) -> f64 {
+    let hints = class_and_id(node);
+    let mut weight = 0.0;
+    if POSITIVE_HINTS.iter().any(|h| hints.contains(h)) {
+        weight += 25.0;
+    }
+    if NEGATIVE_HINTS.iter().any(|h| hints.contains(h)) {
+        weight -= 25.0;
+    }
+    weight
+}
+
+fn initial_score(node: &Handle) -> f64 {
+    let tag_score = match tag_name(node) {
+        Some("article") | Some("main") => 15.0,
+        Some("section") => 3.0,
+        Some("div") => 5.0,
+        Some("pre") | Some("td") | Some("blockquote") => 3.0,
+        Some("address") | Some("ol") | Some("ul") | Some("dl") | Some("dd") | Some("dt")
+        | Some("li") => -3.0,
+        Some("h1") | Some("h2") | Some("h3") | Some("h4") | Some("h5") | Some("h6")
+        | Some("th") => -5.0,
+        _ => 0.0,
+    };
+    tag_score + class_weight(node)
+}
+
+/// Paragraph-like elements whose text contributes to their ancestors' scores
+fn is_paragraph(node: &Handle) -> bool {
+    match tag_name(node) {
+        Some("p") | Some("pre") | Some("td") | Some("blockquote") => true,
+        Some("div") => !node
+            .children
+            .borrow()
+            .iter()
+            .any(|c| tag_name(c).is_some_and(|t| BLOCK_TAGS.contains(&t))),
+        _ => false,
+    }
+}
+
+fn link_density(node: &Handle) -> f64 {
+    let total = text_content(node).trim().chars().count();
+    if total == 0 {
+        return 0.0;
+    }
+    let mut link_len = 0;
+    collect_link_text_len(node, &mut link_len);
+    link_len as f64 / total as f64
+}
+
+fn collect_link_text_len(root: &Handle, len: &mut usize) {
+    let mut stack: Vec<Handle> = root.children.borrow().iter().cloned().collect();
+    while let Some(node) = stack.pop() {
+        if tag_name(&node) == Some("a") {
+            *len += text_content(&node).trim().chars().count();
+        } else {
+            stack.extend(node.children.borrow().iter().cloned());
+        }
+    }
+}
+
+type Scores = HashMap<*const Node, (Handle, f64)>;
+
+fn best_candidate(root: &Handle) -> Option<Handle> {
+    let mut scores = Scores::new();
+    score_paragraphs(root, &mut scores);
+
+    scores
+        .into_values()
+        .map(|(node, score)| {
+            let adjusted = score * (1.0 - link_density(&node));
+            (node, adjusted)
+        })
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(node, _)| node)
+}
+
+fn score_paragraphs(root: &Handle, scores: &mut Scores) {
+    // `None` marks the end of an element's children
+    let mut stack = vec![Some(root.clone())];
+    let mut ancestors: Vec<Handle> = Vec::new();
+
+    while let Some(step) = stack.pop() {
+        let Some(node) = step else {
+            ancestors.pop();
+            continue;
+        };
+
+        if is_paragraph(&node) {
+            let text = text_content(&node);
+            let len = text.trim().chars().count();
+            if len >= MIN_PARAGRAPH_LEN {
+                let score = 1.0 + text.matches(',').count() as f64 + (len / 100).min(3) as f64;
+                // Parent gets the full score, grandparent half
+                for (depth, ancestor) in ancestors.iter().rev().take(2).enumerate() {
+                    let entry = scores
+                        .entry(Rc::as_ptr(ancestor))
+                        .or_insert_with(|| (ancestor.clone(), initial_score(ancestor)));
+                    entry.1 += if depth == 0 { score } else { score / 2.0 };
+                }
+            }
+        }
+
+        if tag_name(&node).is_some() {
+            ancestors.push(node.clone());
+            stack.push(None);
+        }
+        stack.extend(node.children.borrow().iter().rev().cloned().map(Some));
+    }
+}
+
+fn absolutize(base: &str, href: &str) -> String {
+    Url::parse(base)
+        .and_then(|base| base.join(href))
+        .map(|url| url.to_string())
+        .unwrap_or_else(|_| href.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAGE_URL: &str = "https://example.com/news/story";
+
+    const ARTICLE_PAGE: &str = r#"
+        <body>
+            <nav><a href="/">Home</a> <a href="/about">About</a></nav>
+            <div class="cookie-banner">We use cookies, lots of cookies, to track everything.</div>
+            <div id="sidebar">
+                <p>Sidebar promo text that is long enough to be scored, sadly.</p>
+            </div>
+            <article>
+                <h1>Big News</h1>
+                <p class="byline">By Jane Doe</p>
+                <time datetime="2024-05-01T10:00:00Z">May 1</time>
+                <img src="/images/lead.jpg">
+                <p>First paragraph of the story, with commas, details, and plenty of words to count.</p>
+                <p>Second paragraph continues the story, adding context, quotes, and more words.</p>
+                <p>Third paragraph wraps up, with a conclusion that is long enough to matter.</p>
+            </article>
+            <footer><p>Copyright footer text that should never be part of the article.</p></footer>
+        </body>
+    "#;
+
+    fn extract(html: &str) -> MainContent {
+        extract_main_content(html, PAGE_URL, &HashMap::new()).unwrap()
+    }
+
+    // ==================== extract_main_content ====================
+
+    #[test]
+    fn main_content_keeps_article_body() {
+        let main = extract(ARTICLE_PAGE);
+        assert!(main.html.starts_with("<article>"));
+        assert!(main.html.contains("First paragraph"));
+        assert!(main.html.contains("Third paragraph"));
+    }
+
+    #[test]
+    fn main_content_drops_boilerplate() {
+        let main = extract(ARTICLE_PAGE);
+        assert!(!main.html.contains("Home"));
+        assert!(!main.html.contains("cookies"));
+        assert!(!main.html.contains("Sidebar"));
+        assert!(!main.html.contains("Copyright"));
+    }
+
+    #[test]
+    fn main_content_byline() {
+        let main = extract(ARTICLE_PAGE);
+        assert_eq!(main.article.byline.as_deref(), Some("By Jane Doe"));
+    }
+
+    #[test]
+    fn main_content_published_date() {
+        let main = extract(ARTICLE_PAGE);
+        assert_eq!(main.article.published_date.as_deref(), Some("2024-05-01T10:00:00Z"));
+    }
+
+    #[test]
+    fn main_content_lead_image_absolute() {
+        let main = extract(ARTICLE_PAGE);
+        assert_eq!(
+            main.article.lead_image.as_deref(),
+            Some("https://example.com/images/lead.jpg")
+        );
+    }
+
+    #[test]
+    fn main_content_lead_image_prefers_og() {
+        let mut og_tags = HashMap::new();
+        og_tags.insert("og:image".to_string(), "https://cdn.example.com/og.png".to_string());
+        let main = extract_main_content(ARTICLE_PAGE, PAGE_URL, &og_tags).unwrap();
+        assert_eq!(main.article.lead_image.as_deref(), Some("https://cdn.example.com/og.png"));
+    }
+
+    #[test]
+    fn main_content_penalizes_link_lists() {
+        let html = r#"
+            <body>
+                <div class="links">
+                    <p><a href="/a">A very long link text number one, with commas, to score</a></p>
+                    <p><a href="/b">A very long link text number two, with commas, to score</a></p>
+                </div>
+                <div>
+                    <p>Real prose paragraph, with commas, that readers actually care about here.</p>
+                </div>
+            </body>
+        "#;
+        let main = extract(html);
+        assert!(main.html.contains("Real prose"));
+        assert!(!main.html.contains("link text"));
+    }
+
+    // ==================== classify_links ====================
+
+    fn raw_link(url: &str) -> RawLink {
+        RawLink {
+            url: url.to_string(),
+            text: "text".to_string(),
+            rel: vec![],
+        }
+    }
+
+    #[test]
+    fn links_internal_same_host() {
+        let links = classify_links(vec![raw_link("https://example.com/about")], PAGE_URL);
+        assert_eq!(links.len(), 1);
+        assert!(links[0].internal);
+    }
+
+    #[test]
+    fn links_internal_ignores_host_case() {
+        let links = classify_links(vec![raw_link("https://EXAMPLE.com/about")], PAGE_URL);
+        assert!(links[0].internal);
+    }
+
+    #[test]
+    fn links_external_other_host() {
+        let links = classify_links(
+            vec![raw_link("https://other.com/"), raw_link("https://blog.example.com/")],
+            PAGE_URL,
+        );
+        assert!(!links[0].internal);
+        assert!(!links[1].internal);
+    }
+
+    #[test]
+    fn links_skip_non_http() {
+        let links = classify_links(
+            vec![
+                raw_link("javascript:void(0)"),
+                raw_link("mailto:me@example.com"),
+                raw_link("not a url"),
+                raw_link("http://example.com/ok"),
+            ],
+            PAGE_URL,
+        );
+        assert_eq!(links.len(), 1);
+        assert_eq!(links[0].url, "http://example.com/ok");
+    }
+
+    #[test]
+    fn links_keep_text_and_rel() {
+        let link = RawLink {
+            url: "https://other.com/".to_string(),
+            text: "Other".to_string(),
+            rel: vec!["nofollow".to_string(), "noopener".to_string()],
+        };
+        let links = classify_links(vec![link], PAGE_URL);
+        assert_eq!(links[0].text, "Other");
+        assert_eq!(links[0].rel, vec!["nofollow", "noopener"]);
+    }
+
+    // ==================== merge_image_sources ====================
+
+    fn image(url: &str) -> PageImage {
+        PageImage {
+            url: url.to_string(),
+            alt: Some("loaded".to_string()),
+            width: Some(640),
+            height: Some(480),
+            in_viewport: true,
+        }
+    }
+
+    fn source(srcset: &str) -> RawImageSource {
+        RawImageSource {
+            srcset: srcset.to_string(),
+            alt: Some("picture".to_string()),
+            in_viewport: false,
+        }
+    }
+
+    #[test]
+    fn srcset_resolves_relative_candidates() {
+        let base = Url::parse("https://example.com/post/").unwrap();
+        let urls = srcset_urls("small.avif 480w, /img/large.avif 1080w,https://cdn.com/x.avif", &base);
+        assert_eq!(
+            urls,
+            vec![
+                "https://example.com/post/small.avif",
+                "https://example.com/img/large.avif",
+                "https://cdn.com/x.avif",
+            ]
+        );
+    }
+
+    #[test]
+    fn srcset_skips_malformed_candidates() {
+        let base = Url::parse("https://example.com/").unwrap();
+        let urls = srcset_urls("http://[broken/a.png 1x, , ok.png 2x", &base);
+        assert_eq!(urls, vec!["https://example.com/ok.png"]);
+    }
+
+    #[test]
+    fn image_sources_appended_after_images() {
+        let images = merge_image_sources(
+            vec![image("https://example.com/a.png")],
+            vec![source("https://example.com/a.png 1x, b.webp 2x")],
+            PAGE_URL,
+        );
+        assert_eq!(images.len(), 2);
+        assert_eq!(images[0].width, Some(640));
+        assert_eq!(images[1].url, "https://example.com/news/b.webp");
+        assert_eq!(images[1].alt.as_deref(), Some("picture"));
+        assert!(images[1].width.is_none());
+        assert!(!images[1].in_viewport);
+    }
+
+    #[test]
+    fn image_sources_need_a_valid_base() {
+        let images = merge_image_sources(vec![], vec![source("a.png")], "not a url");
+        assert!(images.is_empty());
+    }
+
+    // ==================== deep nesting ====================
+
+    fn deeply_nested(depth: usize) -> String {
+        format!(
+            "<body>{}<p>Innermost paragraph, with commas, long enough to be scored.</p>{}</body>",
+            "<div>".repeat(depth),
+            "</div>".repeat(depth)
+        )
+    }
+
+    #[test]
+    fn deep_nesting_does_not_overflow() {
+        let html = deeply_nested(5_000);
+        assert!(html_to_text(&html).contains("Innermost paragraph"));
+        assert!(extract(&html).html.contains("Innermost paragraph"));
+    }
+
+    // ==================== html_to_text ====================
+
+    #[test]
+    fn text_keeps_paragraph_breaks() {
+        let text = html_to_text("<body><h1>Title</h1><p>First para.</p><p>Second para.</p></body>");
+        assert_eq!(text, "Title\n\nFirst para.\n\nSecond para.");
+    }
+
+    #[test]
+    fn text_normalizes_whitespace() {
+        let text = html_to_text("<body><p>  Lots   of\n\n   space\t here  </p></body>");
+        assert_eq!(text, "Lots of space here");
+    }
+
+    #[test]
+    fn text_drops_markup_and_link_urls() {
+        let text = html_to_text(
+            r#"<body><p>Read <a href="https://example.com/more">the <b>docs</b></a> now.</p><img src="x.png" alt="pic"></body>"#,
+        );
+        assert_eq!(text, "Read the docs now.");
+    }
+
+    #[test]
+    fn text_skips_invisible_elements() {
+        let text = html_to_text(
+            "<html><head><title>T</title><style>p{}</style></head><body><script>var x = 1;</script><p>Visible</p><noscript>NS</noscript></body></html>",
+        );
+        assert_eq!(text, "Visible");
+    }
+
+    #[test]
+    fn text_list_items_on_own_lines() {
+        let text = html_to_text("<body><p>Intro</p><ul><li>One</li><li>Two</li></ul></body>");
+        assert_eq!(text, "Intro\n\nOne\nTwo");
+    }
+
+    #[test]
+    fn text_line_breaks() {
+        let text = html_to_text("<body><p>Line one<br>Line two</p></body>");
+        assert_eq!(text, "Line one\nLine two");
+    }
+
+    #[test]
+    fn text_preserves_preformatted_lines() {
+        let text = html_to_text("<body><pre>fn main() {\n    run();\n}</pre></body>");
+        assert_eq!(text, "fn main() {\n    run();\n}");
+    }
+
+    #[test]
+    fn text_table_cells_separated() {
+        let text = html_to_text("<body><table><tr><td>A</td><td>B</td></tr><tr><td>C</td><td>D</td></tr></table></body>");
+        assert_eq!(text, "A B\nC D");
+    }
+
+    #[test]
+    fn main_content_byline_skips_long_author_blocks() {
+        let bio = "Writes about distributed systems, databases and the people who operate them. ".repeat(3);
+        let html = format!(
+            r#"<body><div class="author-bio">{}</div><span class="byline">By Jane Doe</span></body>"#,
+            bio
+        );
+        assert_eq!(extract(&html).article.byline.as_deref(), Some("By Jane Doe"));
+    }
+
+    #[test]
+    fn main_content_falls_back_to_body() {
+        let main = extract("<body><span>tiny</span></body>");
+        assert!(main.html.starts_with("<body>"));
+        assert!(main.html.contains("tiny"));
+        assert!(main.article.byline.is_none());
+        assert!(main.article.published_date.is_none());
+        assert!(main.article.lead_image.is_none());
+    }
+}
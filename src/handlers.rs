@@ -1,253 +1,585 @@
-use axum::{extract::State, http::HeaderMap, Json};
-use std::net::IpAddr;
+use axum::{
+    body::Body,
+    extract::{Path, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
+    Json,
+};
+use futures_util::future::join_all;
+use futures_util::{stream, Stream};
+use std::convert::Infallible;
 use std::sync::Arc;
-use subtle::ConstantTimeEq;
-use tracing::{error, warn};
-use url::Url;
-
-use crate::browser::BrowserManager;
+use tokio::sync::mpsc::UnboundedSender;
+use tracing::{debug, error, warn, Instrument, Span};
+
+use crate::auth::{ApiKeyRef, KeyRegistry, Scope};
+use crate::browser::{BrowserManager, ScrapeProgress};
+use crate::config::TimeoutConfig;
+use crate::crawl;
+use crate::domains::DomainPolicy;
 use crate::error::AppError;
-use crate::llm::GeminiClient;
-use crate::models::{ScrapeData, ScrapeRequest, ScrapeResponse};
+use crate::jobs::{Job, JobManager};
+use crate::llm::{LlmRegistry, StreamUpdate};
+use crate::models::{
+    AnalysisError, BatchScrapeRequest, BatchScrapeResponse, CrawlRequest, JobRequest, ScrapeData,
+    ScrapeEvent, ScrapeRequest, ScrapeResponse, SitemapRequest, SitemapResponse,
+};
+use crate::ratelimit::{RateLimiter, Resource};
+use crate::robots::{CrawlPacer, RobotsPolicy};
+use crate::scheduler::SlotClaim;
+use crate::sitemap::SitemapFetcher;
+use crate::ssrf::UrlGuard;
 
 const API_KEY_HEADER: &str = "x-api-key";
+const DEFAULT_SITEMAP_URLS: usize = 1_000;
+const MAX_SITEMAP_URLS: usize = 50_000;
 
 pub struct AppState {
     pub browser: BrowserManager,
-    pub llm_client: GeminiClient,
-    pub api_key: String,
+    pub llm: LlmRegistry,
+    pub keys: Arc<KeyRegistry>,
+    pub limits: RateLimiter,
+    pub timeouts: TimeoutConfig,
+    pub max_batch_size: usize,
+    pub max_crawl_pages: usize,
+    pub jobs: JobManager,
+    pub robots: RobotsPolicy,
+    pub sitemaps: SitemapFetcher,
+    pub url_guard: UrlGuard,
 }
 
-/// Constant-time comparison to prevent timing attacks
-fn secure_compare(a: &str, b: &str) -> bool {
-    a.as_bytes().ct_eq(b.as_bytes()).into()
-}
+/// Authenticates the `x-api-key` header, checks `required` scopes and tags the
+/// request span with the key ID
+fn authorize(
+    state: &AppState,
+    headers: &HeaderMap,
+    resource: &str,
+    required: &[Scope],
+) -> Result<ApiKeyRef, AppError> {
+    let provided_key = headers
+        .get(API_KEY_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("");
 
-/// SSRF protection: blocks private/internal IPs
-fn is_private_ip(ip: &IpAddr) -> bool {
-    match ip {
-        IpAddr::V4(v4) => {
-            v4.is_loopback() || v4.is_private() || v4.is_link_local()
-                || v4.is_broadcast() || v4.is_unspecified()
+    let key = state.keys.authenticate(provided_key, required).inspect_err(|e| {
+        if matches!(e, AppError::Unauthorized) {
+            warn!(resource, "Unauthorized");
         }
-        IpAddr::V6(v6) => v6.is_loopback() || v6.is_unspecified(),
+    })?;
+    Span::current().record("key_id", key.id.as_str());
+    debug!(key_id = %key.id, key_label = %key.label, resource, "Authorized");
+
+    Ok(key)
+}
+
+/// `base` plus `Scope::Analysis` when any request asks for LLM analysis
+fn scopes_for<'a>(base: Scope, requests: impl IntoIterator<Item = &'a ScrapeRequest>) -> Vec<Scope> {
+    let mut scopes = vec![base];
+    if requests.into_iter().any(|r| r.analysis_request.is_some()) {
+        scopes.push(Scope::Analysis);
     }
+    scopes
 }
 
-/// Validates URL and blocks SSRF attempts
-fn validate_url(url_str: &str) -> Result<Url, AppError> {
-    let url = Url::parse(url_str)
-        .map_err(|e| AppError::InvalidRequest(format!("Invalid URL: {}", e)))?;
+pub async fn scrape_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(request): Json<ScrapeRequest>,
+) -> Result<Json<ScrapeResponse>, AppError> {
+    let key = authorize(&state, &headers, &request.url, &scopes_for(Scope::Scrape, [&request]))?;
 
-    match url.scheme() {
-        "http" | "https" => {}
-        s => return Err(AppError::InvalidRequest(format!("Invalid scheme: {}", s))),
-    }
+    let data = run_scrape(&state, &key, &request, None).await?;
+    Ok(Json(ScrapeResponse::success(data)))
+}
+
+/// Scrapes up to `max_batch_size` URLs concurrently; results keep input order
+pub async fn batch_scrape_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(batch): Json<BatchScrapeRequest>,
+) -> Result<Json<BatchScrapeResponse>, AppError> {
+    let key = authorize(&state, &headers, "batch", &scopes_for(Scope::Batch, &batch.requests))?;
+    validate_batch(&batch, state.max_batch_size)?;
+
+    Ok(Json(BatchScrapeResponse {
+        success: true,
+        results: scrape_all(&state, &key, &batch.requests).await?,
+    }))
+}
 
-    let host = url
-        .host_str()
-        .ok_or_else(|| AppError::InvalidRequest("Missing host".to_string()))?;
+/// Expands a site's sitemaps into URLs, optionally batch-scraping the first `max_batch_size`
+pub async fn sitemap_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(sitemap_request): Json<SitemapRequest>,
+) -> Result<Json<SitemapResponse>, AppError> {
+    let scraped = sitemap_request.scrape.then_some(&sitemap_request.request);
+    let key = authorize(&state, &headers, &sitemap_request.request.url, &scopes_for(Scope::Batch, scraped))?;
+
+    let input = sitemap_request.request.url.trim();
+    let start = if input.contains("://") {
+        state.url_guard.validate_url(input, &key.domains)?
+    } else {
+        state.url_guard.validate_url(&format!("https://{}", input), &key.domains)?
+    };
+    let max_urls = sitemap_request
+        .max_urls
+        .unwrap_or(DEFAULT_SITEMAP_URLS)
+        .min(MAX_SITEMAP_URLS);
+
+    let expansion = state.sitemaps.expand(&start, &key.domains, max_urls).await?;
+
+    let results = if sitemap_request.scrape {
+        let requests: Vec<ScrapeRequest> = expansion
+            .urls
+            .iter()
+            .take(state.max_batch_size)
+            .map(|entry| ScrapeRequest {
+                url: entry.loc.clone(),
+                ..sitemap_request.request.clone()
+            })
+            .collect();
+        Some(scrape_all(&state, &key, &requests).await?)
+    } else {
+        None
+    };
+
+    Ok(Json(SitemapResponse {
+        success: true,
+        sitemaps: expansion.sitemaps,
+        urls: expansion.urls,
+        errors: expansion.errors,
+        rejected: expansion.rejected,
+        truncated: expansion.truncated,
+        results,
+    }))
+}
+
+/// Charges the whole batch up front, then runs requests concurrently; per-item
+/// errors become error responses, order is kept
+async fn scrape_all(
+    state: &AppState,
+    key: &ApiKeyRef,
+    requests: &[ScrapeRequest],
+) -> Result<Vec<ScrapeResponse>, AppError> {
+    charge_scrapes(state, key, requests)?;
+
+    let pacer = &CrawlPacer::new();
+    Ok(join_all(requests.iter().map(|request| async move {
+        match run_scrape_observed(state, key, request, None, true, Some(pacer)).await {
+            Ok(data) => ScrapeResponse::success(data),
+            Err(e) => {
+                warn!(url = %request.url, error = %e, "Batch item failed");
+                ScrapeResponse::error(e.code(), &e.to_string())
+            }
+        }
+    }))
+    .await)
+}
 
-    if let Ok(ip) = host.parse::<IpAddr>() {
-        if is_private_ip(&ip) {
-            return Err(AppError::InvalidRequest("Private IP not allowed".to_string()));
+/// Charges one page load per request, plus one analysis for each that asks for it.
+/// All or nothing, so a request over its limits is rejected before any page loads
+fn charge_scrapes<'a>(
+    state: &AppState,
+    key: &ApiKeyRef,
+    requests: impl IntoIterator<Item = &'a ScrapeRequest>,
+) -> Result<(), AppError> {
+    let (mut scrapes, mut analyses) = (0, 0);
+    for request in requests {
+        scrapes += 1;
+        if request.analysis_request.is_some() {
+            analyses += 1;
         }
     }
+    state.limits.charge(
+        &key.id,
+        &key.limits,
+        &[(Resource::Scrape, scrapes), (Resource::Analysis, analyses)],
+    )
+}
 
-    let host_lower = host.to_lowercase();
-    if host_lower == "localhost" || host_lower.ends_with(".localhost") {
-        return Err(AppError::InvalidRequest("Localhost not allowed".to_string()));
+fn validate_batch(batch: &BatchScrapeRequest, max_batch_size: usize) -> Result<(), AppError> {
+    if batch.requests.is_empty() {
+        return Err(AppError::InvalidRequest("Batch is empty".to_string()));
+    }
+    if batch.requests.len() > max_batch_size {
+        return Err(AppError::InvalidRequest(format!(
+            "Batch size {} exceeds limit of {}",
+            batch.requests.len(),
+            max_batch_size
+        )));
     }
+    Ok(())
+}
 
-    Ok(url)
+/// Crawls same-origin links from the seed URL, streaming one NDJSON line per page
+pub async fn crawl_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(crawl_request): Json<CrawlRequest>,
+) -> Result<Response, AppError> {
+    let key = authorize(
+        &state,
+        &headers,
+        &crawl_request.request.url,
+        &scopes_for(Scope::Batch, [&crawl_request.request]),
+    )?;
+
+    let seed = state.url_guard.validate_url(&crawl_request.request.url, &key.domains)?;
+    let max_pages = crawl_request.max_pages.min(state.max_crawl_pages);
+    if max_pages == 0 {
+        return Err(AppError::InvalidRequest("max_pages must be at least 1".to_string()));
+    }
+
+    let (tx, rx) = tokio::sync::mpsc::channel(16);
+    // Background tasks keep the request span so their logs carry the key ID
+    tokio::spawn(crawl::crawl(state.clone(), key, seed, crawl_request, max_pages, tx).in_current_span());
+
+    let lines = stream::unfold(rx, |mut rx| async move {
+        let event = rx.recv().await?;
+        let mut line = serde_json::to_vec(&event).unwrap_or_default();
+        line.push(b'\n');
+        Some((Ok::<_, Infallible>(line), rx))
+    });
+
+    Ok((
+        [(header::CONTENT_TYPE, "application/x-ndjson")],
+        Body::from_stream(lines),
+    )
+        .into_response())
 }
 
-pub async fn scrape_handler(
+/// Same pipeline as `/scrape`, reporting progress as Server-Sent Events and
+/// ending with a `result` event
+pub async fn scrape_stream_handler(
     State(state): State<Arc<AppState>>,
     headers: HeaderMap,
     Json(request): Json<ScrapeRequest>,
-) -> Result<Json<ScrapeResponse>, AppError> {
-    let provided_key = headers
-        .get(API_KEY_HEADER)
-        .and_then(|v| v.to_str().ok())
-        .unwrap_or("");
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, AppError> {
+    let key = authorize(&state, &headers, &request.url, &scopes_for(Scope::Scrape, [&request]))?;
+    state.url_guard.validate_url(&request.url, &key.domains)?;
+    // Charged before the stream opens so an exhausted limit is a plain 429
+    charge_scrapes(&state, &key, [&request])?;
+
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+    let task = tokio::spawn(async move {
+        let response = match run_scrape_observed(&state, &key, &request, Some(&tx), true, None).await {
+            Ok(data) => ScrapeResponse::success(data),
+            Err(e) => {
+                warn!(url = %request.url, error = %e, "Streamed scrape failed");
+                ScrapeResponse::error(e.code(), &e.to_string())
+            }
+        };
+        let _ = tx.send(ScrapeEvent::Result {
+            result: Box::new(response),
+        });
+    }.in_current_span());
+
+    // Dropping the stream (client disconnect) stops the scrape
+    let guard = AbortOnDrop(task.abort_handle());
+    let events = stream::unfold((rx, guard), |(mut rx, guard)| async move {
+        let event = rx.recv().await?;
+        let sse = Event::default()
+            .event(event.name())
+            .json_data(&event)
+            .unwrap_or_default();
+        Some((Ok(sse), (rx, guard)))
+    });
+
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
+struct AbortOnDrop(tokio::task::AbortHandle);
 
-    if !secure_compare(provided_key, &state.api_key) {
-        warn!(url = %request.url, "Unauthorized");
-        return Err(AppError::Unauthorized);
+impl Drop for AbortOnDrop {
+    fn drop(&mut self) {
+        self.0.abort();
     }
+}
 
-    let validated_url = validate_url(&request.url)?;
+/// Shared scrape + optional analysis pipeline; page loads and analyses are charged to `key`.
+/// Pages of a multi-page request share a `pacer` that spaces them by the host's `Crawl-delay`
+pub(crate) async fn run_scrape(
+    state: &AppState,
+    key: &ApiKeyRef,
+    request: &ScrapeRequest,
+    pacer: Option<&CrawlPacer>,
+) -> Result<ScrapeData, AppError> {
+    run_scrape_observed(state, key, request, None, false, pacer).await
+}
 
-    let (metadata, content) = state
+/// `run_scrape` that reports pipeline phases and analysis tokens to `events`.
+/// `prepaid` requests were already charged by the caller, e.g. as part of a batch
+async fn run_scrape_observed(
+    state: &AppState,
+    key: &ApiKeyRef,
+    request: &ScrapeRequest,
+    events: Option<&UnboundedSender<ScrapeEvent>>,
+    prepaid: bool,
+    pacer: Option<&CrawlPacer>,
+) -> Result<ScrapeData, AppError> {
+    let emit = |event: ScrapeEvent| {
+        if let Some(tx) = events {
+            let _ = tx.send(event);
+        }
+    };
+
+    let validated_url = state.url_guard.validate_url(&request.url, &key.domains)?;
+    let timeouts = state.timeouts.scrape(request.navigation_timeout_ms)?;
+    let analysis_timeout = state.timeouts.analysis(request.analysis_timeout_ms)?;
+    // Charged before DNS and robots.txt so a key over its limits causes no outbound traffic
+    if !prepaid {
+        charge_scrapes(state, key, [request])?;
+    }
+    state.url_guard.check(&validated_url).await?;
+    let crawl_delay = state.robots.check(&validated_url).await?;
+    if let (Some(pacer), Some(delay)) = (pacer, crawl_delay) {
+        pacer.wait(&validated_url, delay, timeouts.navigation).await?;
+    }
+
+    let page = state
         .browser
-        .scrape_page(validated_url.as_str(), request.output_format)
+        .scrape_page(
+            validated_url.as_str(),
+            request,
+            &key.domains,
+            &slot_claim(key, request),
+            timeouts,
+            |progress| {
+                emit(match progress {
+                    ScrapeProgress::TabAcquired => ScrapeEvent::TabAcquired,
+                    ScrapeProgress::Navigated => ScrapeEvent::Navigated,
+                })
+            },
+        )
         .await?;
+    emit(ScrapeEvent::ContentExtracted {
+        content: page.content.clone(),
+    });
 
-    let (analysis_result, analysis_error) =
+    let (analysis_result, analysis_error, analysis_chunks) =
         if let Some(req) = request.analysis_request.as_ref() {
-            match state.llm_client.analyze(&content, req).await {
-                Ok(result) => (Some(result), None),
+            let analysis = match events {
+                Some(tx) => {
+                    let tx = tx.clone();
+                    let on_token = move |update: StreamUpdate<'_>| {
+                        let _ = tx.send(match update {
+                            StreamUpdate::Token(text) => ScrapeEvent::AnalysisToken {
+                                text: text.to_string(),
+                            },
+                            StreamUpdate::Retry => ScrapeEvent::AnalysisRetry,
+                        });
+                    };
+                    state
+                        .llm
+                        .analyze_streaming(&page.content, req, analysis_timeout, &on_token)
+                        .await
+                }
+                None => state.llm.analyze(&page.content, req, analysis_timeout).await,
+            };
+            match analysis {
+                Ok(analysis) => (Some(analysis.result), None, Some(analysis.chunks)),
                 Err(e) => {
                     error!(error = %e, "LLM analysis failed");
-                    (None, Some(e.to_string()))
+                    (None, Some(AnalysisError::from(&e)), None)
                 }
             }
         } else {
-            (None, None)
+            (None, None, None)
         };
 
-    Ok(Json(ScrapeResponse::success(ScrapeData {
-        metadata,
-        content,
+    Ok(ScrapeData {
+        metadata: page.metadata,
+        content: page.content,
+        selector_matches: page.selector_matches,
+        article: page.article,
+        images: page.images,
+        screenshot: page.screenshot,
+        pdf: page.pdf,
         analysis_result,
         analysis_error,
-    })))
+        analysis_chunks,
+    })
 }
 
-pub async fn health_handler(State(state): State<Arc<AppState>>) -> Json<serde_json::Value> {
-    let stats = state.browser.stats().await;
-
-    Json(serde_json::json!({
-        "status": "healthy",
-        "browser": {
-            "max_concurrent": stats.max_concurrent,
-            "available_slots": stats.available_slots,
-            "idle_tabs": stats.idle_tabs,
-            "active_tabs": stats.active_tabs
+/// Queues a scrape in the background and returns its job ID immediately
+pub async fn create_job_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(job_request): Json<JobRequest>,
+) -> Result<(StatusCode, Json<Job>), AppError> {
+    let key = authorize(
+        &state,
+        &headers,
+        &job_request.request.url,
+        &scopes_for(Scope::Scrape, [&job_request.request]),
+    )?;
+
+    state.url_guard.validate_url(&job_request.request.url, &key.domains)?;
+    let callback_url = match job_request.callback_url.as_deref() {
+        Some(url) => {
+            state.jobs.require_webhook_secret()?;
+            let callback = state.url_guard.validate_url(url, DomainPolicy::unrestricted())?;
+            state.url_guard.check(&callback).await?;
+            Some(callback.to_string())
         }
-    }))
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
+        None => None,
+    };
 
-    // ==================== secure_compare ====================
+    charge_scrapes(&state, &key, [&job_request.request])?;
 
-    #[test]
-    fn secure_compare_equal() {
-        assert!(secure_compare("secret", "secret"));
-    }
-
-    #[test]
-    fn secure_compare_different() {
-        assert!(!secure_compare("secret", "wrong"));
-    }
+    let job = state.jobs.create(&key.id, &job_request.request.url, callback_url);
 
-    #[test]
-    fn secure_compare_empty() {
-        assert!(secure_compare("", ""));
-        assert!(!secure_compare("", "x"));
-    }
-
-    #[test]
-    fn secure_compare_different_length() {
-        assert!(!secure_compare("short", "longer_string"));
-    }
+    let task_state = state.clone();
+    let job_id = job.id.clone();
+    let task = tokio::spawn(run_job(task_state, key, job_id, job_request.request).in_current_span());
+    state.jobs.attach(&job.id, task.abort_handle());
 
-    // ==================== is_private_ip ====================
+    Ok((StatusCode::ACCEPTED, Json(job)))
+}
 
-    #[test]
-    fn private_ip_loopback_v4() {
-        let ip: IpAddr = "127.0.0.1".parse().unwrap();
-        assert!(is_private_ip(&ip));
-    }
+pub async fn get_job_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Result<Json<Job>, AppError> {
+    let key = authorize(&state, &headers, &id, &[Scope::Scrape])?;
+    Ok(Json(state.jobs.get(&key.id, &id)?))
+}
 
-    #[test]
-    fn private_ip_loopback_v6() {
-        let ip: IpAddr = "::1".parse().unwrap();
-        assert!(is_private_ip(&ip));
-    }
+pub async fn cancel_job_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Result<Json<Job>, AppError> {
+    let key = authorize(&state, &headers, &id, &[Scope::Scrape])?;
+    Ok(Json(state.jobs.cancel(&key.id, &id)?))
+}
 
-    #[test]
-    fn private_ip_class_a() {
-        let ip: IpAddr = "10.0.0.1".parse().unwrap();
-        assert!(is_private_ip(&ip));
-    }
+async fn run_job(state: Arc<AppState>, key: ApiKeyRef, id: String, request: ScrapeRequest) {
+    state.jobs.start(&id);
 
-    #[test]
-    fn private_ip_class_b() {
-        let ip: IpAddr = "172.16.0.1".parse().unwrap();
-        assert!(is_private_ip(&ip));
-    }
+    let response = match run_scrape_observed(&state, &key, &request, None, true, None).await {
+        Ok(data) => ScrapeResponse::success(data),
+        Err(e) => {
+            warn!(job_id = %id, error = %e, "Job failed");
+            ScrapeResponse::error(e.code(), &e.to_string())
+        }
+    };
 
-    #[test]
-    fn private_ip_class_c() {
-        let ip: IpAddr = "192.168.1.1".parse().unwrap();
-        assert!(is_private_ip(&ip));
+    if let Some(job) = state.jobs.finish(&id, response) {
+        state.jobs.notify(&job).await;
     }
+}
 
-    #[test]
-    fn private_ip_link_local() {
-        let ip: IpAddr = "169.254.1.1".parse().unwrap();
-        assert!(is_private_ip(&ip));
-    }
+/// Returns the rendered page as raw `application/pdf` bytes
+pub async fn pdf_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(request): Json<ScrapeRequest>,
+) -> Result<Response, AppError> {
+    let key = authorize(&state, &headers, &request.url, &scopes_for(Scope::Scrape, [&request]))?;
 
-    #[test]
-    fn public_ip_allowed() {
-        let ip: IpAddr = "8.8.8.8".parse().unwrap();
-        assert!(!is_private_ip(&ip));
-    }
+    let validated_url = state.url_guard.validate_url(&request.url, &key.domains)?;
+    let timeouts = state.timeouts.scrape(request.navigation_timeout_ms)?;
+    charge_scrapes(&state, &key, [&request])?;
+    state.url_guard.check(&validated_url).await?;
+    state.robots.check(&validated_url).await?;
 
-    // ==================== validate_url ====================
+    let pdf = state
+        .browser
+        .print_page(
+            validated_url.as_str(),
+            &request,
+            &key.domains,
+            &slot_claim(&key, &request),
+            timeouts,
+        )
+        .await?;
 
-    #[test]
-    fn validate_url_https() {
-        assert!(validate_url("https://example.com").is_ok());
-    }
+    Ok(([(header::CONTENT_TYPE, "application/pdf")], pdf).into_response())
+}
 
-    #[test]
-    fn validate_url_http() {
-        assert!(validate_url("http://example.com").is_ok());
-    }
+/// Unauthenticated callers see totals only; a valid `x-api-key` also sees its own queue
+pub async fn health_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Json<serde_json::Value> {
+    let stats = state.browser.stats().await;
 
-    #[test]
-    fn validate_url_with_path() {
-        assert!(validate_url("https://example.com/path/to/page").is_ok());
+    let mut scheduler = serde_json::json!({
+        "active": stats.slots.active,
+        "queued": stats.slots.queued
+    });
+    let provided_key = headers
+        .get(API_KEY_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("");
+    if let Ok(key) = state.keys.authenticate(provided_key, &[]) {
+        let own = stats.slots.tenants.get(&key.id);
+        scheduler["key"] = serde_json::json!({
+            "id": key.id,
+            "active": own.map_or(0, |t| t.active),
+            "queued": own.map_or(0, |t| t.queued)
+        });
     }
 
-    #[test]
-    fn validate_url_with_query() {
-        assert!(validate_url("https://example.com?q=test").is_ok());
-    }
+    Json(serde_json::json!({
+        "status": "healthy",
+        "browser": {
+            "max_concurrent": stats.max_concurrent,
+            "available_slots": stats.available_slots,
+            "idle_tabs": stats.idle_tabs,
+            "active_tabs": stats.active_tabs
+        },
+        "scheduler": scheduler
+    }))
+}
 
-    #[test]
-    fn validate_url_invalid_scheme_ftp() {
-        assert!(validate_url("ftp://example.com").is_err());
+/// Browser slot request for `key`, queued at the request's priority
+fn slot_claim(key: &ApiKeyRef, request: &ScrapeRequest) -> SlotClaim {
+    SlotClaim {
+        tenant: key.id.clone(),
+        policy: key.scheduling.clone(),
+        priority: request.priority,
     }
+}
 
-    #[test]
-    fn validate_url_invalid_scheme_file() {
-        assert!(validate_url("file:///etc/passwd").is_err());
-    }
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    #[test]
-    fn validate_url_invalid_format() {
-        assert!(validate_url("not-a-url").is_err());
-    }
+    // ==================== validate_batch ====================
 
-    #[test]
-    fn validate_url_localhost_blocked() {
-        assert!(validate_url("http://localhost").is_err());
-        assert!(validate_url("http://localhost:8080").is_err());
+    fn make_batch(size: usize) -> BatchScrapeRequest {
+        let requests = (0..size)
+            .map(|i| {
+                serde_json::from_value(serde_json::json!({"url": format!("https://example.com/{}", i)}))
+                    .unwrap()
+            })
+            .collect();
+        BatchScrapeRequest { requests }
     }
 
     #[test]
-    fn validate_url_localhost_subdomain_blocked() {
-        assert!(validate_url("http://api.localhost").is_err());
+    fn validate_batch_within_limit() {
+        assert!(validate_batch(&make_batch(1), 3).is_ok());
+        assert!(validate_batch(&make_batch(3), 3).is_ok());
     }
 
     #[test]
-    fn validate_url_private_ip_blocked() {
-        assert!(validate_url("http://127.0.0.1").is_err());
-        assert!(validate_url("http://10.0.0.1").is_err());
-        assert!(validate_url("http://192.168.1.1").is_err());
-        assert!(validate_url("http://172.16.0.1").is_err());
+    fn validate_batch_empty() {
+        let err = validate_batch(&make_batch(0), 3).unwrap_err();
+        assert!(err.to_string().contains("empty"));
     }
 
     #[test]
-    fn validate_url_public_ip_allowed() {
-        assert!(validate_url("http://8.8.8.8").is_ok());
+    fn validate_batch_over_limit() {
+        let err = validate_batch(&make_batch(4), 3).unwrap_err();
+        assert_eq!(err.code(), "INVALID_REQUEST");
+        assert!(err.to_string().contains("exceeds limit of 3"));
     }
 }
@@ -0,0 +1,454 @@
+use hmac::{Hmac, Mac};
+use reqwest::Client;
+use serde::Serialize;
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::task::AbortHandle;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::error::{AppError, AppResult};
+use crate::models::ScrapeResponse;
+use crate::ssrf::UrlGuard;
+
+pub const SIGNATURE_HEADER: &str = "x-distill-signature";
+/// Unix seconds at delivery; covered by the signature so receivers can reject replays
+pub const TIMESTAMP_HEADER: &str = "x-distill-timestamp";
+pub const JOB_ID_HEADER: &str = "x-distill-job-id";
+
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+const WEBHOOK_ATTEMPTS: u32 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+impl JobStatus {
+    pub fn is_finished(self) -> bool {
+        matches!(self, JobStatus::Completed | JobStatus::Failed | JobStatus::Cancelled)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Job {
+    pub id: String,
+    /// ID of the API key that created the job; other keys cannot see or cancel it
+    #[serde(skip)]
+    pub owner: String,
+    pub status: JobStatus,
+    pub url: String,
+    /// Unix epoch milliseconds
+    pub created_at: u64,
+    pub updated_at: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub callback_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<ScrapeResponse>,
+}
+
+/// Job persistence; the default store keeps everything in process memory
+pub trait JobStore: Send + Sync {
+    fn insert(&self, job: Job);
+
+    fn get(&self, id: &str) -> Option<Job>;
+
+    /// Moves a job to `status`. Returns `None` if the job is unknown or already finished,
+    /// so a late result can never overwrite a cancellation (and vice versa).
+    fn transition(&self, id: &str, status: JobStatus, result: Option<ScrapeResponse>) -> Option<Job>;
+
+    /// Drops finished jobs last updated before `before_ms`; returns how many were removed
+    fn prune(&self, before_ms: u64) -> usize;
+}
+
+#[derive(Default)]
+pub struct MemoryJobStore {
+    jobs: Mutex<HashMap<String, Job>>,
+}
+
+impl MemoryJobStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl JobStore for MemoryJobStore {
+    fn insert(&self, job: Job) {
+        self.jobs.lock().unwrap().insert(job.id.clone(), job);
+    }
+
+    fn get(&self, id: &str) -> Option<Job> {
+        self.jobs.lock().unwrap().get(id).cloned()
+    }
+
+    fn transition(&self, id: &str, status: JobStatus, result: Option<ScrapeResponse>) -> Option<Job> {
+        let mut jobs = self.jobs.lock().unwrap();
+        let job = jobs.get_mut(id)?;
+        if job.status.is_finished() {
+            return None;
+        }
+        job.status = status;
+        job.updated_at = now_millis();
+        if result.is_some() {
+            job.result = result;
+        }
+        Some(job.clone())
+    }
+
+    fn prune(&self, before_ms: u64) -> usize {
+        let mut jobs = self.jobs.lock().unwrap();
+        let before = jobs.len();
+        jobs.retain(|_, job| !job.status.is_finished() || job.updated_at >= before_ms);
+        before - jobs.len()
+    }
+}
+
+/// Tracks job state, running tasks and webhook delivery
+pub struct JobManager {
+    store: Arc<dyn JobStore>,
+    tasks: Mutex<HashMap<String, AbortHandle>>,
+    http_client: Client,
+    /// Without a secret deliveries could not be signed, so callbacks are refused
+    webhook_secret: Option<String>,
+    retention: Duration,
+}
+
+impl JobManager {
+    pub fn new(
+        store: Arc<dyn JobStore>,
+        webhook_secret: Option<String>,
+        retention: Duration,
+        url_guard: UrlGuard,
+    ) -> Self {
+        let http_client = Client::builder()
+            .timeout(WEBHOOK_TIMEOUT)
+            .redirect(reqwest::redirect::Policy::none())
+            // Deliveries connect only to addresses that passed the SSRF check
+            .dns_resolver(Arc::new(url_guard))
+            .build()
+            .expect("Failed to create HTTP client");
+
+        Self {
+            store,
+            tasks: Mutex::new(HashMap::new()),
+            http_client,
+            webhook_secret,
+            retention,
+        }
+    }
+
+    /// Fails with `InvalidRequest` unless webhooks can be signed
+    pub fn require_webhook_secret(&self) -> AppResult<()> {
+        match self.webhook_secret {
+            Some(_) => Ok(()),
+            None => Err(AppError::InvalidRequest(
+                "callback_url requires WEBHOOK_SECRET to be set on the server".to_string(),
+            )),
+        }
+    }
+
+    pub fn create(&self, owner: &str, url: &str, callback_url: Option<String>) -> Job {
+        let cutoff = now_millis().saturating_sub(self.retention.as_millis() as u64);
+        let pruned = self.store.prune(cutoff);
+        if pruned > 0 {
+            info!(pruned, "Pruned expired jobs");
+        }
+
+        let now = now_millis();
+        let job = Job {
+            id: Uuid::new_v4().to_string(),
+            owner: owner.to_string(),
+            status: JobStatus::Queued,
+            url: url.to_string(),
+            created_at: now,
+            updated_at: now,
+            callback_url,
+            result: None,
+        };
+        self.store.insert(job.clone());
+        job
+    }
+
+    /// Jobs owned by another key are reported as missing, not forbidden, so job IDs
+    /// cannot be probed across tenants
+    pub fn get(&self, owner: &str, id: &str) -> AppResult<Job> {
+        self.store
+            .get(id)
+            .filter(|job| job.owner == owner)
+            .ok_or_else(|| AppError::NotFound(format!("Job {}", id)))
+    }
+
+    /// Registers the task running `id` so it can be cancelled
+    pub fn attach(&self, id: &str, handle: AbortHandle) {
+        let mut tasks = self.tasks.lock().unwrap();
+        // The task may already have finished before we got here
+        if self.store.get(id).is_some_and(|job| !job.status.is_finished()) {
+            tasks.insert(id.to_string(), handle);
+        }
+    }
+
+    pub fn start(&self, id: &str) {
+        self.store.transition(id, JobStatus::Running, None);
+    }
+
+    /// Stores the result; returns the job unless it was cancelled meanwhile
+    pub fn finish(&self, id: &str, response: ScrapeResponse) -> Option<Job> {
+        let status = if response.success {
+            JobStatus::Completed
+        } else {
+            JobStatus::Failed
+        };
+        let mut tasks = self.tasks.lock().unwrap();
+        tasks.remove(id);
+        self.store.transition(id, status, Some(response))
+    }
+
+    /// Aborts a queued or running job. Finished jobs are returned unchanged.
+    pub fn cancel(&self, owner: &str, id: &str) -> AppResult<Job> {
+        self.get(owner, id)?;
+        let mut tasks = self.tasks.lock().unwrap();
+        if let Some(handle) = tasks.remove(id) {
+            handle.abort();
+        }
+        match self.store.transition(id, JobStatus::Cancelled, None) {
+            Some(job) => {
+                info!(job_id = id, "Job cancelled");
+                Ok(job)
+            }
+            None => self.get(owner, id),
+        }
+    }
+
+    /// POSTs the finished `ScrapeResponse` to the job's `callback_url`, if any
+    pub async fn notify(&self, job: &Job) {
+        let (Some(callback_url), Some(result)) = (&job.callback_url, &job.result) else {
+            return;
+        };
+        let Some(secret) = &self.webhook_secret else {
+            warn!(job_id = %job.id, "WEBHOOK_SECRET not set, webhook not sent");
+            return;
+        };
+
+        let body = match serde_json::to_vec(result) {
+            Ok(body) => body,
+            Err(e) => {
+                warn!(job_id = %job.id, error = %e, "Failed to serialize webhook body");
+                return;
+            }
+        };
+
+        for attempt in 1..=WEBHOOK_ATTEMPTS {
+            // Re-signed per attempt so retries carry a fresh timestamp
+            let timestamp = now_millis() / 1000;
+            let signature = sign_payload(secret, timestamp, &body);
+            let sent = self
+                .http_client
+                .post(callback_url)
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .header(JOB_ID_HEADER, &job.id)
+                .header(TIMESTAMP_HEADER, timestamp)
+                .header(SIGNATURE_HEADER, &signature)
+                .body(body.clone())
+                .send()
+                .await;
+
+            match sent {
+                Ok(resp) if resp.status().is_success() => {
+                    info!(job_id = %job.id, attempt, "Webhook delivered");
+                    return;
+                }
+                Ok(resp) => {
+                    warn!(job_id = %job.id, attempt, status = %resp.status(), "Webhook rejected");
+                }
+                Err(e) => {
+                    warn!(job_id = %job.id, attempt, error = %e, "Webhook delivery failed");
+                }
+            }
+
+            if attempt < WEBHOOK_ATTEMPTS {
+                tokio::time::sleep(Duration::from_secs(1 << (attempt - 1))).await;
+            }
+        }
+    }
+}
+
+/// `sha256=<hex HMAC-SHA256 of "<timestamp>.<body>">`
+pub fn sign_payload(secret: &str, timestamp: u64, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(format!("{}.", timestamp).as_bytes());
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ssrf::{IpPolicy, SystemResolver};
+
+    fn make_job(id: &str, status: JobStatus, updated_at: u64) -> Job {
+        Job {
+            id: id.to_string(),
+            owner: "key-a".to_string(),
+            status,
+            url: "https://example.com".to_string(),
+            created_at: updated_at,
+            updated_at,
+            callback_url: None,
+            result: None,
+        }
+    }
+
+    fn make_manager() -> JobManager {
+        JobManager::new(
+            Arc::new(MemoryJobStore::new()),
+            Some("secret".to_string()),
+            Duration::from_secs(3600),
+            UrlGuard::new(Arc::new(SystemResolver), IpPolicy::default()),
+        )
+    }
+
+    // ==================== MemoryJobStore ====================
+
+    #[test]
+    fn store_insert_and_get() {
+        let store = MemoryJobStore::new();
+        store.insert(make_job("a", JobStatus::Queued, 1));
+        assert_eq!(store.get("a").unwrap().status, JobStatus::Queued);
+        assert!(store.get("b").is_none());
+    }
+
+    #[test]
+    fn store_transition_sets_result() {
+        let store = MemoryJobStore::new();
+        store.insert(make_job("a", JobStatus::Running, 1));
+        let job = store
+            .transition("a", JobStatus::Failed, Some(ScrapeResponse::error("BROWSER_ERROR", "crashed")))
+            .unwrap();
+        assert_eq!(job.status, JobStatus::Failed);
+        assert!(job.result.is_some());
+        assert!(job.updated_at > 1);
+    }
+
+    #[test]
+    fn store_transition_ignores_finished() {
+        let store = MemoryJobStore::new();
+        store.insert(make_job("a", JobStatus::Cancelled, 1));
+        assert!(store.transition("a", JobStatus::Completed, None).is_none());
+        assert_eq!(store.get("a").unwrap().status, JobStatus::Cancelled);
+    }
+
+    #[test]
+    fn store_transition_unknown() {
+        let store = MemoryJobStore::new();
+        assert!(store.transition("missing", JobStatus::Running, None).is_none());
+    }
+
+    #[test]
+    fn store_prune_keeps_active_and_recent() {
+        let store = MemoryJobStore::new();
+        store.insert(make_job("old-done", JobStatus::Completed, 10));
+        store.insert(make_job("old-running", JobStatus::Running, 10));
+        store.insert(make_job("new-done", JobStatus::Failed, 100));
+        assert_eq!(store.prune(50), 1);
+        assert!(store.get("old-done").is_none());
+        assert!(store.get("old-running").is_some());
+        assert!(store.get("new-done").is_some());
+    }
+
+    // ==================== JobManager ====================
+
+    #[test]
+    fn manager_create_is_queued() {
+        let manager = make_manager();
+        let job = manager.create("key-a", "https://example.com", None);
+        assert_eq!(job.status, JobStatus::Queued);
+        assert_eq!(manager.get("key-a", &job.id).unwrap().url, "https://example.com");
+    }
+
+    #[test]
+    fn manager_get_unknown() {
+        let err = make_manager().get("key-a", "missing").unwrap_err();
+        assert_eq!(err.code(), "NOT_FOUND");
+    }
+
+    #[test]
+    fn manager_hides_other_keys_jobs() {
+        let manager = make_manager();
+        let job = manager.create("key-a", "https://example.com", None);
+
+        assert_eq!(manager.get("key-b", &job.id).unwrap_err().code(), "NOT_FOUND");
+        assert_eq!(manager.cancel("key-b", &job.id).unwrap_err().code(), "NOT_FOUND");
+        assert_eq!(manager.get("key-a", &job.id).unwrap().status, JobStatus::Queued);
+    }
+
+    #[test]
+    fn manager_cancel_then_finish() {
+        let manager = make_manager();
+        let job = manager.create("key-a", "https://example.com", None);
+        manager.start(&job.id);
+        assert_eq!(manager.cancel("key-a", &job.id).unwrap().status, JobStatus::Cancelled);
+        assert!(manager
+            .finish(&job.id, ScrapeResponse::error("BROWSER_ERROR", "late"))
+            .is_none());
+        assert!(manager.get("key-a", &job.id).unwrap().result.is_none());
+    }
+
+    #[test]
+    fn manager_cancel_finished_is_noop() {
+        let manager = make_manager();
+        let job = manager.create("key-a", "https://example.com", None);
+        manager.finish(&job.id, ScrapeResponse::error("BROWSER_ERROR", "crashed"));
+        assert_eq!(manager.cancel("key-a", &job.id).unwrap().status, JobStatus::Failed);
+    }
+
+    #[test]
+    fn manager_requires_secret_for_callbacks() {
+        assert!(make_manager().require_webhook_secret().is_ok());
+
+        let unsigned = JobManager {
+            webhook_secret: None,
+            ..make_manager()
+        };
+        match unsigned.require_webhook_secret() {
+            Err(AppError::InvalidRequest(message)) => assert!(message.contains("WEBHOOK_SECRET")),
+            other => panic!("expected InvalidRequest, got {:?}", other),
+        }
+    }
+
+    // ==================== sign_payload ====================
+
+    #[test]
+    fn sign_payload_known_vector() {
+        let signature = sign_payload("key", 1_700_000_000, b"The quick brown fox jumps over the lazy dog");
+        assert_eq!(
+            signature,
+            "sha256=2f658d6aef4f246e91cd741bbcded7479e9605f9d41c9e248122a117e0e1765b"
+        );
+    }
+
+    #[test]
+    fn sign_payload_covers_timestamp() {
+        let body = b"{}";
+        assert_ne!(sign_payload("key", 1_700_000_000, body), sign_payload("key", 1_700_000_001, body));
+    }
+
+    #[test]
+    fn job_status_serializes_snake_case() {
+        assert_eq!(serde_json::to_value(JobStatus::Cancelled).unwrap(), "cancelled");
+    }
+}
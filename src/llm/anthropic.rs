@@ -0,0 +1,260 @@
+use futures_util::future::BoxFuture;
+use reqwest::Client;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::time::Duration;
+use tracing::error;
+
+use super::{http_client, map_request_error, normalize_schema, provider_error, LlmProvider};
+use crate::error::AppResult;
+use crate::models::ProviderKind;
+
+const DEFAULT_ANTHROPIC_BASE_URL: &str = "https://api.anthropic.com";
+const DEFAULT_ANTHROPIC_MODEL: &str = "claude-sonnet-4-5";
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+const MAX_TOKENS: u32 = 8192;
+/// Forced tool whose input carries the structured answer
+const RESPONSE_TOOL: &str = "respond";
+/// Tool inputs must be objects; other schemas are wrapped under this key
+const WRAPPED_KEY: &str = "result";
+
+const KIND: ProviderKind = ProviderKind::Anthropic;
+
+/// Anthropic Messages API
+pub struct AnthropicProvider {
+    http_client: Client,
+    base_url: String,
+    api_key: Option<String>,
+    default_model: String,
+}
+
+impl AnthropicProvider {
+    pub fn new(base_url: String, api_key: Option<String>, default_model: String) -> Self {
+        Self {
+            http_client: http_client(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key,
+            default_model,
+        }
+    }
+
+    pub fn from_env() -> Self {
+        Self::new(
+            std::env::var("ANTHROPIC_BASE_URL")
+                .unwrap_or_else(|_| DEFAULT_ANTHROPIC_BASE_URL.to_string()),
+            std::env::var("ANTHROPIC_API_KEY").ok(),
+            std::env::var("ANTHROPIC_MODEL")
+                .unwrap_or_else(|_| DEFAULT_ANTHROPIC_MODEL.to_string()),
+        )
+    }
+
+    async fn generate(
+        &self,
+        model: &str,
+        prompt: &str,
+        response_schema: &Value,
+        timeout: Duration,
+    ) -> AppResult<Value> {
+        let api_key = self
+            .api_key
+            .as_ref()
+            .ok_or_else(|| provider_error(KIND, "API key not configured"))?;
+
+        let endpoint = format!("{}/v1/messages", self.base_url);
+        let (input_schema, wrapped) = tool_schema(response_schema);
+        let payload = build_payload(model, prompt, &input_schema);
+
+        let response = self
+            .http_client
+            .post(&endpoint)
+            .header("x-api-key", api_key)
+            .header("anthropic-version", ANTHROPIC_VERSION)
+            .json(&payload)
+            .timeout(timeout)
+            .send()
+            .await
+            .map_err(|e| map_request_error(KIND, "Request failed", e, timeout))?;
+
+        let status = response.status();
+        let body = response
+            .text()
+            .await
+            .map_err(|e| map_request_error(KIND, "Response read failed", e, timeout))?;
+
+        if !status.is_success() {
+            error!(status = %status, "Anthropic API error");
+            return Err(provider_error(KIND, format!("Status {}: {}", status, body)));
+        }
+
+        let message: MessagesResponse = serde_json::from_str(&body)
+            .map_err(|e| provider_error(KIND, format!("Parse failed: {}", e)))?;
+
+        extract_output(message, wrapped)
+    }
+}
+
+impl LlmProvider for AnthropicProvider {
+    fn kind(&self) -> ProviderKind {
+        KIND
+    }
+
+    fn default_model(&self) -> &str {
+        &self.default_model
+    }
+
+    fn is_configured(&self) -> bool {
+        self.api_key.is_some()
+    }
+
+    fn complete<'a>(
+        &'a self,
+        model: &'a str,
+        prompt: &'a str,
+        response_schema: &'a Value,
+        timeout: Duration,
+    ) -> BoxFuture<'a, AppResult<Value>> {
+        Box::pin(self.generate(model, prompt, response_schema, timeout))
+    }
+}
+
+/// Returns the tool input schema and whether the answer was wrapped in an object
+fn tool_schema(response_schema: &Value) -> (Value, bool) {
+    let schema = normalize_schema(response_schema);
+    if schema["type"] == "object" {
+        (schema, false)
+    } else {
+        let wrapped = json!({
+            "type": "object",
+            "properties": { WRAPPED_KEY: schema },
+            "required": [WRAPPED_KEY]
+        });
+        (wrapped, true)
+    }
+}
+
+fn build_payload(model: &str, prompt: &str, input_schema: &Value) -> Value {
+    json!({
+        "model": model,
+        "max_tokens": MAX_TOKENS,
+        "messages": [{
+            "role": "user",
+            "content": prompt
+        }],
+        "tools": [{
+            "name": RESPONSE_TOOL,
+            "description": "Return the answer as structured data.",
+            "input_schema": input_schema
+        }],
+        "tool_choice": { "type": "tool", "name": RESPONSE_TOOL }
+    })
+}
+
+fn extract_output(message: MessagesResponse, wrapped: bool) -> AppResult<Value> {
+    let input = message
+        .content
+        .into_iter()
+        .find_map(|block| match block {
+            ContentBlock::ToolUse { name, input } if name == RESPONSE_TOOL => Some(input),
+            _ => None,
+        })
+        .ok_or_else(|| {
+            provider_error(
+                KIND,
+                format!("No tool_use block (stop_reason: {})", message.stop_reason.unwrap_or_default()),
+            )
+        })?;
+
+    if !wrapped {
+        return Ok(input);
+    }
+    match input {
+        Value::Object(mut map) => map
+            .remove(WRAPPED_KEY)
+            .ok_or_else(|| provider_error(KIND, format!("Missing `{}` in tool input", WRAPPED_KEY))),
+        _ => Err(provider_error(KIND, "Tool input is not an object")),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct MessagesResponse {
+    content: Vec<ContentBlock>,
+    stop_reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ContentBlock {
+    ToolUse {
+        name: String,
+        input: Value,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(body: &str) -> MessagesResponse {
+        serde_json::from_str(body).unwrap()
+    }
+
+    // ==================== tool_schema ====================
+
+    #[test]
+    fn tool_schema_object_passthrough() {
+        let (schema, wrapped) = tool_schema(&json!({"type": "OBJECT", "properties": {}}));
+        assert!(!wrapped);
+        assert_eq!(schema["type"], "object");
+    }
+
+    #[test]
+    fn tool_schema_wraps_non_object() {
+        let (schema, wrapped) = tool_schema(&json!({"type": "array", "items": {"type": "string"}}));
+        assert!(wrapped);
+        assert_eq!(schema["properties"]["result"]["type"], "array");
+        assert_eq!(schema["required"], json!(["result"]));
+    }
+
+    // ==================== build_payload ====================
+
+    #[test]
+    fn payload_forces_response_tool() {
+        let payload = build_payload("claude-sonnet-4-5", "Summarize", &json!({"type": "object"}));
+
+        assert_eq!(payload["messages"][0]["content"], "Summarize");
+        assert_eq!(payload["tools"][0]["name"], RESPONSE_TOOL);
+        assert_eq!(payload["tools"][0]["input_schema"]["type"], "object");
+        assert_eq!(payload["tool_choice"]["name"], RESPONSE_TOOL);
+    }
+
+    // ==================== extract_output ====================
+
+    #[test]
+    fn extract_output_tool_input() {
+        let message = parse(
+            r#"{"content": [
+                {"type": "text", "text": "Here you go"},
+                {"type": "tool_use", "id": "toolu_1", "name": "respond", "input": {"title": "Hi"}}
+            ], "stop_reason": "tool_use"}"#,
+        );
+        assert_eq!(extract_output(message, false).unwrap()["title"], "Hi");
+    }
+
+    #[test]
+    fn extract_output_unwraps() {
+        let message = parse(
+            r#"{"content": [{"type": "tool_use", "id": "toolu_1", "name": "respond", "input": {"result": [1, 2]}}]}"#,
+        );
+        assert_eq!(extract_output(message, true).unwrap(), json!([1, 2]));
+    }
+
+    #[test]
+    fn extract_output_missing_tool_use() {
+        let message = parse(r#"{"content": [{"type": "text", "text": "No"}], "stop_reason": "max_tokens"}"#);
+        let err = extract_output(message, false).unwrap_err();
+        assert_eq!(err.code(), "LLM_PROVIDER_ERROR");
+        assert!(err.to_string().contains("max_tokens"));
+    }
+}
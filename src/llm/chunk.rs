@@ -0,0 +1,264 @@
+/// Rough token count: ~4 ASCII chars per token, one token per non-ASCII char
+/// (CJK text is close to that, and overestimating is the safe side)
+pub fn estimate_tokens(text: &str) -> usize {
+    let (ascii, other): (usize, usize) = text
+        .chars()
+        .fold((0, 0), |(a, o), c| if c.is_ascii() { (a + 1, o) } else { (a, o + 1) });
+    ascii.div_ceil(4) + other
+}
+
+/// Splits Markdown into chunks of at most `budget` estimated tokens.
+/// Breaks at headings and paragraphs, never inside a fenced code block unless
+/// the block alone exceeds the budget.
+pub fn split_markdown(content: &str, budget: usize) -> Vec<String> {
+    let budget = budget.max(1);
+    if estimate_tokens(content) <= budget {
+        return vec![content.to_string()];
+    }
+
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+    let mut current_tokens = 0;
+
+    for block in blocks(content) {
+        let tokens = estimate_tokens(&block.text);
+
+        // Prefer starting a new chunk at a heading once the current one is half full
+        let heading_break = block.heading && current_tokens >= budget / 2;
+        // +1 for the blank-line separator
+        if !current.is_empty() && (current_tokens + tokens + 1 > budget || heading_break) {
+            chunks.push(std::mem::take(&mut current));
+            current_tokens = 0;
+        }
+
+        if tokens > budget {
+            for piece in split_oversized(&block.text, budget) {
+                chunks.push(piece);
+            }
+            continue;
+        }
+
+        if !current.is_empty() {
+            current.push_str("\n\n");
+            current_tokens += 1;
+        }
+        current.push_str(&block.text);
+        current_tokens += tokens;
+    }
+
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+    chunks
+}
+
+struct Block {
+    text: String,
+    heading: bool,
+}
+
+/// Paragraph-level blocks; headings start a block and stay attached to the
+/// paragraph after them, fences are kept whole
+fn blocks(content: &str) -> Vec<Block> {
+    let mut blocks = Vec::new();
+    let mut lines: Vec<&str> = Vec::new();
+    let mut heading = false;
+    let mut in_fence = false;
+
+    let mut flush = |lines: &mut Vec<&str>, heading: bool| {
+        if !lines.is_empty() {
+            blocks.push(Block {
+                text: lines.join("\n"),
+                heading,
+            });
+            lines.clear();
+        }
+    };
+
+    for line in content.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+        } else if !in_fence {
+            if trimmed.is_empty() {
+                if heading && lines.len() == 1 {
+                    lines.push("");
+                    continue;
+                }
+                flush(&mut lines, heading);
+                heading = false;
+                continue;
+            }
+            if is_heading(trimmed) {
+                flush(&mut lines, heading);
+                heading = true;
+            }
+        }
+        lines.push(line);
+    }
+    flush(&mut lines, heading);
+
+    blocks
+}
+
+fn is_heading(line: &str) -> bool {
+    let hashes = line.chars().take_while(|&c| c == '#').count();
+    (1..=6).contains(&hashes) && line[hashes..].starts_with(' ')
+}
+
+/// Splits a single block by lines, then by characters when one line is too long
+fn split_oversized(text: &str, budget: usize) -> Vec<String> {
+    let mut pieces = Vec::new();
+    let mut current = String::new();
+    let mut current_tokens = 0;
+
+    for line in text.lines() {
+        let tokens = estimate_tokens(line);
+        if !current.is_empty() && current_tokens + tokens + 1 > budget {
+            pieces.push(std::mem::take(&mut current));
+            current_tokens = 0;
+        }
+
+        if tokens > budget {
+            let mut piece = String::new();
+            let (mut ascii, mut other) = (0usize, 0usize);
+            for c in line.chars() {
+                piece.push(c);
+                if c.is_ascii() {
+                    ascii += 1;
+                } else {
+                    other += 1;
+                }
+                if ascii.div_ceil(4) + other >= budget {
+                    pieces.push(std::mem::take(&mut piece));
+                    (ascii, other) = (0, 0);
+                }
+            }
+            if !piece.is_empty() {
+                current_tokens = estimate_tokens(&piece);
+                current = piece;
+            }
+            continue;
+        }
+
+        if !current.is_empty() {
+            current.push('\n');
+            current_tokens += 1;
+        }
+        current.push_str(line);
+        current_tokens += tokens;
+    }
+
+    if !current.is_empty() {
+        pieces.push(current);
+    }
+    pieces
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paragraph(word: &str, words: usize) -> String {
+        vec![word; words].join(" ")
+    }
+
+    // ==================== estimate_tokens ====================
+
+    #[test]
+    fn estimate_ascii() {
+        assert_eq!(estimate_tokens(""), 0);
+        assert_eq!(estimate_tokens("abcd"), 1);
+        assert_eq!(estimate_tokens("abcde"), 2);
+    }
+
+    #[test]
+    fn estimate_non_ascii_per_char() {
+        assert_eq!(estimate_tokens("안녕하세요"), 5);
+        assert_eq!(estimate_tokens("hi 세계"), 3);
+    }
+
+    // ==================== split_markdown ====================
+
+    #[test]
+    fn small_content_single_chunk() {
+        let content = "# Title\n\nShort paragraph.";
+        assert_eq!(split_markdown(content, 100), vec![content.to_string()]);
+    }
+
+    #[test]
+    fn chunks_respect_budget() {
+        let content = (0..20)
+            .map(|_| paragraph("word", 20))
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        let chunks = split_markdown(&content, 100);
+
+        assert!(chunks.len() > 1);
+        for chunk in &chunks {
+            assert!(estimate_tokens(chunk) <= 100, "chunk over budget: {}", estimate_tokens(chunk));
+        }
+        assert_eq!(chunks.join("\n\n"), content);
+    }
+
+    #[test]
+    fn prefers_heading_boundaries() {
+        let content = format!(
+            "# One\n\n{}\n\n# Two\n\n{}",
+            paragraph("aaaa", 50),
+            paragraph("bbbb", 50)
+        );
+        let chunks = split_markdown(&content, 100);
+
+        assert_eq!(chunks.len(), 2);
+        assert!(chunks[0].starts_with("# One\n\naaaa"));
+        assert!(chunks[1].starts_with("# Two\n\nbbbb"));
+    }
+
+    #[test]
+    fn heading_attached_to_following_paragraph() {
+        let content = format!("{}\n## Section\n{}", paragraph("aaaa", 60), paragraph("bbbb", 30));
+        let chunks = split_markdown(&content, 80);
+
+        assert_eq!(chunks.len(), 2);
+        assert!(chunks[1].starts_with("## Section\n"));
+    }
+
+    #[test]
+    fn keeps_code_fence_together() {
+        let code = "```\nline one\n\nline two\n```";
+        let content = format!("{}\n\n{}\n\n{}", paragraph("aaaa", 40), code, paragraph("bbbb", 40));
+        let chunks = split_markdown(&content, 50);
+
+        assert!(chunks.iter().any(|c| c.contains("line one\n\nline two")));
+    }
+
+    #[test]
+    fn oversized_paragraph_split() {
+        let content = format!("{}\n\n{}", paragraph("word", 400), paragraph("tail", 5));
+        let chunks = split_markdown(&content, 100);
+
+        assert!(chunks.len() >= 5);
+        for chunk in &chunks {
+            assert!(estimate_tokens(chunk) <= 100);
+        }
+        assert!(chunks.last().unwrap().contains("tail"));
+    }
+
+    #[test]
+    fn oversized_non_ascii_split_on_char_boundary() {
+        let content = "가".repeat(250);
+        let chunks = split_markdown(&content, 100);
+
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(chunks.concat(), content);
+    }
+
+    #[test]
+    fn is_heading_rules() {
+        assert!(is_heading("# Title"));
+        assert!(is_heading("###### Deep"));
+        assert!(!is_heading("####### Too deep"));
+        assert!(!is_heading("#hashtag"));
+    }
+}
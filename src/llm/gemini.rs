@@ -0,0 +1,428 @@
+use futures_util::future::BoxFuture;
+use futures_util::StreamExt;
+use reqwest::Client;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::time::Duration;
+use tracing::{error, warn};
+
+use super::{
+    http_client, map_request_error, parse_json_output, provider_error, LlmProvider, StreamUpdate, TokenSink,
+};
+use crate::error::{AppError, AppResult};
+use crate::models::ProviderKind;
+
+const GEMINI_API_BASE: &str = "https://generativelanguage.googleapis.com/v1beta/models";
+pub const DEFAULT_GEMINI_MODEL: &str = "gemini-3-flash-preview";
+
+const KIND: ProviderKind = ProviderKind::Gemini;
+
+pub struct GeminiProvider {
+    http_client: Client,
+    api_key: Option<String>,
+}
+
+impl GeminiProvider {
+    pub fn new(api_key: Option<String>) -> Self {
+        Self {
+            http_client: http_client(),
+            api_key,
+        }
+    }
+
+    pub fn from_env() -> Self {
+        let api_key = std::env::var("GEMINI_API_KEY").ok();
+
+        if api_key.is_none() {
+            warn!("GEMINI_API_KEY not set");
+        }
+
+        Self::new(api_key)
+    }
+
+    async fn generate(
+        &self,
+        model: &str,
+        prompt: &str,
+        response_schema: &Value,
+        timeout: Duration,
+    ) -> AppResult<Value> {
+        let api_key = self
+            .api_key
+            .as_ref()
+            .ok_or(AppError::GeminiKeyNotConfigured)?;
+
+        let endpoint = format!("{}/{}:generateContent?key={}", GEMINI_API_BASE, model, api_key);
+
+        let payload = build_payload(prompt, response_schema);
+
+        let response = self
+            .http_client
+            .post(&endpoint)
+            .json(&payload)
+            .timeout(timeout)
+            .send()
+            .await
+            .map_err(|e| map_request_error(KIND, "Request failed", e, timeout))?;
+
+        let status = response.status();
+        let body = response
+            .text()
+            .await
+            .map_err(|e| map_request_error(KIND, "Response read failed", e, timeout))?;
+
+        if !status.is_success() {
+            error!(status = %status, "Gemini API error");
+            return Err(provider_error(KIND, format!("Status {}: {}", status, body)));
+        }
+
+        let gemini_response: GeminiResponse = serde_json::from_str(&body)
+            .map_err(|e| provider_error(KIND, format!("Parse failed: {}", e)))?;
+
+        extract_output(gemini_response)
+    }
+
+    /// `streamGenerateContent` over SSE; each event carries the next slice of the JSON text
+    async fn generate_stream(
+        &self,
+        model: &str,
+        prompt: &str,
+        response_schema: &Value,
+        timeout: Duration,
+        on_token: &TokenSink,
+    ) -> AppResult<Value> {
+        let api_key = self
+            .api_key
+            .as_ref()
+            .ok_or(AppError::GeminiKeyNotConfigured)?;
+
+        let endpoint = format!(
+            "{}/{}:streamGenerateContent?alt=sse&key={}",
+            GEMINI_API_BASE, model, api_key
+        );
+
+        let payload = build_payload(prompt, response_schema);
+
+        let response = self
+            .http_client
+            .post(&endpoint)
+            .json(&payload)
+            .timeout(timeout)
+            .send()
+            .await
+            .map_err(|e| map_request_error(KIND, "Request failed", e, timeout))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            error!(status = %status, "Gemini API error");
+            return Err(provider_error(KIND, format!("Status {}: {}", status, body)));
+        }
+
+        let mut lines = SseLines::default();
+        let mut text = String::new();
+        let mut body = response.bytes_stream();
+        while let Some(bytes) = body.next().await {
+            let bytes = bytes.map_err(|e| map_request_error(KIND, "Stream read failed", e, timeout))?;
+            for line in lines.push(&bytes) {
+                if let Some(delta) = parse_stream_line(&line)? {
+                    on_token(StreamUpdate::Token(&delta));
+                    text.push_str(&delta);
+                }
+            }
+        }
+        if let Some(line) = lines.finish() {
+            if let Some(delta) = parse_stream_line(&line)? {
+                on_token(StreamUpdate::Token(&delta));
+                text.push_str(&delta);
+            }
+        }
+
+        parse_json_output(KIND, &text)
+    }
+}
+
+impl LlmProvider for GeminiProvider {
+    fn kind(&self) -> ProviderKind {
+        KIND
+    }
+
+    fn default_model(&self) -> &str {
+        DEFAULT_GEMINI_MODEL
+    }
+
+    fn is_configured(&self) -> bool {
+        self.api_key.is_some()
+    }
+
+    fn complete<'a>(
+        &'a self,
+        model: &'a str,
+        prompt: &'a str,
+        response_schema: &'a Value,
+        timeout: Duration,
+    ) -> BoxFuture<'a, AppResult<Value>> {
+        Box::pin(self.generate(model, prompt, response_schema, timeout))
+    }
+
+    fn complete_stream<'a>(
+        &'a self,
+        model: &'a str,
+        prompt: &'a str,
+        response_schema: &'a Value,
+        timeout: Duration,
+        on_token: &'a TokenSink,
+    ) -> BoxFuture<'a, AppResult<Value>> {
+        Box::pin(self.generate_stream(model, prompt, response_schema, timeout, on_token))
+    }
+}
+
+fn build_payload(prompt: &str, response_schema: &Value) -> Value {
+    json!({
+        "contents": [{
+            "parts": [{
+                "text": prompt
+            }]
+        }],
+        "generationConfig": {
+            "responseMimeType": "application/json",
+            "responseSchema": response_schema
+        }
+    })
+}
+
+fn extract_output(response: GeminiResponse) -> AppResult<Value> {
+    let text = response
+        .candidates
+        .into_iter()
+        .next()
+        .ok_or_else(|| provider_error(KIND, "No candidates"))?
+        .content
+        .parts
+        .into_iter()
+        .next()
+        .ok_or_else(|| provider_error(KIND, "No parts"))?
+        .text;
+
+    parse_json_output(KIND, &text)
+}
+
+/// Splits a byte stream into lines, keeping partial lines (and split UTF-8) until complete
+#[derive(Default)]
+struct SseLines {
+    buffer: Vec<u8>,
+}
+
+impl SseLines {
+    fn push(&mut self, bytes: &[u8]) -> Vec<String> {
+        self.buffer.extend_from_slice(bytes);
+        let mut lines = Vec::new();
+        while let Some(pos) = self.buffer.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=pos).collect();
+            lines.push(String::from_utf8_lossy(&line).trim_end().to_string());
+        }
+        lines
+    }
+
+    fn finish(self) -> Option<String> {
+        let line = String::from_utf8_lossy(&self.buffer).trim_end().to_string();
+        (!line.is_empty()).then_some(line)
+    }
+}
+
+/// Text delta of one SSE line; `None` for blank lines, comments and text-less events
+fn parse_stream_line(line: &str) -> AppResult<Option<String>> {
+    let Some(data) = line.strip_prefix("data:") else {
+        return Ok(None);
+    };
+    let chunk: StreamChunk = serde_json::from_str(data.trim())
+        .map_err(|e| provider_error(KIND, format!("Stream parse failed: {}", e)))?;
+
+    let text: String = chunk
+        .candidates
+        .into_iter()
+        .next()
+        .and_then(|candidate| candidate.content)
+        .map(|content| content.parts.into_iter().map(|part| part.text).collect())
+        .unwrap_or_default();
+    Ok((!text.is_empty()).then_some(text))
+}
+
+/// One `streamGenerateContent` event; the last one may carry only usage metadata
+#[derive(Debug, Deserialize)]
+struct StreamChunk {
+    #[serde(default)]
+    candidates: Vec<StreamCandidate>,
+}
+
+#[derive(Debug, Deserialize)]
+struct StreamCandidate {
+    content: Option<StreamContent>,
+}
+
+#[derive(Debug, Deserialize)]
+struct StreamContent {
+    #[serde(default)]
+    parts: Vec<StreamPart>,
+}
+
+#[derive(Debug, Deserialize)]
+struct StreamPart {
+    #[serde(default)]
+    text: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GeminiResponse {
+    candidates: Vec<Candidate>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Candidate {
+    content: Content,
+}
+
+#[derive(Debug, Deserialize)]
+struct Content {
+    parts: Vec<Part>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Part {
+    text: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // ==================== build_payload ====================
+
+    #[test]
+    fn payload_contains_prompt() {
+        let payload = build_payload("Extract entities\n\nHello world", &json!({"type": "object"}));
+
+        let text = payload["contents"][0]["parts"][0]["text"].as_str().unwrap();
+        assert!(text.contains("Extract entities"));
+        assert!(text.contains("Hello world"));
+    }
+
+    #[test]
+    fn payload_has_json_response_type() {
+        let payload = build_payload("Test", &json!({"type": "object"}));
+
+        let mime_type = payload["generationConfig"]["responseMimeType"].as_str().unwrap();
+        assert_eq!(mime_type, "application/json");
+    }
+
+    #[test]
+    fn payload_includes_response_schema() {
+        let schema = json!({"type": "object", "properties": {"name": {"type": "string"}}});
+        let payload = build_payload("Test", &schema);
+
+        let schema = &payload["generationConfig"]["responseSchema"];
+        assert_eq!(schema["type"], "object");
+        assert!(schema["properties"]["name"].is_object());
+    }
+
+    // ==================== extract_output ====================
+
+    #[test]
+    fn extract_output_valid_json() {
+        let response = GeminiResponse {
+            candidates: vec![Candidate {
+                content: Content {
+                    parts: vec![Part {
+                        text: r#"{"result": "success"}"#.to_string(),
+                    }],
+                },
+            }],
+        };
+
+        let output = extract_output(response).unwrap();
+        assert_eq!(output["result"], "success");
+    }
+
+    #[test]
+    fn extract_output_no_candidates() {
+        let response = GeminiResponse { candidates: vec![] };
+
+        let err = extract_output(response).unwrap_err();
+        assert!(err.to_string().contains("No candidates"));
+    }
+
+    #[test]
+    fn extract_output_no_parts() {
+        let response = GeminiResponse {
+            candidates: vec![Candidate {
+                content: Content { parts: vec![] },
+            }],
+        };
+
+        let err = extract_output(response).unwrap_err();
+        assert!(err.to_string().contains("No parts"));
+    }
+
+    #[test]
+    fn extract_output_invalid_json() {
+        let response = GeminiResponse {
+            candidates: vec![Candidate {
+                content: Content {
+                    parts: vec![Part {
+                        text: "not valid json".to_string(),
+                    }],
+                },
+            }],
+        };
+
+        let err = extract_output(response).unwrap_err();
+        assert!(err.to_string().contains("JSON parse failed"));
+    }
+
+    // ==================== streaming ====================
+
+    #[test]
+    fn sse_lines_split_across_pushes() {
+        let mut lines = SseLines::default();
+        assert!(lines.push(b"data: {\"a\"").is_empty());
+        assert_eq!(lines.push(b": 1}\r\n\r\ndata: x"), vec!["data: {\"a\": 1}", ""]);
+        assert_eq!(lines.finish(), Some("data: x".to_string()));
+    }
+
+    #[test]
+    fn sse_lines_keep_split_utf8() {
+        let bytes = "data: 안녕\n".as_bytes();
+        let mut lines = SseLines::default();
+        assert!(lines.push(&bytes[..8]).is_empty());
+        assert_eq!(lines.push(&bytes[8..]), vec!["data: 안녕"]);
+        assert_eq!(lines.finish(), None);
+    }
+
+    #[test]
+    fn stream_line_text_delta() {
+        let line = r#"data: {"candidates": [{"content": {"parts": [{"text": "{\"ti"}, {"text": "tle\""}], "role": "model"}}]}"#;
+        assert_eq!(parse_stream_line(line).unwrap(), Some(r#"{"title""#.to_string()));
+    }
+
+    #[test]
+    fn stream_line_without_text() {
+        assert_eq!(parse_stream_line("").unwrap(), None);
+        assert_eq!(parse_stream_line(": keep-alive").unwrap(), None);
+        let usage_only = r#"data: {"candidates": [{"finishReason": "STOP"}], "usageMetadata": {}}"#;
+        assert_eq!(parse_stream_line(usage_only).unwrap(), None);
+    }
+
+    #[test]
+    fn stream_line_invalid_json() {
+        let err = parse_stream_line("data: {oops").unwrap_err();
+        assert!(err.to_string().contains("gemini: Stream parse failed"));
+    }
+
+    // ==================== is_configured ====================
+
+    #[test]
+    fn is_configured_depends_on_key() {
+        assert!(GeminiProvider::new(Some("key".to_string())).is_configured());
+        assert!(!GeminiProvider::new(None).is_configured());
+    }
+}
@@ -0,0 +1,800 @@
+mod anthropic;
+mod chunk;
+mod gemini;
+mod openai;
+mod schema;
+
+use futures_util::future::BoxFuture;
+use futures_util::{stream, StreamExt};
+use serde_json::Value;
+use std::time::Duration;
+use tracing::{debug, warn};
+
+use crate::error::{AppError, AppResult, TimeoutPhase};
+use crate::models::{
+    AnalysisChunks, AnalysisError, AnalysisRequest, ChunkError, ProviderKind, SchemaViolation,
+};
+
+const DEFAULT_CHUNK_TOKENS: usize = 100_000;
+const MIN_CHUNK_TOKENS: usize = 1_000;
+const MAX_CHUNKS: usize = 50;
+const MAX_PARALLEL_CHUNKS: usize = 4;
+/// Violations quoted back to the model on retry
+const MAX_REPORTED_VIOLATIONS: usize = 20;
+
+pub use anthropic::AnthropicProvider;
+pub use gemini::GeminiProvider;
+pub use openai::OpenAiProvider;
+
+/// What a streamed analysis reports while the final call runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamUpdate<'a> {
+    /// Raw text delta of the model answer
+    Token(&'a str),
+    /// The answer so far failed `response_schema`; tokens of a new attempt follow
+    Retry,
+}
+
+/// Receives a streamed model answer as it arrives
+pub type TokenSink = dyn Fn(StreamUpdate<'_>) + Send + Sync;
+
+/// A chat/completions backend that returns JSON shaped by a response schema
+pub trait LlmProvider: Send + Sync {
+    fn kind(&self) -> ProviderKind;
+
+    fn default_model(&self) -> &str;
+
+    fn is_configured(&self) -> bool;
+
+    /// Sends one prompt and parses the model's JSON answer
+    fn complete<'a>(
+        &'a self,
+        model: &'a str,
+        prompt: &'a str,
+        response_schema: &'a Value,
+        timeout: Duration,
+    ) -> BoxFuture<'a, AppResult<Value>>;
+
+    /// Like `complete`, reporting text deltas to `on_token` as they arrive.
+    /// Providers without streaming support answer in one piece and report nothing.
+    fn complete_stream<'a>(
+        &'a self,
+        model: &'a str,
+        prompt: &'a str,
+        response_schema: &'a Value,
+        timeout: Duration,
+        _on_token: &'a TokenSink,
+    ) -> BoxFuture<'a, AppResult<Value>> {
+        self.complete(model, prompt, response_schema, timeout)
+    }
+}
+
+/// Final analysis result plus how the content was split to get it
+#[derive(Debug)]
+pub struct Analysis {
+    pub result: Value,
+    pub chunks: AnalysisChunks,
+}
+
+/// Configured providers, selected per request via `AnalysisRequest.provider`
+pub struct LlmRegistry {
+    providers: Vec<Box<dyn LlmProvider>>,
+    default_provider: ProviderKind,
+    /// Default per-chunk budget for map-reduce analysis
+    chunk_tokens: usize,
+}
+
+impl LlmRegistry {
+    pub fn new(
+        providers: Vec<Box<dyn LlmProvider>>,
+        default_provider: ProviderKind,
+        chunk_tokens: usize,
+    ) -> Self {
+        Self {
+            providers,
+            default_provider,
+            chunk_tokens,
+        }
+    }
+
+    pub fn from_env() -> Self {
+        let default_provider = std::env::var("LLM_DEFAULT_PROVIDER")
+            .ok()
+            .and_then(|v| serde_json::from_value(Value::String(v.to_lowercase())).ok())
+            .unwrap_or(ProviderKind::Gemini);
+        let chunk_tokens = std::env::var("ANALYSIS_CHUNK_TOKENS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_CHUNK_TOKENS);
+
+        Self::new(
+            vec![
+                Box::new(GeminiProvider::from_env()),
+                Box::new(OpenAiProvider::from_env()),
+                Box::new(AnthropicProvider::from_env()),
+            ],
+            default_provider,
+            chunk_tokens,
+        )
+    }
+
+    pub fn configured(&self) -> Vec<ProviderKind> {
+        self.providers
+            .iter()
+            .filter(|p| p.is_configured())
+            .map(|p| p.kind())
+            .collect()
+    }
+
+    pub fn default_provider(&self) -> ProviderKind {
+        self.default_provider
+    }
+
+    /// Picks the requested (or default) provider and checks it can be called
+    fn provider(&self, kind: Option<ProviderKind>) -> AppResult<&dyn LlmProvider> {
+        let kind = kind.unwrap_or(self.default_provider);
+        let provider = self
+            .providers
+            .iter()
+            .find(|p| p.kind() == kind)
+            .map(|p| p.as_ref())
+            .filter(|p| p.is_configured());
+
+        match (provider, kind) {
+            (Some(provider), _) => Ok(provider),
+            (None, ProviderKind::Gemini) => Err(AppError::GeminiKeyNotConfigured),
+            (None, kind) => Err(AppError::LlmProviderNotConfigured(kind.to_string())),
+        }
+    }
+
+    /// Analyzes `content`, splitting it into chunks (map) and merging the partial
+    /// results (reduce) when it exceeds the chunk budget. `timeout` bounds the whole run.
+    pub async fn analyze(
+        &self,
+        content: &str,
+        request: &AnalysisRequest,
+        timeout: Duration,
+    ) -> AppResult<Analysis> {
+        self.run_analysis(content, request, timeout, None).await
+    }
+
+    /// `analyze` that streams the tokens of the call producing the final result
+    /// (the single call, or the reduce call when the content was split)
+    pub async fn analyze_streaming(
+        &self,
+        content: &str,
+        request: &AnalysisRequest,
+        timeout: Duration,
+        on_token: &TokenSink,
+    ) -> AppResult<Analysis> {
+        self.run_analysis(content, request, timeout, Some(on_token)).await
+    }
+
+    async fn run_analysis(
+        &self,
+        content: &str,
+        request: &AnalysisRequest,
+        timeout: Duration,
+        on_token: Option<&TokenSink>,
+    ) -> AppResult<Analysis> {
+        let provider = self.provider(request.provider)?;
+        let model = request.model.as_deref().unwrap_or(provider.default_model());
+
+        let budget = request
+            .chunk_tokens
+            .unwrap_or(self.chunk_tokens)
+            .max(MIN_CHUNK_TOKENS);
+        let chunks = chunk::split_markdown(content, budget);
+        if chunks.len() > MAX_CHUNKS {
+            return Err(AppError::InvalidRequest(format!(
+                "Content needs {} chunks of {} tokens, limit is {}; raise chunk_tokens",
+                chunks.len(),
+                budget,
+                MAX_CHUNKS
+            )));
+        }
+
+        debug!(
+            provider = %provider.kind(),
+            model,
+            content_len = content.len(),
+            chunks = chunks.len(),
+            "Calling LLM provider"
+        );
+
+        let run = map_reduce(provider, model, request, &chunks, timeout, on_token);
+        tokio::time::timeout(timeout, run)
+            .await
+            .map_err(|_| {
+                AppError::Timeout(
+                    TimeoutPhase::Analysis,
+                    format!("Timeout after {}ms", timeout.as_millis()),
+                )
+            })?
+    }
+}
+
+async fn map_reduce(
+    provider: &dyn LlmProvider,
+    model: &str,
+    request: &AnalysisRequest,
+    chunks: &[String],
+    timeout: Duration,
+    on_token: Option<&TokenSink>,
+) -> AppResult<Analysis> {
+    let schema = &request.response_schema;
+
+    if let [content] = chunks {
+        let prompt = build_prompt(&request.prompt, content);
+        let result = complete_validated(provider, model, &prompt, schema, timeout, on_token).await?;
+        return Ok(Analysis {
+            result,
+            chunks: AnalysisChunks {
+                count: 1,
+                errors: Vec::new(),
+            },
+        });
+    }
+
+    let total = chunks.len();
+    let calls: Vec<BoxFuture<'_, AppResult<Value>>> = chunks
+        .iter()
+        .enumerate()
+        .map(|(index, content)| {
+            let prompt = build_chunk_prompt(&request.prompt, content, index, total);
+            Box::pin(async move {
+                complete_validated(provider, model, &prompt, schema, timeout, None).await
+            }) as BoxFuture<'_, _>
+        })
+        .collect();
+    let mapped: Vec<AppResult<Value>> = stream::iter(calls)
+        .buffered(MAX_PARALLEL_CHUNKS)
+        .collect()
+        .await;
+
+    let mut partials = Vec::new();
+    let mut errors = Vec::new();
+    let mut first_error = None;
+    for (index, result) in mapped.into_iter().enumerate() {
+        match result {
+            Ok(value) => partials.push(value),
+            Err(e) => {
+                warn!(chunk = index, error = %e, "Chunk analysis failed");
+                errors.push(ChunkError {
+                    chunk: index,
+                    error: AnalysisError::from(&e),
+                });
+                first_error.get_or_insert(e);
+            }
+        }
+    }
+
+    let result = match (partials.len(), first_error) {
+        (0, Some(e)) => return Err(e),
+        (1, _) => partials.remove(0),
+        _ => {
+            let prompt = build_reduce_prompt(&request.prompt, &partials, total);
+            complete_validated(provider, model, &prompt, schema, timeout, on_token).await?
+        }
+    };
+
+    Ok(Analysis {
+        result,
+        chunks: AnalysisChunks {
+            count: total,
+            errors,
+        },
+    })
+}
+
+/// Calls the provider and checks the answer against `response_schema`. On a
+/// mismatch the prompt is sent once more with the violations appended.
+async fn complete_validated(
+    provider: &dyn LlmProvider,
+    model: &str,
+    prompt: &str,
+    response_schema: &Value,
+    timeout: Duration,
+    on_token: Option<&TokenSink>,
+) -> AppResult<Value> {
+    let call = |prompt: String| async move {
+        match on_token {
+            Some(sink) => {
+                provider
+                    .complete_stream(model, &prompt, response_schema, timeout, sink)
+                    .await
+            }
+            None => provider.complete(model, &prompt, response_schema, timeout).await,
+        }
+    };
+
+    let result = call(prompt.to_string()).await?;
+    let violations = schema::validate(response_schema, &result);
+    if violations.is_empty() {
+        return Ok(result);
+    }
+
+    warn!(
+        provider = %provider.kind(),
+        violations = violations.len(),
+        "LLM output does not match response_schema, retrying"
+    );
+    if let Some(sink) = on_token {
+        sink(StreamUpdate::Retry);
+    }
+    let result = call(build_retry_prompt(prompt, &result, &violations)).await?;
+    let violations = schema::validate(response_schema, &result);
+    if violations.is_empty() {
+        Ok(result)
+    } else {
+        Err(AppError::SchemaValidation(violations))
+    }
+}
+
+fn build_prompt(prompt: &str, content: &str) -> String {
+    format!("{}\n\n---\n\nContent to analyze:\n\n{}", prompt, content)
+}
+
+fn build_chunk_prompt(prompt: &str, content: &str, index: usize, total: usize) -> String {
+    let note = format!(
+        "{}\n\nThe content below is part {} of {} of a longer document. \
+         Answer using only this part; another step will merge the parts.",
+        prompt,
+        index + 1,
+        total
+    );
+    build_prompt(&note, content)
+}
+
+fn build_reduce_prompt(prompt: &str, partials: &[Value], total: usize) -> String {
+    format!(
+        "{}\n\n---\n\nThe document was too long to analyze at once, so it was split into {} parts \
+         that were analyzed separately. Below are the partial results as a JSON array, in document order. \
+         Merge them into a single result for the whole document: combine lists without duplicates \
+         and prefer the most complete values.\n\nPartial results:\n\n{}",
+        prompt,
+        total,
+        Value::Array(partials.to_vec())
+    )
+}
+
+fn build_retry_prompt(prompt: &str, previous: &Value, violations: &[SchemaViolation]) -> String {
+    let listed = violations
+        .iter()
+        .take(MAX_REPORTED_VIOLATIONS)
+        .map(|v| format!("- {}: {}", v.path, v.message))
+        .collect::<Vec<_>>()
+        .join("\n");
+    format!(
+        "{}\n\n---\n\nYour previous answer did not match the required response schema:\n\n{}\n\n\
+         Previous answer:\n\n{}\n\nAnswer again, fixing these errors.",
+        prompt, listed, previous
+    )
+}
+
+/// Parses the model's text answer, tolerating a surrounding Markdown code fence
+fn parse_json_output(kind: ProviderKind, text: &str) -> AppResult<Value> {
+    let trimmed = text.trim();
+    let unfenced = trimmed
+        .strip_prefix("```json")
+        .or_else(|| trimmed.strip_prefix("```"))
+        .and_then(|rest| rest.strip_suffix("```"))
+        .unwrap_or(trimmed);
+
+    serde_json::from_str(unfenced.trim())
+        .map_err(|e| provider_error(kind, format!("JSON parse failed: {}", e)))
+}
+
+fn provider_error(kind: ProviderKind, message: impl std::fmt::Display) -> AppError {
+    AppError::LlmProvider(format!("{}: {}", kind, message))
+}
+
+fn map_request_error(
+    kind: ProviderKind,
+    context: &str,
+    error: reqwest::Error,
+    timeout: Duration,
+) -> AppError {
+    if error.is_timeout() {
+        AppError::Timeout(
+            TimeoutPhase::Analysis,
+            format!("Timeout after {}ms", timeout.as_millis()),
+        )
+    } else {
+        provider_error(kind, format!("{}: {}", context, error))
+    }
+}
+
+/// Lower-cases `type` keywords so Gemini-style schemas (`"STRING"`) work with JSON Schema providers
+fn normalize_schema(schema: &Value) -> Value {
+    match schema {
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(key, value)| {
+                    let value = match (key.as_str(), value) {
+                        ("type", Value::String(t)) => Value::String(t.to_lowercase()),
+                        ("properties", Value::Object(props)) => Value::Object(
+                            props
+                                .iter()
+                                .map(|(name, prop)| (name.clone(), normalize_schema(prop)))
+                                .collect(),
+                        ),
+                        _ => normalize_schema(value),
+                    };
+                    (key.clone(), value)
+                })
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.iter().map(normalize_schema).collect()),
+        other => other.clone(),
+    }
+}
+
+fn http_client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .connect_timeout(Duration::from_secs(10))
+        .build()
+        .expect("Failed to create HTTP client")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::sync::{Arc, Mutex};
+
+    /// Records prompts; fails any prompt containing `fail_marker`
+    struct StubProvider {
+        prompts: Mutex<Vec<String>>,
+        fail_marker: Option<&'static str>,
+    }
+
+    impl StubProvider {
+        fn new(fail_marker: Option<&'static str>) -> Self {
+            Self {
+                prompts: Mutex::new(Vec::new()),
+                fail_marker,
+            }
+        }
+    }
+
+    impl LlmProvider for StubProvider {
+        fn kind(&self) -> ProviderKind {
+            ProviderKind::Openai
+        }
+
+        fn default_model(&self) -> &str {
+            "stub"
+        }
+
+        fn is_configured(&self) -> bool {
+            true
+        }
+
+        fn complete<'a>(
+            &'a self,
+            _model: &'a str,
+            prompt: &'a str,
+            _response_schema: &'a Value,
+            _timeout: Duration,
+        ) -> BoxFuture<'a, AppResult<Value>> {
+            self.prompts.lock().unwrap().push(prompt.to_string());
+            let result = if self.fail_marker.is_some_and(|m| prompt.contains(m)) {
+                Err(provider_error(ProviderKind::Openai, "Status 500: boom"))
+            } else if prompt.contains("Partial results") {
+                Ok(json!({"merged": true}))
+            } else {
+                Ok(json!({"chars": prompt.len()}))
+            };
+            Box::pin(async move { result })
+        }
+    }
+
+    /// Returns `answers` in order and records prompts
+    struct ScriptedProvider {
+        answers: Mutex<Vec<Value>>,
+        prompts: Mutex<Vec<String>>,
+    }
+
+    impl ScriptedProvider {
+        fn new(answers: Vec<Value>) -> Self {
+            Self {
+                answers: Mutex::new(answers),
+                prompts: Mutex::new(Vec::new()),
+            }
+        }
+    }
+
+    impl LlmProvider for ScriptedProvider {
+        fn kind(&self) -> ProviderKind {
+            ProviderKind::Anthropic
+        }
+
+        fn default_model(&self) -> &str {
+            "scripted"
+        }
+
+        fn is_configured(&self) -> bool {
+            true
+        }
+
+        fn complete<'a>(
+            &'a self,
+            _model: &'a str,
+            prompt: &'a str,
+            _response_schema: &'a Value,
+            _timeout: Duration,
+        ) -> BoxFuture<'a, AppResult<Value>> {
+            self.prompts.lock().unwrap().push(prompt.to_string());
+            let answer = self.answers.lock().unwrap().remove(0);
+            Box::pin(async move { Ok(answer) })
+        }
+    }
+
+    fn title_schema() -> Value {
+        json!({
+            "type": "OBJECT",
+            "properties": {"title": {"type": "STRING"}},
+            "required": ["title"]
+        })
+    }
+
+    fn make_analysis_request() -> AnalysisRequest {
+        AnalysisRequest {
+            provider: None,
+            model: None,
+            prompt: "Extract".to_string(),
+            response_schema: json!({"type": "object"}),
+            chunk_tokens: None,
+        }
+    }
+
+    fn make_chunks(parts: &[&str]) -> Vec<String> {
+        parts.iter().map(|p| p.to_string()).collect()
+    }
+
+    fn make_registry(default_provider: ProviderKind) -> LlmRegistry {
+        LlmRegistry::new(
+            vec![
+                Box::new(GeminiProvider::new(None)),
+                Box::new(OpenAiProvider::new(
+                    "http://localhost:11434/v1".to_string(),
+                    None,
+                    "llama3.1".to_string(),
+                )),
+                Box::new(AnthropicProvider::new(
+                    "https://api.anthropic.com".to_string(),
+                    None,
+                    "claude-sonnet-4-5".to_string(),
+                )),
+            ],
+            default_provider,
+            DEFAULT_CHUNK_TOKENS,
+        )
+    }
+
+    // ==================== LlmRegistry ====================
+
+    #[test]
+    fn registry_selects_requested_provider() {
+        let registry = make_registry(ProviderKind::Gemini);
+        let provider = registry.provider(Some(ProviderKind::Openai)).unwrap();
+        assert_eq!(provider.kind(), ProviderKind::Openai);
+        assert_eq!(provider.default_model(), "llama3.1");
+    }
+
+    #[test]
+    fn registry_uses_default_provider() {
+        let registry = make_registry(ProviderKind::Openai);
+        assert_eq!(registry.provider(None).unwrap().kind(), ProviderKind::Openai);
+    }
+
+    #[test]
+    fn registry_gemini_not_configured() {
+        let registry = make_registry(ProviderKind::Gemini);
+        let err = registry.provider(None).err().unwrap();
+        assert_eq!(err.code(), "GEMINI_KEY_NOT_CONFIGURED");
+    }
+
+    #[test]
+    fn registry_other_provider_not_configured() {
+        let registry = make_registry(ProviderKind::Gemini);
+        let err = registry.provider(Some(ProviderKind::Anthropic)).err().unwrap();
+        assert_eq!(err.code(), "LLM_PROVIDER_NOT_CONFIGURED");
+        assert!(err.to_string().contains("anthropic"));
+    }
+
+    #[test]
+    fn registry_lists_configured() {
+        let registry = make_registry(ProviderKind::Gemini);
+        assert_eq!(registry.configured(), vec![ProviderKind::Openai]);
+    }
+
+    // ==================== map_reduce ====================
+
+    #[tokio::test]
+    async fn map_reduce_single_chunk_skips_reduce() {
+        let provider = StubProvider::new(None);
+        let analysis = map_reduce(&provider, "stub", &make_analysis_request(), &make_chunks(&["only"]), Duration::from_secs(1), None)
+            .await
+            .unwrap();
+
+        assert_eq!(analysis.chunks.count, 1);
+        assert_eq!(provider.prompts.lock().unwrap().len(), 1);
+        assert!(analysis.result.get("chars").is_some());
+    }
+
+    #[tokio::test]
+    async fn map_reduce_merges_chunks() {
+        let provider = StubProvider::new(None);
+        let chunks = make_chunks(&["first", "second", "third"]);
+        let analysis = map_reduce(&provider, "stub", &make_analysis_request(), &chunks, Duration::from_secs(1), None)
+            .await
+            .unwrap();
+
+        assert_eq!(analysis.result, json!({"merged": true}));
+        assert_eq!(analysis.chunks.count, 3);
+        assert!(analysis.chunks.errors.is_empty());
+
+        let prompts = provider.prompts.lock().unwrap();
+        assert_eq!(prompts.len(), 4);
+        assert!(prompts[0].contains("part 1 of 3") && prompts[0].contains("first"));
+        assert!(prompts[3].contains("split into 3 parts"));
+    }
+
+    #[tokio::test]
+    async fn map_reduce_reports_chunk_errors() {
+        let provider = StubProvider::new(Some("second"));
+        let chunks = make_chunks(&["first", "second", "third"]);
+        let analysis = map_reduce(&provider, "stub", &make_analysis_request(), &chunks, Duration::from_secs(1), None)
+            .await
+            .unwrap();
+
+        assert_eq!(analysis.result, json!({"merged": true}));
+        assert_eq!(analysis.chunks.errors.len(), 1);
+        assert_eq!(analysis.chunks.errors[0].chunk, 1);
+        assert_eq!(analysis.chunks.errors[0].error.code, "LLM_PROVIDER_ERROR");
+        assert!(analysis.chunks.errors[0].error.message.contains("boom"));
+    }
+
+    #[tokio::test]
+    async fn map_reduce_single_survivor_skips_reduce() {
+        let provider = StubProvider::new(Some("second"));
+        let chunks = make_chunks(&["first", "second"]);
+        let analysis = map_reduce(&provider, "stub", &make_analysis_request(), &chunks, Duration::from_secs(1), None)
+            .await
+            .unwrap();
+
+        assert!(analysis.result.get("chars").is_some());
+        assert_eq!(provider.prompts.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn map_reduce_all_chunks_failed() {
+        let provider = StubProvider::new(Some("part"));
+        let chunks = make_chunks(&["first", "second"]);
+        let err = map_reduce(&provider, "stub", &make_analysis_request(), &chunks, Duration::from_secs(1), None)
+            .await
+            .unwrap_err();
+
+        assert_eq!(err.code(), "LLM_PROVIDER_ERROR");
+    }
+
+    // ==================== complete_validated ====================
+
+    #[tokio::test]
+    async fn complete_validated_valid_first_try() {
+        let provider = ScriptedProvider::new(vec![json!({"title": "Hi"})]);
+        let result = complete_validated(&provider, "scripted", "Extract", &title_schema(), Duration::from_secs(1), None)
+            .await
+            .unwrap();
+
+        assert_eq!(result["title"], "Hi");
+        assert_eq!(provider.prompts.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn complete_validated_retries_with_violations() {
+        let provider = ScriptedProvider::new(vec![json!({"title": 42}), json!({"title": "Hi"})]);
+        let result = complete_validated(&provider, "scripted", "Extract", &title_schema(), Duration::from_secs(1), None)
+            .await
+            .unwrap();
+
+        assert_eq!(result["title"], "Hi");
+        let prompts = provider.prompts.lock().unwrap();
+        assert_eq!(prompts.len(), 2);
+        assert!(prompts[1].starts_with("Extract"));
+        assert!(prompts[1].contains("- $.title: expected string, got number"));
+        assert!(prompts[1].contains(r#"{"title":42}"#));
+    }
+
+    #[tokio::test]
+    async fn complete_validated_signals_retry_to_stream() {
+        let provider = ScriptedProvider::new(vec![json!({"title": 42}), json!({"title": "Hi"})]);
+        let updates = Arc::new(Mutex::new(Vec::new()));
+        let seen = updates.clone();
+        let sink = move |update: StreamUpdate<'_>| {
+            seen.lock().unwrap().push(update == StreamUpdate::Retry);
+        };
+        complete_validated(&provider, "scripted", "Extract", &title_schema(), Duration::from_secs(1), Some(&sink))
+            .await
+            .unwrap();
+
+        // ScriptedProvider does not stream, so the retry is the only update
+        assert_eq!(*updates.lock().unwrap(), vec![true]);
+    }
+
+    #[tokio::test]
+    async fn complete_validated_fails_after_one_retry() {
+        let provider = ScriptedProvider::new(vec![json!({}), json!({"name": "x"})]);
+        let err = complete_validated(&provider, "scripted", "Extract", &title_schema(), Duration::from_secs(1), None)
+            .await
+            .unwrap_err();
+
+        assert_eq!(provider.prompts.lock().unwrap().len(), 2);
+        match err {
+            AppError::SchemaValidation(violations) => {
+                assert_eq!(violations.len(), 1);
+                assert_eq!(violations[0].path, "$");
+                assert_eq!(violations[0].message, "missing required property `title`");
+            }
+            other => panic!("unexpected error: {}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn analyze_rejects_too_many_chunks() {
+        let registry = make_registry(ProviderKind::Openai);
+        let content = vec!["word ".repeat(1_000); MAX_CHUNKS + 1].join("\n\n");
+        let request = AnalysisRequest {
+            chunk_tokens: Some(MIN_CHUNK_TOKENS),
+            ..make_analysis_request()
+        };
+
+        let err = registry.analyze(&content, &request, Duration::from_secs(1)).await.unwrap_err();
+        assert_eq!(err.code(), "INVALID_REQUEST");
+    }
+
+    // ==================== parse_json_output ====================
+
+    #[test]
+    fn parse_json_plain() {
+        let value = parse_json_output(ProviderKind::Openai, r#"{"a": 1}"#).unwrap();
+        assert_eq!(value["a"], 1);
+    }
+
+    #[test]
+    fn parse_json_fenced() {
+        let value = parse_json_output(ProviderKind::Openai, "```json\n{\"a\": 1}\n```").unwrap();
+        assert_eq!(value["a"], 1);
+        let value = parse_json_output(ProviderKind::Openai, "```\n[1, 2]\n```").unwrap();
+        assert_eq!(value, json!([1, 2]));
+    }
+
+    #[test]
+    fn parse_json_invalid_names_provider() {
+        let err = parse_json_output(ProviderKind::Anthropic, "not json").unwrap_err();
+        assert_eq!(err.code(), "LLM_PROVIDER_ERROR");
+        assert!(err.to_string().contains("anthropic: JSON parse failed"));
+    }
+
+    // ==================== normalize_schema ====================
+
+    #[test]
+    fn normalize_schema_lowercases_types() {
+        let schema = json!({
+            "type": "OBJECT",
+            "properties": {
+                "type": {"type": "STRING", "enum": ["A", "B"]},
+                "tags": {"type": "ARRAY", "items": {"type": "STRING"}}
+            },
+            "required": ["type"]
+        });
+        let normalized = normalize_schema(&schema);
+        assert_eq!(normalized["type"], "object");
+        assert_eq!(normalized["properties"]["type"]["type"], "string");
+        assert_eq!(normalized["properties"]["type"]["enum"], json!(["A", "B"]));
+        assert_eq!(normalized["properties"]["tags"]["items"]["type"], "string");
+        assert_eq!(normalized["required"], json!(["type"]));
+    }
+}
@@ -0,0 +1,212 @@
+use futures_util::future::BoxFuture;
+use reqwest::Client;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::time::Duration;
+use tracing::error;
+
+use super::{
+    http_client, map_request_error, normalize_schema, parse_json_output, provider_error,
+    LlmProvider,
+};
+use crate::error::AppResult;
+use crate::models::ProviderKind;
+
+const DEFAULT_OPENAI_BASE_URL: &str = "https://api.openai.com/v1";
+const DEFAULT_OPENAI_MODEL: &str = "gpt-4o-mini";
+
+const KIND: ProviderKind = ProviderKind::Openai;
+
+/// OpenAI-compatible `/chat/completions` (OpenAI, vLLM, Ollama, ...)
+pub struct OpenAiProvider {
+    http_client: Client,
+    base_url: String,
+    api_key: Option<String>,
+    default_model: String,
+}
+
+impl OpenAiProvider {
+    pub fn new(base_url: String, api_key: Option<String>, default_model: String) -> Self {
+        Self {
+            http_client: http_client(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key,
+            default_model,
+        }
+    }
+
+    pub fn from_env() -> Self {
+        Self::new(
+            std::env::var("OPENAI_BASE_URL").unwrap_or_else(|_| DEFAULT_OPENAI_BASE_URL.to_string()),
+            std::env::var("OPENAI_API_KEY").ok(),
+            std::env::var("OPENAI_MODEL").unwrap_or_else(|_| DEFAULT_OPENAI_MODEL.to_string()),
+        )
+    }
+
+    async fn generate(
+        &self,
+        model: &str,
+        prompt: &str,
+        response_schema: &Value,
+        timeout: Duration,
+    ) -> AppResult<Value> {
+        let endpoint = format!("{}/chat/completions", self.base_url);
+        let payload = build_payload(model, prompt, response_schema);
+
+        let mut request = self.http_client.post(&endpoint).json(&payload).timeout(timeout);
+        if let Some(api_key) = &self.api_key {
+            request = request.bearer_auth(api_key);
+        }
+
+        let response = request
+            .send()
+            .await
+            .map_err(|e| map_request_error(KIND, "Request failed", e, timeout))?;
+
+        let status = response.status();
+        let body = response
+            .text()
+            .await
+            .map_err(|e| map_request_error(KIND, "Response read failed", e, timeout))?;
+
+        if !status.is_success() {
+            error!(status = %status, "OpenAI-compatible API error");
+            return Err(provider_error(KIND, format!("Status {}: {}", status, body)));
+        }
+
+        let completion: ChatCompletion = serde_json::from_str(&body)
+            .map_err(|e| provider_error(KIND, format!("Parse failed: {}", e)))?;
+
+        extract_output(completion)
+    }
+}
+
+impl LlmProvider for OpenAiProvider {
+    fn kind(&self) -> ProviderKind {
+        KIND
+    }
+
+    fn default_model(&self) -> &str {
+        &self.default_model
+    }
+
+    /// Local servers need no key, so a custom base URL is enough
+    fn is_configured(&self) -> bool {
+        self.api_key.is_some() || self.base_url != DEFAULT_OPENAI_BASE_URL
+    }
+
+    fn complete<'a>(
+        &'a self,
+        model: &'a str,
+        prompt: &'a str,
+        response_schema: &'a Value,
+        timeout: Duration,
+    ) -> BoxFuture<'a, AppResult<Value>> {
+        Box::pin(self.generate(model, prompt, response_schema, timeout))
+    }
+}
+
+fn build_payload(model: &str, prompt: &str, response_schema: &Value) -> Value {
+    json!({
+        "model": model,
+        "messages": [{
+            "role": "user",
+            "content": prompt
+        }],
+        "response_format": {
+            "type": "json_schema",
+            "json_schema": {
+                "name": "response",
+                "schema": normalize_schema(response_schema)
+            }
+        }
+    })
+}
+
+fn extract_output(completion: ChatCompletion) -> AppResult<Value> {
+    let choice = completion
+        .choices
+        .into_iter()
+        .next()
+        .ok_or_else(|| provider_error(KIND, "No choices"))?;
+
+    if let Some(refusal) = choice.message.refusal {
+        return Err(provider_error(KIND, format!("Refused: {}", refusal)));
+    }
+    let text = choice
+        .message
+        .content
+        .ok_or_else(|| provider_error(KIND, "Empty message"))?;
+
+    parse_json_output(KIND, &text)
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatCompletion {
+    choices: Vec<Choice>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Choice {
+    message: Message,
+}
+
+#[derive(Debug, Deserialize)]
+struct Message {
+    content: Option<String>,
+    refusal: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(body: &str) -> ChatCompletion {
+        serde_json::from_str(body).unwrap()
+    }
+
+    // ==================== build_payload ====================
+
+    #[test]
+    fn payload_shape() {
+        let payload = build_payload("gpt-4o-mini", "Summarize", &json!({"type": "OBJECT"}));
+
+        assert_eq!(payload["model"], "gpt-4o-mini");
+        assert_eq!(payload["messages"][0]["role"], "user");
+        assert_eq!(payload["messages"][0]["content"], "Summarize");
+        assert_eq!(payload["response_format"]["type"], "json_schema");
+        assert_eq!(payload["response_format"]["json_schema"]["schema"]["type"], "object");
+    }
+
+    // ==================== extract_output ====================
+
+    #[test]
+    fn extract_output_valid_json() {
+        let completion = parse(r#"{"choices": [{"message": {"role": "assistant", "content": "{\"ok\": true}"}}]}"#);
+        assert_eq!(extract_output(completion).unwrap()["ok"], true);
+    }
+
+    #[test]
+    fn extract_output_no_choices() {
+        let err = extract_output(parse(r#"{"choices": []}"#)).unwrap_err();
+        assert!(err.to_string().contains("openai: No choices"));
+    }
+
+    #[test]
+    fn extract_output_refusal() {
+        let completion = parse(r#"{"choices": [{"message": {"content": null, "refusal": "Cannot help"}}]}"#);
+        let err = extract_output(completion).unwrap_err();
+        assert_eq!(err.code(), "LLM_PROVIDER_ERROR");
+        assert!(err.to_string().contains("Cannot help"));
+    }
+
+    // ==================== is_configured ====================
+
+    #[test]
+    fn configured_by_key_or_custom_base_url() {
+        let model = DEFAULT_OPENAI_MODEL.to_string();
+        assert!(!OpenAiProvider::new(DEFAULT_OPENAI_BASE_URL.to_string(), None, model.clone()).is_configured());
+        assert!(OpenAiProvider::new(DEFAULT_OPENAI_BASE_URL.to_string(), Some("sk".to_string()), model.clone()).is_configured());
+        assert!(OpenAiProvider::new("http://vllm:8000/v1/".to_string(), None, model).is_configured());
+    }
+}
@@ -0,0 +1,204 @@
+use serde_json::Value;
+
+use crate::models::SchemaViolation;
+
+/// Validates `value` against a JSON Schema subset: `type` (case-insensitive, single
+/// or list, plus Gemini's `nullable`), `required`, `properties`, `enum` and `items`.
+/// Unknown keywords are ignored.
+pub fn validate(schema: &Value, value: &Value) -> Vec<SchemaViolation> {
+    let mut violations = Vec::new();
+    check(schema, value, "$", &mut violations);
+    violations
+}
+
+fn check(schema: &Value, value: &Value, path: &str, out: &mut Vec<SchemaViolation>) {
+    let Some(schema) = schema.as_object() else {
+        return;
+    };
+
+    if value.is_null() && schema.get("nullable") == Some(&Value::Bool(true)) {
+        return;
+    }
+
+    let allowed: Vec<String> = match schema.get("type") {
+        Some(Value::String(t)) => vec![t.to_lowercase()],
+        Some(Value::Array(types)) => types
+            .iter()
+            .filter_map(Value::as_str)
+            .map(str::to_lowercase)
+            .collect(),
+        _ => Vec::new(),
+    };
+    if !allowed.is_empty() && !allowed.iter().any(|t| type_matches(t, value)) {
+        out.push(violation(
+            path,
+            format!("expected {}, got {}", allowed.join(" or "), type_name(value)),
+        ));
+        return;
+    }
+
+    if let Some(Value::Array(options)) = schema.get("enum") {
+        if !options.contains(value) {
+            out.push(violation(
+                path,
+                format!("must be one of {}", Value::Array(options.clone())),
+            ));
+        }
+    }
+
+    if let Value::Object(map) = value {
+        if let Some(Value::Array(required)) = schema.get("required") {
+            for name in required.iter().filter_map(Value::as_str) {
+                if !map.contains_key(name) {
+                    out.push(violation(path, format!("missing required property `{}`", name)));
+                }
+            }
+        }
+        if let Some(Value::Object(properties)) = schema.get("properties") {
+            for (name, property) in properties {
+                if let Some(field) = map.get(name) {
+                    check(property, field, &format!("{}.{}", path, name), out);
+                }
+            }
+        }
+    }
+
+    if let (Value::Array(items), Some(item_schema)) = (value, schema.get("items")) {
+        for (index, item) in items.iter().enumerate() {
+            check(item_schema, item, &format!("{}[{}]", path, index), out);
+        }
+    }
+}
+
+fn type_matches(expected: &str, value: &Value) -> bool {
+    match expected {
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => {
+            value.is_i64() || value.is_u64() || value.as_f64().is_some_and(|f| f.fract() == 0.0)
+        }
+        "boolean" => value.is_boolean(),
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "null" => value.is_null(),
+        _ => true,
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn violation(path: &str, message: String) -> SchemaViolation {
+    SchemaViolation {
+        path: path.to_string(),
+        message,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn paths(violations: &[SchemaViolation]) -> Vec<&str> {
+        violations.iter().map(|v| v.path.as_str()).collect()
+    }
+
+    // ==================== type ====================
+
+    #[test]
+    fn valid_object() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "title": {"type": "string"},
+                "count": {"type": "integer"},
+                "score": {"type": "number"},
+                "tags": {"type": "array", "items": {"type": "string"}}
+            },
+            "required": ["title"]
+        });
+        let value = json!({"title": "Hi", "count": 3, "score": 0.5, "tags": ["a"]});
+        assert!(validate(&schema, &value).is_empty());
+    }
+
+    #[test]
+    fn type_case_insensitive() {
+        let schema = json!({"type": "OBJECT", "properties": {"name": {"type": "String"}}});
+        assert!(validate(&schema, &json!({"name": "x"})).is_empty());
+        assert_eq!(paths(&validate(&schema, &json!({"name": 1}))), vec!["$.name"]);
+    }
+
+    #[test]
+    fn type_mismatch_message() {
+        let violations = validate(&json!({"type": "string"}), &json!(42));
+        assert_eq!(violations[0].path, "$");
+        assert_eq!(violations[0].message, "expected string, got number");
+    }
+
+    #[test]
+    fn integer_rejects_fraction() {
+        let schema = json!({"type": "integer"});
+        assert!(validate(&schema, &json!(2.0)).is_empty());
+        assert_eq!(validate(&schema, &json!(2.5)).len(), 1);
+    }
+
+    #[test]
+    fn type_list_and_nullable() {
+        assert!(validate(&json!({"type": ["string", "null"]}), &json!(null)).is_empty());
+        assert!(validate(&json!({"type": "string", "nullable": true}), &json!(null)).is_empty());
+        assert_eq!(validate(&json!({"type": "string"}), &json!(null)).len(), 1);
+    }
+
+    // ==================== required / enum / items ====================
+
+    #[test]
+    fn missing_required() {
+        let schema = json!({"type": "object", "required": ["title", "url"]});
+        let violations = validate(&schema, &json!({"title": "x"}));
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].message, "missing required property `url`");
+    }
+
+    #[test]
+    fn enum_values() {
+        let schema = json!({"type": "string", "enum": ["positive", "negative"]});
+        assert!(validate(&schema, &json!("positive")).is_empty());
+        let violations = validate(&schema, &json!("neutral"));
+        assert_eq!(violations[0].message, r#"must be one of ["positive","negative"]"#);
+    }
+
+    #[test]
+    fn nested_array_items_paths() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "people": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {"name": {"type": "string"}},
+                        "required": ["name"]
+                    }
+                }
+            }
+        });
+        let value = json!({"people": [{"name": "a"}, {"name": 1}, {}]});
+        assert_eq!(paths(&validate(&schema, &value)), vec!["$.people[1].name", "$.people[2]"]);
+    }
+
+    #[test]
+    fn unknown_keywords_and_empty_schema_accept() {
+        assert!(validate(&json!({}), &json!({"anything": [1, 2]})).is_empty());
+        assert!(validate(&json!({"type": "string", "format": "uri"}), &json!("x")).is_empty());
+        assert!(validate(&json!(true), &json!(1)).is_empty());
+    }
+}
@@ -1,23 +1,51 @@
+mod auth;
 mod browser;
+mod config;
+mod crawl;
+mod domains;
 mod error;
+mod extract;
 mod handlers;
+mod jobs;
 mod llm;
 mod models;
-
-use axum::{routing::{get, post}, Router};
+mod proxy;
+mod ratelimit;
+mod robots;
+mod scheduler;
+mod sitemap;
+mod ssrf;
+
+use axum::{extract::Request, routing::{get, post}, Router};
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::signal;
 use tower_http::cors::{AllowOrigin, Any, CorsLayer};
 use tower_http::trace::TraceLayer;
-use tracing::{info, warn};
+use tracing::{info, info_span, warn};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
+use crate::auth::KeyRegistry;
 use crate::browser::BrowserManager;
-use crate::handlers::{health_handler, scrape_handler, AppState};
-use crate::llm::GeminiClient;
+use crate::config::TimeoutConfig;
+use crate::handlers::{
+    batch_scrape_handler, cancel_job_handler, crawl_handler, create_job_handler,
+    get_job_handler, health_handler, pdf_handler, scrape_handler, scrape_stream_handler,
+    sitemap_handler, AppState,
+};
+use crate::jobs::{JobManager, MemoryJobStore};
+use crate::llm::LlmRegistry;
+use crate::ratelimit::RateLimiter;
+use crate::robots::RobotsPolicy;
+use crate::sitemap::SitemapFetcher;
+use crate::ssrf::UrlGuard;
 
 const DEFAULT_PORT: u16 = 3000;
 const DEFAULT_MAX_CONCURRENT_TABS: usize = 50;
+const DEFAULT_MAX_BATCH_SIZE: usize = 20;
+const DEFAULT_MAX_CRAWL_PAGES: usize = 500;
+const DEFAULT_JOB_RETENTION_SECS: u64 = 3600;
+const DEFAULT_ROBOTS_USER_AGENT: &str = "distill";
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
@@ -29,37 +57,100 @@ async fn main() -> anyhow::Result<()> {
         .with(tracing_subscriber::fmt::layer())
         .init();
 
-    let api_key = std::env::var("API_KEY").unwrap_or_else(|_| {
-        warn!("API_KEY not set, using default");
-        "changeme".to_string()
-    });
+    let keys = Arc::new(KeyRegistry::from_env()?);
+    auth::spawn_reload_on_sighup(keys.clone());
 
     let max_concurrent_tabs = std::env::var("MAX_CONCURRENT_TABS")
         .ok()
         .and_then(|v| v.parse().ok())
         .unwrap_or(DEFAULT_MAX_CONCURRENT_TABS);
 
-    let browser = BrowserManager::new(max_concurrent_tabs)?;
-    let llm_client = GeminiClient::new();
+    let max_batch_size = std::env::var("MAX_BATCH_SIZE")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_MAX_BATCH_SIZE);
+
+    let max_crawl_pages = std::env::var("MAX_CRAWL_PAGES")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_MAX_CRAWL_PAGES);
+
+    let job_retention_secs = std::env::var("JOB_RETENTION_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_JOB_RETENTION_SECS);
+
+    // No fallback: a guessable or shared secret would let anyone forge webhooks
+    let webhook_secret = std::env::var("WEBHOOK_SECRET").ok().filter(|s| !s.is_empty());
+    if webhook_secret.is_none() {
+        warn!("WEBHOOK_SECRET not set, jobs with a callback_url will be rejected");
+    }
+
+    let robots_user_agent = std::env::var("ROBOTS_USER_AGENT")
+        .unwrap_or_else(|_| DEFAULT_ROBOTS_USER_AGENT.to_string());
+    let respect_robots = std::env::var("RESPECT_ROBOTS_TXT")
+        .map(|v| v != "false" && v != "0")
+        .unwrap_or(true);
+    if !respect_robots {
+        warn!("RESPECT_ROBOTS_TXT disabled, robots.txt will be ignored");
+    }
+    let url_guard = UrlGuard::from_env()?;
+    let jobs = JobManager::new(
+        Arc::new(MemoryJobStore::new()),
+        webhook_secret,
+        Duration::from_secs(job_retention_secs),
+        url_guard.clone(),
+    );
+    let robots = RobotsPolicy::new(robots_user_agent, respect_robots, url_guard.clone());
+
+    let browser = BrowserManager::new(max_concurrent_tabs, url_guard.clone())?;
+    let llm = LlmRegistry::from_env();
+    let timeouts = TimeoutConfig::from_env();
 
     info!(
         port = DEFAULT_PORT,
         max_tabs = max_concurrent_tabs,
-        gemini = llm_client.is_configured(),
+        api_keys = keys.count(),
+        llm_default = %llm.default_provider(),
+        llm_configured = ?llm.configured(),
+        max_navigation_ms = timeouts.max_navigation.as_millis() as u64,
+        max_analysis_ms = timeouts.max_analysis.as_millis() as u64,
         "Distill starting"
     );
 
     let state = Arc::new(AppState {
         browser,
-        llm_client,
-        api_key,
+        llm,
+        keys,
+        limits: RateLimiter::new(),
+        timeouts,
+        max_batch_size,
+        max_crawl_pages,
+        jobs,
+        robots,
+        sitemaps: SitemapFetcher::new(url_guard.clone()),
+        url_guard,
     });
 
     let app = Router::new()
         .route("/scrape", post(scrape_handler))
+        .route("/scrape/batch", post(batch_scrape_handler))
+        .route("/scrape/pdf", post(pdf_handler))
+        .route("/scrape/stream", post(scrape_stream_handler))
+        .route("/crawl", post(crawl_handler))
+        .route("/sitemap", post(sitemap_handler))
+        .route("/jobs", post(create_job_handler))
+        .route("/jobs/{id}", get(get_job_handler).delete(cancel_job_handler))
         .route("/health", get(health_handler))
         .layer(build_cors_layer())
-        .layer(TraceLayer::new_for_http())
+        .layer(TraceLayer::new_for_http().make_span_with(|request: &Request<_>| {
+            info_span!(
+                "request",
+                method = %request.method(),
+                uri = %request.uri(),
+                key_id = tracing::field::Empty,
+            )
+        }))
         .with_state(state);
 
     let port = std::env::var("PORT")
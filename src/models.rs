@@ -1,14 +1,38 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::fmt;
 
-pub const DEFAULT_MODEL: &str = "gemini-3-flash-preview";
-
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Clone)]
 pub struct ScrapeRequest {
     pub url: String,
     #[serde(default = "default_output_format")]
     pub output_format: OutputFormat,
+    #[serde(default)]
+    pub extract_mode: ExtractMode,
+    pub wait_for: Option<WaitFor>,
+    #[serde(default)]
+    pub include_selectors: Vec<String>,
+    #[serde(default)]
+    pub exclude_selectors: Vec<String>,
+    #[serde(default)]
+    pub include_images: bool,
+    pub screenshot: Option<ScreenshotOptions>,
+    pub pdf: Option<PdfOptions>,
+    pub navigation_timeout_ms: Option<u64>,
+    pub analysis_timeout_ms: Option<u64>,
     pub analysis_request: Option<AnalysisRequest>,
+    /// Order among this API key's queued requests when browser slots are busy
+    #[serde(default)]
+    pub priority: Priority,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Priority {
+    Low,
+    #[default]
+    Normal,
+    High,
 }
 
 fn default_output_format() -> OutputFormat {
@@ -20,21 +44,183 @@ fn default_output_format() -> OutputFormat {
 pub enum OutputFormat {
     Markdown,
     Html,
+    Text,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ExtractMode {
+    #[default]
+    Full,
+    MainContent,
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct ScreenshotOptions {
+    #[serde(default)]
+    pub full_page: bool,
+    #[serde(default)]
+    pub format: ImageFormat,
+    /// JPEG only, 0-100
+    pub quality: Option<u32>,
+    pub clip_selector: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageFormat {
+    #[default]
+    Png,
+    Jpeg,
+}
+
+impl ImageFormat {
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            ImageFormat::Png => "image/png",
+            ImageFormat::Jpeg => "image/jpeg",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct PdfOptions {
+    #[serde(default)]
+    pub paper_size: PaperSize,
+    #[serde(default)]
+    pub margins: PdfMargins,
+    #[serde(default)]
+    pub landscape: bool,
+    #[serde(default)]
+    pub print_background: bool,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum PaperSize {
+    #[default]
+    Letter,
+    Legal,
+    Tabloid,
+    A3,
+    A4,
+    A5,
+}
+
+impl PaperSize {
+    /// Portrait (width, height) in inches
+    pub fn dimensions_in(&self) -> (f64, f64) {
+        match self {
+            PaperSize::Letter => (8.5, 11.0),
+            PaperSize::Legal => (8.5, 14.0),
+            PaperSize::Tabloid => (11.0, 17.0),
+            PaperSize::A3 => (11.69, 16.54),
+            PaperSize::A4 => (8.27, 11.69),
+            PaperSize::A5 => (5.83, 8.27),
+        }
+    }
+}
+
+/// Page margins in inches
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+pub struct PdfMargins {
+    #[serde(default = "default_margin_in")]
+    pub top: f64,
+    #[serde(default = "default_margin_in")]
+    pub bottom: f64,
+    #[serde(default = "default_margin_in")]
+    pub left: f64,
+    #[serde(default = "default_margin_in")]
+    pub right: f64,
+}
+
+impl Default for PdfMargins {
+    fn default() -> Self {
+        let margin = default_margin_in();
+        Self {
+            top: margin,
+            bottom: margin,
+            left: margin,
+            right: margin,
+        }
+    }
+}
+
+fn default_margin_in() -> f64 {
+    0.4
+}
+
+/// Extra readiness conditions checked after navigation, in field order
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct WaitFor {
+    pub selector: Option<SelectorWait>,
+    pub network_idle: Option<NetworkIdleWait>,
+    pub js: Option<JsWait>,
+    pub delay_ms: Option<u64>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct SelectorWait {
+    pub selector: String,
+    #[serde(default = "default_wait_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct NetworkIdleWait {
+    #[serde(default = "default_idle_ms")]
+    pub idle_ms: u64,
+    #[serde(default = "default_wait_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct JsWait {
+    pub expression: String,
+    #[serde(default = "default_wait_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+fn default_wait_timeout_ms() -> u64 {
+    5_000
+}
+
+fn default_idle_ms() -> u64 {
+    500
+}
+
+#[derive(Debug, Deserialize, Clone)]
 pub struct AnalysisRequest {
-    #[serde(default = "default_model")]
-    pub model: String,
+    /// Falls back to `LLM_DEFAULT_PROVIDER`
+    pub provider: Option<ProviderKind>,
+    /// Falls back to the provider's default model
+    pub model: Option<String>,
     pub prompt: String,
     pub response_schema: serde_json::Value,
+    /// Per-chunk budget for long content; defaults to `ANALYSIS_CHUNK_TOKENS`
+    pub chunk_tokens: Option<usize>,
 }
 
-fn default_model() -> String {
-    DEFAULT_MODEL.to_string()
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ProviderKind {
+    Gemini,
+    Openai,
+    Anthropic,
 }
 
-#[derive(Debug, Serialize)]
+impl fmt::Display for ProviderKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ProviderKind::Gemini => "gemini",
+            ProviderKind::Openai => "openai",
+            ProviderKind::Anthropic => "anthropic",
+        };
+        f.write_str(name)
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
 pub struct ScrapeResponse {
     pub success: bool,
     pub data: Option<ScrapeData>,
@@ -62,23 +248,262 @@ impl ScrapeResponse {
     }
 }
 
+#[derive(Debug, Deserialize)]
+pub struct BatchScrapeRequest {
+    pub requests: Vec<ScrapeRequest>,
+}
+
+/// Per-item results, in request order
+#[derive(Debug, Serialize)]
+pub struct BatchScrapeResponse {
+    pub success: bool,
+    pub results: Vec<ScrapeResponse>,
+}
+
+/// `POST /sitemap` body: a domain or sitemap URL, plus scrape options used when `scrape` is set
+#[derive(Debug, Deserialize)]
+pub struct SitemapRequest {
+    #[serde(flatten)]
+    pub request: ScrapeRequest,
+    pub max_urls: Option<usize>,
+    #[serde(default)]
+    pub scrape: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SitemapResponse {
+    pub success: bool,
+    /// Sitemap files read, in fetch order
+    pub sitemaps: Vec<String>,
+    pub urls: Vec<SitemapEntry>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<SitemapError>,
+    /// Discovered URLs dropped by SSRF validation
+    pub rejected: usize,
+    pub truncated: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub results: Option<Vec<ScrapeResponse>>,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct SitemapEntry {
+    pub loc: String,
+    pub lastmod: Option<String>,
+    pub changefreq: Option<String>,
+    pub priority: Option<f32>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct SitemapError {
+    pub url: String,
+    pub error: String,
+}
+
+/// `POST /jobs` body: a scrape request plus an optional completion webhook
+#[derive(Debug, Deserialize)]
+pub struct JobRequest {
+    #[serde(flatten)]
+    pub request: ScrapeRequest,
+    pub callback_url: Option<String>,
+}
+
+/// `POST /crawl` body: the seed URL and scrape options applied to every page
+#[derive(Debug, Deserialize)]
+pub struct CrawlRequest {
+    #[serde(flatten)]
+    pub request: ScrapeRequest,
+    /// Link hops from the seed; 0 scrapes only the seed
+    #[serde(default = "default_max_depth")]
+    pub max_depth: u32,
+    #[serde(default = "default_max_pages")]
+    pub max_pages: usize,
+    /// Globs (`*`, `?`); patterns starting with `/` match the path, others the full URL
+    #[serde(default)]
+    pub include_patterns: Vec<String>,
+    #[serde(default)]
+    pub exclude_patterns: Vec<String>,
+}
+
+fn default_max_depth() -> u32 {
+    2
+}
+
+fn default_max_pages() -> usize {
+    50
+}
+
+/// One NDJSON line of a crawl stream
 #[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CrawlEvent {
+    Page {
+        url: String,
+        depth: u32,
+        result: Box<ScrapeResponse>,
+    },
+    Done {
+        pages: usize,
+        failed: usize,
+    },
+}
+
+/// One `/scrape/stream` SSE event; the SSE event name is the `type` tag
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ScrapeEvent {
+    TabAcquired,
+    Navigated,
+    ContentExtracted {
+        content: String,
+    },
+    /// Raw text delta of the model's JSON answer
+    AnalysisToken {
+        text: String,
+    },
+    /// The answer streamed so far failed `response_schema` and is discarded;
+    /// `analysis_token` events of the retry follow
+    AnalysisRetry,
+    /// Final response, same shape as `/scrape`
+    Result {
+        result: Box<ScrapeResponse>,
+    },
+}
+
+impl ScrapeEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ScrapeEvent::TabAcquired => "tab_acquired",
+            ScrapeEvent::Navigated => "navigated",
+            ScrapeEvent::ContentExtracted { .. } => "content_extracted",
+            ScrapeEvent::AnalysisToken { .. } => "analysis_token",
+            ScrapeEvent::AnalysisRetry => "analysis_retry",
+            ScrapeEvent::Result { .. } => "result",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Clone, Default)]
 pub struct ScrapeData {
     pub metadata: PageMetadata,
     pub content: String,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub selector_matches: Option<SelectorMatches>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub article: Option<ArticleInfo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub images: Option<Vec<PageImage>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub screenshot: Option<Screenshot>,
+    /// Base64-encoded `application/pdf`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pdf: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub analysis_result: Option<serde_json::Value>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub analysis_error: Option<String>,
+    pub analysis_error: Option<AnalysisError>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub analysis_chunks: Option<AnalysisChunks>,
 }
 
+/// How content was split for map-reduce analysis
 #[derive(Debug, Serialize, Clone)]
+pub struct AnalysisChunks {
+    pub count: usize,
+    /// Chunks whose partial analysis failed and were left out of the merge
+    pub errors: Vec<ChunkError>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ChunkError {
+    /// Zero-based chunk index
+    pub chunk: usize,
+    pub error: AnalysisError,
+}
+
+/// Why analysis produced no result; `validation_errors` is set when the model's
+/// answer did not match `response_schema` even after a retry
+#[derive(Debug, Serialize, Clone)]
+pub struct AnalysisError {
+    pub code: String,
+    pub message: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub validation_errors: Vec<SchemaViolation>,
+}
+
+/// One `response_schema` mismatch, located by a `$.field[0]`-style path
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct SchemaViolation {
+    pub path: String,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize, Clone, Default)]
 pub struct PageMetadata {
     pub title: String,
+    pub description: Option<String>,
+    pub canonical_url: Option<String>,
+    pub language: Option<String>,
+    /// `article:published_time` meta tag
+    pub published_time: Option<String>,
     pub og_tags: HashMap<String, String>,
+    pub twitter_tags: HashMap<String, String>,
+    pub favicons: Vec<Favicon>,
+    /// Parsed `application/ld+json` blocks, in document order
+    pub json_ld: Vec<serde_json::Value>,
+    pub links: Vec<PageLink>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Favicon {
+    pub url: String,
+    pub rel: String,
+    pub sizes: Option<String>,
+    pub mime_type: Option<String>,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct PageLink {
+    pub url: String,
+    pub text: String,
+    pub rel: Vec<String>,
+    /// Same host as the scraped URL
+    pub internal: bool,
+}
+
+/// Article details found by `main_content` extraction
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct ArticleInfo {
+    pub byline: Option<String>,
+    pub published_date: Option<String>,
+    pub lead_image: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct PageImage {
+    pub url: String,
+    /// `None` when the attribute is missing, empty for decorative images
+    pub alt: Option<String>,
+    /// Natural dimensions, when the image was loaded
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub in_viewport: bool,
+}
+
+/// Base64-encoded page capture
+#[derive(Debug, Serialize, Clone)]
+pub struct Screenshot {
+    pub mime_type: String,
+    pub data: String,
+}
+
+/// Number of elements each content selector matched
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct SelectorMatches {
+    pub include: HashMap<String, usize>,
+    pub exclude: HashMap<String, usize>,
+}
+
+#[derive(Debug, Serialize, Clone)]
 pub struct ErrorDetail {
     pub code: String,
     pub message: String,
@@ -89,7 +514,50 @@ pub struct ErrorDetail {
 pub struct PageExtractResult {
     pub title: String,
     pub og_tags: HashMap<String, String>,
+    #[serde(default)]
+    pub twitter_tags: HashMap<String, String>,
+    pub description: Option<String>,
+    pub canonical_url: Option<String>,
+    pub language: Option<String>,
+    pub published_time: Option<String>,
+    #[serde(default)]
+    pub favicons: Vec<Favicon>,
+    #[serde(default)]
+    pub json_ld: Vec<serde_json::Value>,
+    #[serde(default)]
+    pub links: Vec<RawLink>,
+    #[serde(default)]
+    pub images: Vec<PageImage>,
+    #[serde(default)]
+    pub image_sources: Vec<RawImageSource>,
+    /// `document.baseURI`, which relative `srcset` candidates resolve against
+    #[serde(default)]
+    pub base_url: Option<String>,
     pub body_html: String,
+    #[serde(default)]
+    pub selector_matches: SelectorMatches,
+    #[serde(default)]
+    pub invalid_selectors: Vec<String>,
+}
+
+/// Anchor as reported by the browser, before host classification
+#[derive(Debug, Deserialize)]
+pub struct RawLink {
+    pub url: String,
+    #[serde(default)]
+    pub text: String,
+    #[serde(default)]
+    pub rel: Vec<String>,
+}
+
+/// `<picture>` source as reported by the browser, before `srcset` parsing
+#[derive(Debug, Deserialize)]
+pub struct RawImageSource {
+    pub srcset: String,
+    /// `alt` of the picture's `<img>` fallback
+    pub alt: Option<String>,
+    #[serde(default)]
+    pub in_viewport: bool,
 }
 
 #[cfg(test)]
@@ -104,9 +572,40 @@ mod tests {
         let req: ScrapeRequest = serde_json::from_str(json).unwrap();
         assert_eq!(req.url, "https://example.com");
         assert_eq!(req.output_format, OutputFormat::Markdown);
+        assert_eq!(req.extract_mode, ExtractMode::Full);
+        assert!(req.wait_for.is_none());
+        assert!(req.include_selectors.is_empty());
+        assert!(req.exclude_selectors.is_empty());
+        assert!(!req.include_images);
+        assert!(req.navigation_timeout_ms.is_none());
+        assert!(req.analysis_timeout_ms.is_none());
         assert!(req.analysis_request.is_none());
     }
 
+    #[test]
+    fn scrape_request_selectors() {
+        let json = r#"{
+            "url": "https://example.com",
+            "include_selectors": ["article", "main"],
+            "exclude_selectors": ["nav", "footer", ".ads"]
+        }"#;
+        let req: ScrapeRequest = serde_json::from_str(json).unwrap();
+        assert_eq!(req.include_selectors, vec!["article", "main"]);
+        assert_eq!(req.exclude_selectors, vec!["nav", "footer", ".ads"]);
+    }
+
+    #[test]
+    fn scrape_request_timeouts() {
+        let json = r#"{
+            "url": "https://example.com",
+            "navigation_timeout_ms": 20000,
+            "analysis_timeout_ms": 90000
+        }"#;
+        let req: ScrapeRequest = serde_json::from_str(json).unwrap();
+        assert_eq!(req.navigation_timeout_ms, Some(20_000));
+        assert_eq!(req.analysis_timeout_ms, Some(90_000));
+    }
+
     #[test]
     fn scrape_request_html_format() {
         let json = r#"{"url": "https://example.com", "output_format": "html"}"#;
@@ -114,6 +613,123 @@ mod tests {
         assert_eq!(req.output_format, OutputFormat::Html);
     }
 
+    #[test]
+    fn scrape_request_text_format() {
+        let json = r#"{"url": "https://example.com", "output_format": "text"}"#;
+        let req: ScrapeRequest = serde_json::from_str(json).unwrap();
+        assert_eq!(req.output_format, OutputFormat::Text);
+    }
+
+    #[test]
+    fn scrape_request_main_content_mode() {
+        let json = r#"{"url": "https://example.com", "extract_mode": "main_content"}"#;
+        let req: ScrapeRequest = serde_json::from_str(json).unwrap();
+        assert_eq!(req.extract_mode, ExtractMode::MainContent);
+    }
+
+    #[test]
+    fn scrape_request_screenshot_defaults() {
+        let json = r#"{"url": "https://example.com", "screenshot": {}}"#;
+        let req: ScrapeRequest = serde_json::from_str(json).unwrap();
+        let screenshot = req.screenshot.unwrap();
+        assert!(!screenshot.full_page);
+        assert_eq!(screenshot.format, ImageFormat::Png);
+        assert!(screenshot.quality.is_none());
+        assert!(screenshot.clip_selector.is_none());
+    }
+
+    #[test]
+    fn scrape_request_screenshot_jpeg() {
+        let json = r#"{
+            "url": "https://example.com",
+            "screenshot": {"full_page": true, "format": "jpeg", "quality": 80, "clip_selector": "main"}
+        }"#;
+        let req: ScrapeRequest = serde_json::from_str(json).unwrap();
+        let screenshot = req.screenshot.unwrap();
+        assert!(screenshot.full_page);
+        assert_eq!(screenshot.format, ImageFormat::Jpeg);
+        assert_eq!(screenshot.format.mime_type(), "image/jpeg");
+        assert_eq!(screenshot.quality, Some(80));
+        assert_eq!(screenshot.clip_selector.as_deref(), Some("main"));
+    }
+
+    #[test]
+    fn scrape_request_pdf_defaults() {
+        let json = r#"{"url": "https://example.com", "pdf": {}}"#;
+        let req: ScrapeRequest = serde_json::from_str(json).unwrap();
+        let pdf = req.pdf.unwrap();
+        assert_eq!(pdf.paper_size, PaperSize::Letter);
+        assert_eq!(pdf.margins, PdfMargins::default());
+        assert!(!pdf.landscape);
+        assert!(!pdf.print_background);
+    }
+
+    #[test]
+    fn scrape_request_pdf_options() {
+        let json = r#"{
+            "url": "https://example.com",
+            "pdf": {
+                "paper_size": "a4",
+                "margins": {"top": 1.0, "left": 0.5},
+                "landscape": true,
+                "print_background": true
+            }
+        }"#;
+        let req: ScrapeRequest = serde_json::from_str(json).unwrap();
+        let pdf = req.pdf.unwrap();
+        assert_eq!(pdf.paper_size, PaperSize::A4);
+        assert_eq!(pdf.margins.top, 1.0);
+        assert_eq!(pdf.margins.left, 0.5);
+        assert_eq!(pdf.margins.bottom, 0.4);
+        assert!(pdf.landscape);
+        assert!(pdf.print_background);
+    }
+
+    #[test]
+    fn paper_size_dimensions() {
+        assert_eq!(PaperSize::Letter.dimensions_in(), (8.5, 11.0));
+        assert_eq!(PaperSize::A4.dimensions_in(), (8.27, 11.69));
+    }
+
+    #[test]
+    fn scrape_request_wait_for_all() {
+        let json = r##"{
+            "url": "https://example.com",
+            "wait_for": {
+                "selector": {"selector": "#app", "timeout_ms": 3000},
+                "network_idle": {"idle_ms": 800},
+                "js": {"expression": "window.ready === true"},
+                "delay_ms": 250
+            }
+        }"##;
+        let req: ScrapeRequest = serde_json::from_str(json).unwrap();
+        let wait_for = req.wait_for.unwrap();
+        let selector = wait_for.selector.unwrap();
+        assert_eq!(selector.selector, "#app");
+        assert_eq!(selector.timeout_ms, 3000);
+        let network_idle = wait_for.network_idle.unwrap();
+        assert_eq!(network_idle.idle_ms, 800);
+        assert_eq!(network_idle.timeout_ms, 5_000);
+        assert_eq!(wait_for.js.unwrap().expression, "window.ready === true");
+        assert_eq!(wait_for.delay_ms, Some(250));
+    }
+
+    #[test]
+    fn scrape_request_wait_for_defaults() {
+        let json = r#"{
+            "url": "https://example.com",
+            "wait_for": {"network_idle": {}}
+        }"#;
+        let req: ScrapeRequest = serde_json::from_str(json).unwrap();
+        let wait_for = req.wait_for.unwrap();
+        assert!(wait_for.selector.is_none());
+        assert!(wait_for.js.is_none());
+        assert!(wait_for.delay_ms.is_none());
+        let network_idle = wait_for.network_idle.unwrap();
+        assert_eq!(network_idle.idle_ms, 500);
+        assert_eq!(network_idle.timeout_ms, 5_000);
+    }
+
     #[test]
     fn scrape_request_with_analysis() {
         let json = r#"{
@@ -126,7 +742,8 @@ mod tests {
         let req: ScrapeRequest = serde_json::from_str(json).unwrap();
         let analysis = req.analysis_request.unwrap();
         assert_eq!(analysis.prompt, "Summarize");
-        assert_eq!(analysis.model, DEFAULT_MODEL);
+        assert!(analysis.provider.is_none());
+        assert!(analysis.model.is_none());
     }
 
     #[test]
@@ -140,21 +757,64 @@ mod tests {
             }
         }"#;
         let req: ScrapeRequest = serde_json::from_str(json).unwrap();
-        assert_eq!(req.analysis_request.unwrap().model, "gemini-pro");
+        assert_eq!(req.analysis_request.unwrap().model.as_deref(), Some("gemini-pro"));
+    }
+
+    #[test]
+    fn scrape_request_custom_provider() {
+        let json = r#"{
+            "url": "https://example.com",
+            "analysis_request": {
+                "provider": "anthropic",
+                "prompt": "Summarize",
+                "response_schema": {}
+            }
+        }"#;
+        let req: ScrapeRequest = serde_json::from_str(json).unwrap();
+        assert_eq!(req.analysis_request.unwrap().provider, Some(ProviderKind::Anthropic));
+    }
+
+    #[test]
+    fn scrape_request_priority() {
+        let req: ScrapeRequest = serde_json::from_str(r#"{"url": "https://example.com"}"#).unwrap();
+        assert_eq!(req.priority, Priority::Normal);
+
+        let json = r#"{"url": "https://example.com", "priority": "high"}"#;
+        let req: ScrapeRequest = serde_json::from_str(json).unwrap();
+        assert_eq!(req.priority, Priority::High);
+        assert!(Priority::High > Priority::Normal && Priority::Normal > Priority::Low);
+    }
+
+    #[test]
+    fn provider_kind_display_matches_serde() {
+        for kind in [ProviderKind::Gemini, ProviderKind::Openai, ProviderKind::Anthropic] {
+            assert_eq!(serde_json::to_value(kind).unwrap(), kind.to_string());
+        }
     }
 
     // ==================== ScrapeResponse ====================
 
+    fn make_metadata() -> PageMetadata {
+        PageMetadata {
+            title: "Test".to_string(),
+            description: None,
+            canonical_url: None,
+            language: None,
+            published_time: None,
+            og_tags: HashMap::new(),
+            twitter_tags: HashMap::new(),
+            favicons: vec![],
+            json_ld: vec![],
+            links: vec![],
+        }
+    }
+
     #[test]
     fn scrape_response_success() {
         let data = ScrapeData {
-            metadata: PageMetadata {
-                title: "Test".to_string(),
-                og_tags: HashMap::new(),
-            },
+            metadata: make_metadata(),
             content: "Content".to_string(),
-            analysis_result: None,
-            analysis_error: None,
+            ..Default::default()
         };
         let resp = ScrapeResponse::success(data);
         assert!(resp.success);
@@ -175,15 +835,16 @@ mod tests {
     #[test]
     fn scrape_response_json_omits_none() {
         let data = ScrapeData {
-            metadata: PageMetadata {
-                title: "Test".to_string(),
-                og_tags: HashMap::new(),
-            },
+            metadata: make_metadata(),
             content: "Content".to_string(),
-            analysis_result: None,
-            analysis_error: None,
+            ..Default::default()
         };
         let json = serde_json::to_string(&ScrapeResponse::success(data)).unwrap();
+        assert!(!json.contains("selector_matches"));
+        assert!(!json.contains("article"));
+        assert!(!json.contains("images"));
+        assert!(!json.contains("screenshot"));
+        assert!(!json.contains("pdf"));
         assert!(!json.contains("analysis_result"));
         assert!(!json.contains("analysis_error"));
     }
@@ -191,19 +852,208 @@ mod tests {
     #[test]
     fn scrape_response_json_includes_analysis() {
         let data = ScrapeData {
-            metadata: PageMetadata {
-                title: "Test".to_string(),
-                og_tags: HashMap::new(),
-            },
+            metadata: make_metadata(),
             content: "Content".to_string(),
             analysis_result: Some(serde_json::json!({"summary": "test"})),
-            analysis_error: None,
+            ..Default::default()
         };
         let json = serde_json::to_string(&ScrapeResponse::success(data)).unwrap();
         assert!(json.contains("analysis_result"));
         assert!(json.contains("summary"));
     }
 
+    #[test]
+    fn scrape_response_json_structured_analysis_error() {
+        let data = ScrapeData {
+            metadata: make_metadata(),
+            content: "Content".to_string(),
+            analysis_error: Some(AnalysisError {
+                code: "SCHEMA_VALIDATION_FAILED".to_string(),
+                message: "LLM output does not match response_schema (1 violation(s))".to_string(),
+                validation_errors: vec![SchemaViolation {
+                    path: "$.title".to_string(),
+                    message: "expected string, got number".to_string(),
+                }],
+            }),
+            ..Default::default()
+        };
+        let json = serde_json::to_value(ScrapeResponse::success(data)).unwrap();
+        let error = &json["data"]["analysis_error"];
+        assert_eq!(error["code"], "SCHEMA_VALIDATION_FAILED");
+        assert_eq!(error["validation_errors"][0]["path"], "$.title");
+    }
+
+    #[test]
+    fn analysis_error_omits_empty_validation_errors() {
+        let error = AnalysisError {
+            code: "LLM_PROVIDER_ERROR".to_string(),
+            message: "gemini: boom".to_string(),
+            validation_errors: Vec::new(),
+        };
+        let json = serde_json::to_string(&error).unwrap();
+        assert!(!json.contains("validation_errors"));
+    }
+
+    // ==================== Batch ====================
+
+    #[test]
+    fn batch_request_parse() {
+        let json = r#"{
+            "requests": [
+                {"url": "https://example.com/a"},
+                {"url": "https://example.com/b", "output_format": "text"}
+            ]
+        }"#;
+        let batch: BatchScrapeRequest = serde_json::from_str(json).unwrap();
+        assert_eq!(batch.requests.len(), 2);
+        assert_eq!(batch.requests[1].output_format, OutputFormat::Text);
+    }
+
+    #[test]
+    fn batch_response_mixed_results() {
+        let response = BatchScrapeResponse {
+            success: true,
+            results: vec![
+                ScrapeResponse::error("TIMEOUT_EXCEEDED", "slow"),
+                ScrapeResponse::error("INVALID_REQUEST", "bad url"),
+            ],
+        };
+        let json = serde_json::to_value(&response).unwrap();
+        assert_eq!(json["results"][0]["error"]["code"], "TIMEOUT_EXCEEDED");
+        assert_eq!(json["results"][1]["success"], false);
+    }
+
+    // ==================== Sitemap ====================
+
+    #[test]
+    fn sitemap_request_parse() {
+        let json = r#"{"url": "example.com", "max_urls": 10, "scrape": true, "output_format": "text"}"#;
+        let sitemap: SitemapRequest = serde_json::from_str(json).unwrap();
+        assert_eq!(sitemap.request.url, "example.com");
+        assert_eq!(sitemap.max_urls, Some(10));
+        assert!(sitemap.scrape);
+        assert_eq!(sitemap.request.output_format, OutputFormat::Text);
+    }
+
+    #[test]
+    fn sitemap_request_defaults() {
+        let sitemap: SitemapRequest =
+            serde_json::from_str(r#"{"url": "https://example.com/sitemap.xml"}"#).unwrap();
+        assert!(sitemap.max_urls.is_none());
+        assert!(!sitemap.scrape);
+    }
+
+    #[test]
+    fn sitemap_response_omits_empty() {
+        let response = SitemapResponse {
+            success: true,
+            sitemaps: vec!["https://example.com/sitemap.xml".to_string()],
+            urls: vec![],
+            errors: vec![],
+            rejected: 0,
+            truncated: false,
+            results: None,
+        };
+        let json = serde_json::to_value(&response).unwrap();
+        assert!(json.get("errors").is_none());
+        assert!(json.get("results").is_none());
+        assert_eq!(json["rejected"], 0);
+    }
+
+    // ==================== JobRequest ====================
+
+    #[test]
+    fn job_request_flattens_scrape_request() {
+        let json = r#"{
+            "url": "https://example.com",
+            "output_format": "html",
+            "callback_url": "https://hooks.example.com/distill"
+        }"#;
+        let job: JobRequest = serde_json::from_str(json).unwrap();
+        assert_eq!(job.request.url, "https://example.com");
+        assert_eq!(job.request.output_format, OutputFormat::Html);
+        assert_eq!(job.callback_url.as_deref(), Some("https://hooks.example.com/distill"));
+    }
+
+    #[test]
+    fn job_request_without_callback() {
+        let job: JobRequest = serde_json::from_str(r#"{"url": "https://example.com"}"#).unwrap();
+        assert!(job.callback_url.is_none());
+        assert_eq!(job.request.extract_mode, ExtractMode::Full);
+    }
+
+    // ==================== Crawl ====================
+
+    #[test]
+    fn crawl_request_defaults() {
+        let crawl: CrawlRequest = serde_json::from_str(r#"{"url": "https://example.com"}"#).unwrap();
+        assert_eq!(crawl.max_depth, 2);
+        assert_eq!(crawl.max_pages, 50);
+        assert!(crawl.include_patterns.is_empty());
+        assert_eq!(crawl.request.output_format, OutputFormat::Markdown);
+    }
+
+    #[test]
+    fn crawl_request_parse() {
+        let json = r#"{
+            "url": "https://example.com/docs",
+            "max_depth": 0,
+            "max_pages": 5,
+            "include_patterns": ["/docs/*"],
+            "exclude_patterns": ["*.pdf"],
+            "output_format": "text"
+        }"#;
+        let crawl: CrawlRequest = serde_json::from_str(json).unwrap();
+        assert_eq!(crawl.max_depth, 0);
+        assert_eq!(crawl.include_patterns, vec!["/docs/*"]);
+        assert_eq!(crawl.request.output_format, OutputFormat::Text);
+    }
+
+    #[test]
+    fn crawl_event_tagged() {
+        let event = CrawlEvent::Page {
+            url: "https://example.com".to_string(),
+            depth: 1,
+            result: Box::new(ScrapeResponse::error("BROWSER_ERROR", "crashed")),
+        };
+        let json = serde_json::to_value(&event).unwrap();
+        assert_eq!(json["type"], "page");
+        assert_eq!(json["depth"], 1);
+        assert_eq!(json["result"]["success"], false);
+
+        let done = serde_json::to_value(CrawlEvent::Done { pages: 3, failed: 1 }).unwrap();
+        assert_eq!(done["type"], "done");
+        assert_eq!(done["pages"], 3);
+    }
+
+    // ==================== ScrapeEvent ====================
+
+    #[test]
+    fn scrape_event_name_matches_tag() {
+        let events = [
+            ScrapeEvent::TabAcquired,
+            ScrapeEvent::Navigated,
+            ScrapeEvent::ContentExtracted { content: "# Title".to_string() },
+            ScrapeEvent::AnalysisToken { text: "{\"ti".to_string() },
+            ScrapeEvent::AnalysisRetry,
+            ScrapeEvent::Result { result: Box::new(ScrapeResponse::error("BROWSER_ERROR", "crashed")) },
+        ];
+        for event in events {
+            let json = serde_json::to_value(&event).unwrap();
+            assert_eq!(json["type"], event.name());
+        }
+    }
+
+    #[test]
+    fn scrape_event_fields() {
+        let token = serde_json::to_value(ScrapeEvent::AnalysisToken { text: "abc".to_string() }).unwrap();
+        assert_eq!(token["text"], "abc");
+
+        let result = ScrapeEvent::Result { result: Box::new(ScrapeResponse::error("TIMEOUT_EXCEEDED", "slow")) };
+        let json = serde_json::to_value(&result).unwrap();
+        assert_eq!(json["result"]["error"]["code"], "TIMEOUT_EXCEEDED");
+    }
+
     // ==================== PageExtractResult ====================
 
     #[test]
@@ -228,5 +1078,121 @@ mod tests {
         }"#;
         let result: PageExtractResult = serde_json::from_str(json).unwrap();
         assert!(result.og_tags.is_empty());
+        assert!(result.twitter_tags.is_empty());
+        assert!(result.description.is_none());
+        assert!(result.favicons.is_empty());
+        assert!(result.json_ld.is_empty());
+        assert!(result.links.is_empty());
+        assert!(result.selector_matches.include.is_empty());
+        assert!(result.invalid_selectors.is_empty());
+    }
+
+    #[test]
+    fn page_extract_result_rich_metadata() {
+        let json = r#"{
+            "title": "Post",
+            "og_tags": {},
+            "twitter_tags": {"twitter:card": "summary"},
+            "description": "A post",
+            "canonical_url": "https://example.com/post",
+            "language": "ko",
+            "published_time": "2024-05-01T10:00:00Z",
+            "favicons": [{"url": "https://example.com/favicon.ico", "rel": "icon", "sizes": null, "mime_type": "image/x-icon"}],
+            "json_ld": [{"@type": "Article", "headline": "Post"}],
+            "body_html": "<body></body>"
+        }"#;
+        let result: PageExtractResult = serde_json::from_str(json).unwrap();
+        assert_eq!(result.twitter_tags["twitter:card"], "summary");
+        assert_eq!(result.description.as_deref(), Some("A post"));
+        assert_eq!(result.canonical_url.as_deref(), Some("https://example.com/post"));
+        assert_eq!(result.language.as_deref(), Some("ko"));
+        assert_eq!(result.published_time.as_deref(), Some("2024-05-01T10:00:00Z"));
+        assert_eq!(result.favicons[0].mime_type.as_deref(), Some("image/x-icon"));
+        assert_eq!(result.json_ld[0]["@type"], "Article");
+    }
+
+    #[test]
+    fn scrape_response_json_includes_metadata_fields() {
+        let data = ScrapeData {
+            metadata: make_metadata(),
+            content: "Content".to_string(),
+            ..Default::default()
+        };
+        let json = serde_json::to_value(ScrapeResponse::success(data)).unwrap();
+        let metadata = &json["data"]["metadata"];
+        for field in ["description", "canonical_url", "language", "published_time", "twitter_tags", "favicons", "json_ld"] {
+            assert!(metadata.get(field).is_some(), "missing {}", field);
+        }
+    }
+
+    #[test]
+    fn page_extract_result_links() {
+        let json = r#"{
+            "title": "",
+            "og_tags": {},
+            "links": [
+                {"url": "https://example.com/a", "text": "A", "rel": ["nofollow"]},
+                {"url": "https://other.com/"}
+            ],
+            "body_html": "<body></body>"
+        }"#;
+        let result: PageExtractResult = serde_json::from_str(json).unwrap();
+        assert_eq!(result.links.len(), 2);
+        assert_eq!(result.links[0].rel, vec!["nofollow"]);
+        assert!(result.links[1].text.is_empty());
+        assert!(result.links[1].rel.is_empty());
+    }
+
+    #[test]
+    fn page_extract_result_images() {
+        let json = r#"{
+            "title": "",
+            "og_tags": {},
+            "images": [
+                {"url": "https://example.com/a.png", "alt": "A", "width": 640, "height": 480, "in_viewport": true},
+                {"url": "https://example.com/b.webp", "alt": null, "width": null, "height": null, "in_viewport": false}
+            ],
+            "body_html": "<body></body>"
+        }"#;
+        let result: PageExtractResult = serde_json::from_str(json).unwrap();
+        assert_eq!(result.images.len(), 2);
+        assert_eq!(result.images[0].width, Some(640));
+        assert!(result.images[0].in_viewport);
+        assert!(result.images[1].alt.is_none());
+        assert!(result.images[1].width.is_none());
+        assert!(result.image_sources.is_empty());
+    }
+
+    #[test]
+    fn page_extract_result_image_sources() {
+        let json = r#"{
+            "title": "",
+            "og_tags": {},
+            "image_sources": [
+                {"srcset": "a.avif 1x, a@2x.avif 2x", "alt": "A", "in_viewport": true}
+            ],
+            "base_url": "https://example.com/post/",
+            "body_html": "<body></body>"
+        }"#;
+        let result: PageExtractResult = serde_json::from_str(json).unwrap();
+        assert_eq!(result.image_sources[0].srcset, "a.avif 1x, a@2x.avif 2x");
+        assert_eq!(result.image_sources[0].alt.as_deref(), Some("A"));
+        assert!(result.image_sources[0].in_viewport);
+        assert_eq!(result.base_url.as_deref(), Some("https://example.com/post/"));
+    }
+
+    #[test]
+    fn page_extract_result_selector_matches() {
+        let json = r#"{
+            "title": "",
+            "og_tags": {},
+            "body_html": "<body><article></article></body>",
+            "selector_matches": {"include": {"article": 1}, "exclude": {"nav": 2}},
+            "invalid_selectors": ["[["]
+        }"#;
+        let result: PageExtractResult = serde_json::from_str(json).unwrap();
+        assert_eq!(result.selector_matches.include["article"], 1);
+        assert_eq!(result.selector_matches.exclude["nav"], 2);
+        assert_eq!(result.invalid_selectors, vec!["[["]);
     }
 }
@@ -0,0 +1,474 @@
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+use tokio::io::{copy, copy_bidirectional, AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tracing::{debug, warn};
+use url::Url;
+
+use crate::error::{AppError, AppResult};
+use crate::ssrf::UrlGuard;
+
+/// Largest request line plus headers accepted from the browser
+const MAX_HEAD_BYTES: usize = 64 * 1024;
+const HEAD_TIMEOUT: Duration = Duration::from_secs(30);
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// Hop-by-hop headers dropped in both directions of a plain HTTP exchange
+const HOP_HEADERS: &[&str] = &["connection", "keep-alive", "proxy-connection", "proxy-authorization"];
+
+/// Loopback HTTP proxy that Chrome sends all of its traffic through. Every
+/// destination is resolved by [`UrlGuard`] and the upstream socket is opened to one
+/// of exactly those checked addresses, so DNS rebinding cannot hand Chrome a private
+/// address after the check. WebSockets and Worker fetches arrive here as well.
+pub struct EgressProxy {
+    addr: SocketAddr,
+}
+
+impl EgressProxy {
+    /// Binds an ephemeral loopback port and serves it on the current runtime
+    pub fn start(guard: UrlGuard) -> AppResult<Self> {
+        let listener = std::net::TcpListener::bind((IpAddr::from([127, 0, 0, 1]), 0))
+            .and_then(|listener| listener.set_nonblocking(true).map(|_| listener))
+            .and_then(TcpListener::from_std)
+            .map_err(|e| AppError::Internal(format!("Egress proxy bind failed: {}", e)))?;
+        let addr = listener
+            .local_addr()
+            .map_err(|e| AppError::Internal(format!("Egress proxy bind failed: {}", e)))?;
+
+        tokio::spawn(serve(listener, guard));
+        Ok(Self { addr })
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+}
+
+async fn serve(listener: TcpListener, guard: UrlGuard) {
+    loop {
+        match listener.accept().await {
+            Ok((stream, _)) => {
+                let guard = guard.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = handle(stream, &guard).await {
+                        debug!(error = %e, "Egress proxy connection ended");
+                    }
+                });
+            }
+            Err(e) => warn!(error = %e, "Egress proxy accept failed"),
+        }
+    }
+}
+
+/// Request line and headers as sent by the browser, plus any body bytes read past them
+struct RequestHead {
+    method: String,
+    target: String,
+    version: String,
+    headers: Vec<String>,
+    leftover: Vec<u8>,
+}
+
+async fn handle(mut client: TcpStream, guard: &UrlGuard) -> io::Result<()> {
+    let head = match tokio::time::timeout(HEAD_TIMEOUT, read_head(&mut client)).await {
+        Ok(Ok(Some(head))) => head,
+        Ok(Ok(None)) => return reply(&mut client, "400 Bad Request").await,
+        Ok(Err(e)) => return Err(e),
+        Err(_) => return reply(&mut client, "408 Request Timeout").await,
+    };
+    let tunnel = head.method.eq_ignore_ascii_case("CONNECT");
+
+    let destination = match destination(&head, tunnel) {
+        Some(url) => url,
+        None => return reply(&mut client, "400 Bad Request").await,
+    };
+
+    let body_len = match request_body_len(&head, tunnel) {
+        Ok(len) => len,
+        Err(status) => return reply(&mut client, status).await,
+    };
+
+    let mut upstream = match connect_checked(guard, &destination).await {
+        Ok(stream) => stream,
+        Err(AppError::InvalidRequest(reason)) => {
+            warn!(url = %destination, error = %reason, "Blocked browser connection");
+            return reply(&mut client, "403 Forbidden").await;
+        }
+        Err(e) => {
+            debug!(url = %destination, error = %e, "Egress proxy upstream failed");
+            return reply(&mut client, "502 Bad Gateway").await;
+        }
+    };
+
+    if tunnel {
+        client.write_all(b"HTTP/1.1 200 Connection Established\r\n\r\n").await?;
+        upstream.write_all(&head.leftover).await?;
+        copy_bidirectional(&mut client, &mut upstream).await?;
+        return Ok(());
+    }
+
+    forward_request(&mut client, &mut upstream, &head, &destination, body_len).await?;
+    forward_response(&mut upstream, &mut client).await
+}
+
+/// Body length of a plain request, or the status to refuse it with
+fn request_body_len(head: &RequestHead, tunnel: bool) -> Result<u64, &'static str> {
+    if tunnel {
+        return Ok(0);
+    }
+    // Chunked uploads cannot be bounded without decoding them; browsers send them only over HTTP/2
+    if header_value(&head.headers, "transfer-encoding").is_some() {
+        return Err("411 Length Required");
+    }
+    match header_value(&head.headers, "content-length") {
+        Some(len) => len.parse().map_err(|_| "400 Bad Request"),
+        None => Ok(0),
+    }
+}
+
+/// Sends exactly one request: anything the browser pipelines after its body is never read
+async fn forward_request(
+    client: &mut TcpStream,
+    upstream: &mut TcpStream,
+    head: &RequestHead,
+    destination: &Url,
+    body_len: u64,
+) -> io::Result<()> {
+    upstream.write_all(origin_form(head, destination).as_bytes()).await?;
+    let buffered = head.leftover.len().min(body_len as usize);
+    upstream.write_all(&head.leftover[..buffered]).await?;
+
+    let remaining = body_len - buffered as u64;
+    if copy(&mut client.take(remaining), upstream).await? < remaining {
+        return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "request body cut short"));
+    }
+    Ok(())
+}
+
+/// Relays the single response with `Connection: close`, then closes the browser side
+async fn forward_response(upstream: &mut TcpStream, client: &mut TcpStream) -> io::Result<()> {
+    let Some((head, leftover)) = read_raw_head(upstream).await? else {
+        return reply(client, "502 Bad Gateway").await;
+    };
+
+    let mut lines = head.split("\r\n");
+    let mut out = format!("{}\r\n", lines.next().unwrap_or_default());
+    for header in lines.filter(|header| !is_hop_header(header)) {
+        out.push_str(header);
+        out.push_str("\r\n");
+    }
+    out.push_str("Connection: close\r\n\r\n");
+
+    client.write_all(out.as_bytes()).await?;
+    client.write_all(&leftover).await?;
+    copy(upstream, client).await?;
+    client.shutdown().await
+}
+
+/// `None` when the head is malformed or over [`MAX_HEAD_BYTES`]
+async fn read_head(client: &mut TcpStream) -> io::Result<Option<RequestHead>> {
+    let Some((text, leftover)) = read_raw_head(client).await? else {
+        return Ok(None);
+    };
+    let mut lines = text.split("\r\n");
+    let mut request_line = lines.next().unwrap_or_default().split(' ');
+    let (Some(method), Some(target), Some(version)) =
+        (request_line.next(), request_line.next(), request_line.next())
+    else {
+        return Ok(None);
+    };
+
+    Ok(Some(RequestHead {
+        method: method.to_string(),
+        target: target.to_string(),
+        version: version.to_string(),
+        headers: lines.map(str::to_string).collect(),
+        leftover,
+    }))
+}
+
+/// Start line and headers up to the blank line, plus any bytes read past it
+async fn read_raw_head(stream: &mut (impl AsyncRead + Unpin)) -> io::Result<Option<(String, Vec<u8>)>> {
+    let mut buf = Vec::with_capacity(1024);
+    let mut chunk = [0u8; 4096];
+
+    let end = loop {
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            return Ok(None);
+        }
+        buf.extend_from_slice(&chunk[..n]);
+        if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos;
+        }
+        if buf.len() > MAX_HEAD_BYTES {
+            return Ok(None);
+        }
+    };
+
+    let leftover = buf.split_off(end + 4);
+    buf.truncate(end);
+    Ok(String::from_utf8(buf).ok().map(|text| (text, leftover)))
+}
+
+fn header_value<'a>(headers: &'a [String], name: &str) -> Option<&'a str> {
+    headers.iter().find_map(|header| {
+        let (key, value) = header.split_once(':')?;
+        key.trim().eq_ignore_ascii_case(name).then(|| value.trim())
+    })
+}
+
+fn is_hop_header(header: &str) -> bool {
+    let name = header.split(':').next().unwrap_or_default().trim();
+    HOP_HEADERS.iter().any(|hop| name.eq_ignore_ascii_case(hop))
+}
+
+/// `CONNECT host:port` for tunnels, an absolute `http://` URL for plain requests
+fn destination(head: &RequestHead, tunnel: bool) -> Option<Url> {
+    if tunnel {
+        let (_, port) = head.target.rsplit_once(':')?;
+        port.parse::<u16>().ok()?;
+        return Url::parse(&format!("http://{}/", head.target)).ok();
+    }
+    Url::parse(&head.target).ok().filter(|url| url.scheme() == "http")
+}
+
+/// Resolves through the guard and connects to the first checked address that answers
+async fn connect_checked(guard: &UrlGuard, url: &Url) -> AppResult<TcpStream> {
+    let port = url.port_or_known_default().unwrap_or(80);
+    let ips = {
+        let guard = guard.clone();
+        let url = url.clone();
+        tokio::task::spawn_blocking(move || guard.resolve(&url))
+            .await
+            .map_err(|e| AppError::Internal(format!("Task join error: {}", e)))??
+    };
+
+    let mut last_error = None;
+    for ip in ips {
+        match tokio::time::timeout(CONNECT_TIMEOUT, TcpStream::connect((ip, port))).await {
+            Ok(Ok(stream)) => return Ok(stream),
+            Ok(Err(e)) => last_error = Some(e.to_string()),
+            Err(_) => last_error = Some(format!("connect to {} timed out", ip)),
+        }
+    }
+    Err(AppError::Internal(last_error.unwrap_or_default()))
+}
+
+/// Rewrites the absolute-form request for the pinned upstream. `Connection: close` on
+/// both sides ends the exchange after one response, so the browser sends its next
+/// request, for whatever host, on a fresh connection that is checked again
+fn origin_form(head: &RequestHead, url: &Url) -> String {
+    let mut path = url.path().to_string();
+    if let Some(query) = url.query() {
+        path.push('?');
+        path.push_str(query);
+    }
+
+    let mut out = format!("{} {} {}\r\n", head.method, path, head.version);
+    for header in head.headers.iter().filter(|header| !is_hop_header(header)) {
+        out.push_str(header);
+        out.push_str("\r\n");
+    }
+    out.push_str("Connection: close\r\n\r\n");
+    out
+}
+
+async fn reply(client: &mut TcpStream, status: &str) -> io::Result<()> {
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+        status
+    );
+    client.write_all(response.as_bytes()).await?;
+    client.shutdown().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ssrf::{HostResolver, IpPolicy};
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    use std::sync::Mutex;
+
+    /// Hands out each host's answers in order, repeating the last one
+    struct SequenceResolver(Mutex<HashMap<String, Vec<IpAddr>>>);
+
+    impl SequenceResolver {
+        fn new(host: &str, answers: &[&str]) -> Self {
+            let answers = answers.iter().map(|ip| ip.parse().unwrap()).collect();
+            Self(Mutex::new(HashMap::from([(host.to_string(), answers)])))
+        }
+    }
+
+    impl HostResolver for SequenceResolver {
+        fn lookup(&self, host: &str, _port: u16) -> io::Result<Vec<IpAddr>> {
+            let mut records = self.0.lock().unwrap();
+            let answers = records
+                .get_mut(host)
+                .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "NXDOMAIN"))?;
+            let ip = if answers.len() > 1 { answers.remove(0) } else { answers[0] };
+            Ok(vec![ip])
+        }
+    }
+
+    /// Proxy whose policy lets the local test server through (only 127.0.0.1)
+    fn start_proxy(resolver: SequenceResolver) -> SocketAddr {
+        let policy = IpPolicy::new(vec!["127.0.0.1/32".parse().unwrap()], vec![]);
+        let guard = UrlGuard::new(Arc::new(resolver), policy);
+        EgressProxy::start(guard).unwrap().addr()
+    }
+
+    /// Answers every connection with the request line it received
+    async fn echo_server() -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let Ok(Some(head)) = read_head(&mut stream).await else { return };
+                    let body = format!("{} {}", head.method, head.target);
+                    let response = format!(
+                        "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        body.len(),
+                        body
+                    );
+                    let _ = stream.write_all(response.as_bytes()).await;
+                });
+            }
+        });
+        port
+    }
+
+    /// Keep-alive server that answers with every byte it received after the request head
+    async fn recording_server() -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let Ok(Some((_, mut body))) = read_raw_head(&mut stream).await else { return };
+                    let mut chunk = [0u8; 1024];
+                    while let Ok(Ok(n @ 1..)) =
+                        tokio::time::timeout(Duration::from_millis(200), stream.read(&mut chunk)).await
+                    {
+                        body.extend_from_slice(&chunk[..n]);
+                    }
+                    let head = format!(
+                        "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: keep-alive\r\nKeep-Alive: timeout=5\r\n\r\n",
+                        body.len()
+                    );
+                    let _ = stream.write_all(head.as_bytes()).await;
+                    let _ = stream.write_all(&body).await;
+                });
+            }
+        });
+        port
+    }
+
+    async fn send(proxy: SocketAddr, request: &str) -> String {
+        let mut stream = TcpStream::connect(proxy).await.unwrap();
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    /// CONNECT, then one request through the tunnel
+    async fn tunnel(proxy: SocketAddr, authority: &str) -> String {
+        send(
+            proxy,
+            &format!(
+                "CONNECT {a} HTTP/1.1\r\nHost: {a}\r\n\r\nGET /ws HTTP/1.1\r\nHost: {a}\r\n\r\n",
+                a = authority
+            ),
+        )
+        .await
+    }
+
+    // ==================== forwarding ====================
+
+    #[tokio::test]
+    async fn forwards_plain_requests_in_origin_form() {
+        let port = echo_server().await;
+        let proxy = start_proxy(SequenceResolver::new("site.test", &["127.0.0.1"]));
+
+        let response = send(
+            proxy,
+            &format!(
+                "GET http://site.test:{p}/a?b=1 HTTP/1.1\r\nHost: site.test:{p}\r\nProxy-Connection: keep-alive\r\n\r\n",
+                p = port
+            ),
+        )
+        .await;
+        assert!(response.starts_with("HTTP/1.1 200 OK"), "{}", response);
+        assert!(response.ends_with("GET /a?b=1"), "{}", response);
+    }
+
+    #[tokio::test]
+    async fn plain_requests_end_after_one_response() {
+        let port = recording_server().await;
+        let proxy = start_proxy(SequenceResolver::new("site.test", &["127.0.0.1"]));
+
+        let response = send(
+            proxy,
+            &format!(
+                "POST http://site.test:{p}/a HTTP/1.1\r\nHost: site.test:{p}\r\nContent-Length: 5\r\n\r\nhello\
+                 GET http://other.test:{p}/b HTTP/1.1\r\nHost: other.test:{p}\r\n\r\n",
+                p = port
+            ),
+        )
+        .await;
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        assert_eq!(body, "hello");
+        assert!(head.ends_with("\r\nConnection: close"), "{}", head);
+        assert!(!head.to_ascii_lowercase().contains("keep-alive"), "{}", head);
+    }
+
+    #[tokio::test]
+    async fn tunnels_connect_to_checked_address() {
+        let port = echo_server().await;
+        let proxy = start_proxy(SequenceResolver::new("site.test", &["127.0.0.1"]));
+
+        let response = tunnel(proxy, &format!("site.test:{}", port)).await;
+        assert!(response.starts_with("HTTP/1.1 200 Connection Established"), "{}", response);
+        assert!(response.ends_with("GET /ws"), "{}", response);
+    }
+
+    // ==================== blocking ====================
+
+    #[tokio::test]
+    async fn refuses_private_destinations() {
+        let proxy = start_proxy(SequenceResolver::new("internal.test", &["10.0.0.5"]));
+
+        assert!(tunnel(proxy, "internal.test:443").await.starts_with("HTTP/1.1 403"));
+        assert!(tunnel(proxy, "169.254.169.254:80").await.starts_with("HTTP/1.1 403"));
+        assert!(send(proxy, "GET http://internal.test/ HTTP/1.1\r\n\r\n")
+            .await
+            .starts_with("HTTP/1.1 403"));
+    }
+
+    #[tokio::test]
+    async fn rebinding_is_checked_per_connection() {
+        let port = echo_server().await;
+        let proxy = start_proxy(SequenceResolver::new("rebind.test", &["127.0.0.1", "10.0.0.5"]));
+        let authority = format!("rebind.test:{}", port);
+
+        assert!(tunnel(proxy, &authority).await.ends_with("GET /ws"));
+        assert!(tunnel(proxy, &authority).await.starts_with("HTTP/1.1 403"));
+    }
+
+    #[tokio::test]
+    async fn rejects_malformed_requests() {
+        let proxy = start_proxy(SequenceResolver::new("site.test", &["127.0.0.1"]));
+
+        assert!(send(proxy, "GET /relative HTTP/1.1\r\n\r\n").await.starts_with("HTTP/1.1 400"));
+        assert!(send(proxy, "CONNECT site.test HTTP/1.1\r\n\r\n").await.starts_with("HTTP/1.1 400"));
+        assert!(send(
+            proxy,
+            "POST http://site.test/ HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n0\r\n\r\n"
+        )
+        .await
+        .starts_with("HTTP/1.1 411"));
+    }
+}
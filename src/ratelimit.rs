@@ -0,0 +1,406 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use crate::error::{AppError, AppResult};
+
+const SECS_PER_DAY: u64 = 86_400;
+
+/// What a request is charged against
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Resource {
+    /// One browser page load
+    Scrape,
+    /// One LLM analysis (all chunks of a page count once)
+    Analysis,
+}
+
+impl std::fmt::Display for Resource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Resource::Scrape => "scrape",
+            Resource::Analysis => "analysis",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Per-key limits from the key config; anything unset is unlimited
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct KeyLimits {
+    #[serde(default)]
+    pub scrape: Limit,
+    #[serde(default)]
+    pub analysis: Limit,
+}
+
+impl KeyLimits {
+    fn get(&self, resource: Resource) -> &Limit {
+        match resource {
+            Resource::Scrape => &self.scrape,
+            Resource::Analysis => &self.analysis,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Limit {
+    /// Token-bucket refill rate
+    pub per_minute: Option<u32>,
+    /// Bucket size; defaults to `per_minute`
+    pub burst: Option<u32>,
+    /// Calls per UTC day
+    pub daily: Option<u64>,
+}
+
+struct Usage {
+    tokens: f64,
+    refilled_at: Instant,
+    /// Days since the Unix epoch that `used_today` belongs to
+    day: u64,
+    used_today: u64,
+}
+
+/// Token buckets and daily counters, keyed by API key ID and resource
+#[derive(Default)]
+pub struct RateLimiter {
+    usage: Mutex<HashMap<(String, Resource), Usage>>,
+}
+
+impl RateLimiter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Charges every `(resource, count)` pair to `key_id` or none of them, so a request that
+    /// needs several resources is rejected before any work starts. Over a limit this returns
+    /// `RateLimited` with the number of seconds until the charge would succeed
+    pub fn charge(&self, key_id: &str, limits: &KeyLimits, charges: &[(Resource, u64)]) -> AppResult<()> {
+        let unix_secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        self.charge_at(key_id, limits, charges, Instant::now(), unix_secs)
+    }
+
+    #[cfg(test)]
+    fn check_at(
+        &self,
+        key_id: &str,
+        limits: &KeyLimits,
+        resource: Resource,
+        now: Instant,
+        unix_secs: u64,
+    ) -> AppResult<()> {
+        self.charge_at(key_id, limits, &[(resource, 1)], now, unix_secs)
+    }
+
+    fn charge_at(
+        &self,
+        key_id: &str,
+        limits: &KeyLimits,
+        charges: &[(Resource, u64)],
+        now: Instant,
+        unix_secs: u64,
+    ) -> AppResult<()> {
+        let today = unix_secs / SECS_PER_DAY;
+        let mut usage = self.usage.lock().unwrap();
+
+        // Check everything before charging anything: rejected requests are not charged
+        for &(resource, count) in charges {
+            let limit = limits.get(resource);
+            let Some(entry) = refreshed(&mut usage, key_id, resource, limit, now, today) else {
+                continue;
+            };
+            if count == 0 {
+                continue;
+            }
+
+            if let Some(daily) = limit.daily {
+                if count > daily {
+                    return Err(AppError::InvalidRequest(format!(
+                        "{} {} calls exceed the daily quota of {} for key '{}'",
+                        count, resource, daily, key_id
+                    )));
+                }
+                if entry.used_today + count > daily {
+                    let retry_after = SECS_PER_DAY - unix_secs % SECS_PER_DAY;
+                    return Err(AppError::RateLimited(
+                        format!("daily {} quota of {} used up for key '{}'", resource, daily, key_id),
+                        retry_after,
+                    ));
+                }
+            }
+
+            if let Some(per_minute) = limit.per_minute {
+                let burst = burst(limit);
+                if count as f64 > burst {
+                    return Err(AppError::InvalidRequest(format!(
+                        "{} {} calls exceed the burst of {} for key '{}'",
+                        count, resource, burst, key_id
+                    )));
+                }
+                if entry.tokens < count as f64 {
+                    let rate = per_minute as f64 / 60.0;
+                    let retry_after = if rate > 0.0 {
+                        ((count as f64 - entry.tokens) / rate).ceil() as u64
+                    } else {
+                        SECS_PER_DAY
+                    };
+                    return Err(AppError::RateLimited(
+                        format!("{} limit of {}/min exceeded for key '{}'", resource, per_minute, key_id),
+                        retry_after.max(1),
+                    ));
+                }
+            }
+        }
+
+        for &(resource, count) in charges {
+            let limit = limits.get(resource);
+            if let Some(entry) = usage.get_mut(&(key_id.to_string(), resource)) {
+                if limit.per_minute.is_some() {
+                    entry.tokens -= count as f64;
+                }
+                entry.used_today += count;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn burst(limit: &Limit) -> f64 {
+    limit.burst.or(limit.per_minute).unwrap_or(0).max(1) as f64
+}
+
+/// `key_id`'s usage of `resource` with tokens refilled and the day rolled over to
+/// `today`; `None` when the resource is unlimited
+fn refreshed<'a>(
+    usage: &'a mut HashMap<(String, Resource), Usage>,
+    key_id: &str,
+    resource: Resource,
+    limit: &Limit,
+    now: Instant,
+    today: u64,
+) -> Option<&'a mut Usage> {
+    if limit.per_minute.is_none() && limit.daily.is_none() {
+        return None;
+    }
+
+    let burst = burst(limit);
+    let entry = usage
+        .entry((key_id.to_string(), resource))
+        .or_insert_with(|| Usage {
+            tokens: burst,
+            refilled_at: now,
+            day: today,
+            used_today: 0,
+        });
+
+    if entry.day != today {
+        entry.day = today;
+        entry.used_today = 0;
+    }
+    if let Some(per_minute) = limit.per_minute {
+        let rate = per_minute as f64 / 60.0;
+        let elapsed = now.saturating_duration_since(entry.refilled_at).as_secs_f64();
+        // A reload may have shrunk the bucket
+        entry.tokens = (entry.tokens + elapsed * rate).min(burst);
+        entry.refilled_at = now;
+    }
+    Some(entry)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn limits(per_minute: Option<u32>, burst: Option<u32>, daily: Option<u64>) -> KeyLimits {
+        KeyLimits {
+            scrape: Limit {
+                per_minute,
+                burst,
+                daily,
+            },
+            analysis: Limit::default(),
+        }
+    }
+
+    fn retry_after(result: AppResult<()>) -> u64 {
+        match result {
+            Err(AppError::RateLimited(_, secs)) => secs,
+            other => panic!("expected RateLimited, got {:?}", other),
+        }
+    }
+
+    // ==================== token bucket ====================
+
+    #[test]
+    fn unlimited_by_default() {
+        let limiter = RateLimiter::new();
+        for _ in 0..1_000 {
+            assert!(limiter.charge("a", &KeyLimits::default(), &[(Resource::Scrape, 1)]).is_ok());
+        }
+    }
+
+    #[test]
+    fn burst_then_limited() {
+        let limiter = RateLimiter::new();
+        let limits = limits(Some(60), Some(3), None);
+        let now = Instant::now();
+
+        for _ in 0..3 {
+            assert!(limiter.check_at("a", &limits, Resource::Scrape, now, 0).is_ok());
+        }
+        assert_eq!(retry_after(limiter.check_at("a", &limits, Resource::Scrape, now, 0)), 1);
+    }
+
+    #[test]
+    fn bucket_refills_over_time() {
+        let limiter = RateLimiter::new();
+        let limits = limits(Some(6), None, None);
+        let now = Instant::now();
+
+        for _ in 0..6 {
+            assert!(limiter.check_at("a", &limits, Resource::Scrape, now, 0).is_ok());
+        }
+        // 6/min = one token every 10s
+        assert_eq!(retry_after(limiter.check_at("a", &limits, Resource::Scrape, now, 0)), 10);
+        let later = now + Duration::from_secs(10);
+        assert!(limiter.check_at("a", &limits, Resource::Scrape, later, 10).is_ok());
+        assert!(limiter.check_at("a", &limits, Resource::Scrape, later, 10).is_err());
+    }
+
+    #[test]
+    fn keys_and_resources_are_separate() {
+        let limiter = RateLimiter::new();
+        let limits = KeyLimits {
+            scrape: Limit {
+                per_minute: Some(1),
+                ..Limit::default()
+            },
+            analysis: Limit {
+                per_minute: Some(1),
+                ..Limit::default()
+            },
+        };
+        let now = Instant::now();
+
+        assert!(limiter.check_at("a", &limits, Resource::Scrape, now, 0).is_ok());
+        assert!(limiter.check_at("a", &limits, Resource::Scrape, now, 0).is_err());
+        assert!(limiter.check_at("a", &limits, Resource::Analysis, now, 0).is_ok());
+        assert!(limiter.check_at("b", &limits, Resource::Scrape, now, 0).is_ok());
+    }
+
+    // ==================== daily quota ====================
+
+    #[test]
+    fn daily_quota_resets_at_utc_midnight() {
+        let limiter = RateLimiter::new();
+        let limits = limits(None, None, Some(2));
+        let now = Instant::now();
+        let noon = SECS_PER_DAY * 100 + SECS_PER_DAY / 2;
+
+        assert!(limiter.check_at("a", &limits, Resource::Scrape, now, noon).is_ok());
+        assert!(limiter.check_at("a", &limits, Resource::Scrape, now, noon).is_ok());
+        assert_eq!(
+            retry_after(limiter.check_at("a", &limits, Resource::Scrape, now, noon)),
+            SECS_PER_DAY / 2
+        );
+
+        let next_day = SECS_PER_DAY * 101;
+        assert!(limiter.check_at("a", &limits, Resource::Scrape, now, next_day).is_ok());
+    }
+
+    #[test]
+    fn rejected_calls_are_not_charged() {
+        let limiter = RateLimiter::new();
+        let limits = limits(Some(60), Some(1), Some(2));
+        let now = Instant::now();
+
+        assert!(limiter.check_at("a", &limits, Resource::Scrape, now, 0).is_ok());
+        assert!(limiter.check_at("a", &limits, Resource::Scrape, now, 0).is_err());
+        // The rejected call did not use the second daily slot
+        let later = now + Duration::from_secs(1);
+        assert!(limiter.check_at("a", &limits, Resource::Scrape, later, 1).is_ok());
+    }
+
+    // ==================== charge ====================
+
+    fn scrape_and_analysis(per_minute: u32) -> KeyLimits {
+        let limit = Limit {
+            per_minute: Some(per_minute),
+            ..Limit::default()
+        };
+        KeyLimits {
+            scrape: limit.clone(),
+            analysis: limit,
+        }
+    }
+
+    #[test]
+    fn charge_is_all_or_nothing() {
+        let limiter = RateLimiter::new();
+        let limits = KeyLimits {
+            scrape: Limit {
+                per_minute: Some(10),
+                ..Limit::default()
+            },
+            analysis: Limit {
+                per_minute: Some(1),
+                ..Limit::default()
+            },
+        };
+        let now = Instant::now();
+        let both = [(Resource::Scrape, 1), (Resource::Analysis, 1)];
+
+        assert!(limiter.charge_at("a", &limits, &both, now, 0).is_ok());
+        assert!(limiter.charge_at("a", &limits, &both, now, 0).is_err());
+        // The rejected analysis did not take a scrape token either
+        for _ in 0..9 {
+            assert!(limiter.check_at("a", &limits, Resource::Scrape, now, 0).is_ok());
+        }
+        assert!(limiter.check_at("a", &limits, Resource::Scrape, now, 0).is_err());
+    }
+
+    #[test]
+    fn charge_counts_whole_batch() {
+        let limiter = RateLimiter::new();
+        let limits = scrape_and_analysis(6);
+        let now = Instant::now();
+
+        assert!(limiter.charge_at("a", &limits, &[(Resource::Scrape, 4)], now, 0).is_ok());
+        // Two tokens left, three more needed at one per 10s
+        assert_eq!(
+            retry_after(limiter.charge_at("a", &limits, &[(Resource::Scrape, 3)], now, 0)),
+            10
+        );
+        assert!(limiter.charge_at("a", &limits, &[(Resource::Scrape, 2)], now, 0).is_ok());
+    }
+
+    #[test]
+    fn charge_over_capacity_is_invalid() {
+        let limiter = RateLimiter::new();
+        let per_minute = scrape_and_analysis(5);
+        let err = limiter
+            .charge_at("a", &per_minute, &[(Resource::Scrape, 6)], Instant::now(), 0)
+            .unwrap_err();
+        assert_eq!(err.code(), "INVALID_REQUEST");
+
+        let daily = limits(None, None, Some(3));
+        let err = limiter
+            .charge_at("a", &daily, &[(Resource::Scrape, 4)], Instant::now(), 0)
+            .unwrap_err();
+        assert_eq!(err.code(), "INVALID_REQUEST");
+    }
+
+    #[test]
+    fn limits_parse() {
+        let limits: KeyLimits =
+            serde_json::from_str(r#"{"scrape": {"per_minute": 30, "daily": 1000}}"#).unwrap();
+        assert_eq!(limits.scrape.per_minute, Some(30));
+        assert_eq!(limits.scrape.burst, None);
+        assert_eq!(limits.analysis.daily, None);
+    }
+}
@@ -0,0 +1,609 @@
+use futures_util::StreamExt;
+use reqwest::{Client, Response, StatusCode};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{debug, warn};
+use url::Url;
+
+use crate::error::{AppError, AppResult, TimeoutPhase};
+use crate::ssrf::UrlGuard;
+
+const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+const CACHE_TTL: Duration = Duration::from_secs(3600);
+/// Unreachable robots.txt means "disallow all" (RFC 9309), so retry those sooner
+const UNREACHABLE_TTL: Duration = Duration::from_secs(60);
+const MAX_CRAWL_DELAY: Duration = Duration::from_secs(30);
+const MAX_CACHED_ORIGINS: usize = 1024;
+/// Parsers must read at least 500 KiB (RFC 9309 §2.5); anything past that is ignored
+pub(crate) const MAX_ROBOTS_BYTES: usize = 500 * 1024;
+
+#[derive(Debug, Clone, PartialEq)]
+struct Rule {
+    allow: bool,
+    pattern: String,
+}
+
+#[derive(Debug, Default)]
+struct Group {
+    agents: Vec<String>,
+    rules: Vec<Rule>,
+    crawl_delay: Option<Duration>,
+}
+
+/// Rules from one robots.txt that apply to our user-agent token
+#[derive(Debug, Default, Clone)]
+pub struct RobotsRules {
+    rules: Vec<Rule>,
+    crawl_delay: Option<Duration>,
+}
+
+impl RobotsRules {
+    pub fn allow_all() -> Self {
+        Self::default()
+    }
+
+    pub fn disallow_all() -> Self {
+        Self {
+            rules: vec![Rule {
+                allow: false,
+                pattern: "/".to_string(),
+            }],
+            crawl_delay: None,
+        }
+    }
+
+    /// Parses robots.txt and keeps the groups for `user_agent`, falling back to `*`
+    pub fn parse(body: &str, user_agent: &str) -> Self {
+        let token = user_agent
+            .split('/')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_lowercase();
+
+        let mut groups: Vec<Group> = Vec::new();
+        let mut last_was_agent = false;
+
+        for line in body.lines() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let key = key.trim().to_lowercase();
+            let value = value.trim();
+
+            if key == "user-agent" {
+                if !last_was_agent {
+                    groups.push(Group::default());
+                }
+                if let Some(group) = groups.last_mut() {
+                    group.agents.push(value.to_lowercase());
+                }
+                last_was_agent = true;
+                continue;
+            }
+            last_was_agent = false;
+
+            let Some(group) = groups.last_mut() else {
+                continue;
+            };
+            match key.as_str() {
+                "allow" | "disallow" if !value.is_empty() => group.rules.push(Rule {
+                    allow: key == "allow",
+                    pattern: value.to_string(),
+                }),
+                "crawl-delay" => {
+                    if let Ok(secs) = value.parse::<f64>() {
+                        if secs.is_finite() && secs >= 0.0 {
+                            group.crawl_delay =
+                                Some(Duration::from_secs_f64(secs).min(MAX_CRAWL_DELAY));
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let specific: Vec<&Group> = groups
+            .iter()
+            .filter(|g| g.agents.contains(&token))
+            .collect();
+        let selected = if specific.is_empty() {
+            groups
+                .iter()
+                .filter(|g| g.agents.iter().any(|a| a == "*"))
+                .collect()
+        } else {
+            specific
+        };
+
+        Self {
+            rules: selected.iter().flat_map(|g| g.rules.clone()).collect(),
+            crawl_delay: selected.iter().find_map(|g| g.crawl_delay),
+        }
+    }
+
+    /// Longest matching rule wins; `Allow` wins ties
+    pub fn is_allowed(&self, path: &str) -> bool {
+        if path == "/robots.txt" {
+            return true;
+        }
+
+        self.rules
+            .iter()
+            .filter(|rule| pattern_matches(&rule.pattern, path))
+            .max_by_key(|rule| (rule.pattern.len(), rule.allow))
+            .is_none_or(|rule| rule.allow)
+    }
+
+    pub fn crawl_delay(&self) -> Option<Duration> {
+        self.crawl_delay
+    }
+}
+
+/// `Sitemap:` URLs listed anywhere in robots.txt
+pub fn sitemap_urls(body: &str) -> Vec<String> {
+    body.lines()
+        .filter_map(|line| line.split_once(':'))
+        .filter(|(key, _)| key.trim().eq_ignore_ascii_case("sitemap"))
+        .map(|(_, value)| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+        .collect()
+}
+
+/// robots.txt path pattern: `*` wildcard, trailing `$` anchors, otherwise a prefix match
+fn pattern_matches(pattern: &str, path: &str) -> bool {
+    let (pattern, anchored) = match pattern.strip_suffix('$') {
+        Some(p) => (p, true),
+        None => (pattern, false),
+    };
+    let pattern = pattern.as_bytes();
+    let path = path.as_bytes();
+
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    loop {
+        if p == pattern.len() && (!anchored || t == path.len()) {
+            return true;
+        }
+        if p < pattern.len() && pattern[p] == b'*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if p < pattern.len() && t < path.len() && pattern[p] == path[t] {
+            p += 1;
+            t += 1;
+        } else if let Some((star, matched)) = backtrack.filter(|&(_, m)| m < path.len()) {
+            p = star + 1;
+            t = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+}
+
+struct CachedRules {
+    rules: Arc<RobotsRules>,
+    expires_at: Instant,
+}
+
+/// Per-origin robots.txt cache
+pub struct RobotsPolicy {
+    enabled: bool,
+    user_agent: String,
+    http_client: Client,
+    cache: Mutex<HashMap<String, CachedRules>>,
+}
+
+impl RobotsPolicy {
+    pub fn new(user_agent: String, enabled: bool, guard: UrlGuard) -> Self {
+        let http_client = Client::builder()
+            .timeout(FETCH_TIMEOUT)
+            .user_agent(user_agent.clone())
+            .redirect(guard.guarded_redirects())
+            .dns_resolver(Arc::new(guard))
+            .build()
+            .expect("Failed to create HTTP client");
+
+        Self {
+            enabled,
+            user_agent,
+            http_client,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Rejects URLs disallowed for our user-agent; returns the host's `Crawl-delay`
+    /// for callers that pace several pages through a [`CrawlPacer`]
+    pub async fn check(&self, url: &Url) -> AppResult<Option<Duration>> {
+        if !self.enabled {
+            return Ok(None);
+        }
+
+        let rules = self.rules_for(url).await;
+        let path = match url.query() {
+            Some(query) => format!("{}?{}", url.path(), query),
+            None => url.path().to_string(),
+        };
+        if !rules.is_allowed(&path) {
+            return Err(AppError::RobotsDisallowed(url.to_string()));
+        }
+        Ok(rules.crawl_delay())
+    }
+
+    async fn rules_for(&self, url: &Url) -> Arc<RobotsRules> {
+        let origin = url.origin().ascii_serialization();
+
+        if let Some(cached) = self.cache.lock().unwrap().get(&origin) {
+            if cached.expires_at > Instant::now() {
+                return cached.rules.clone();
+            }
+        }
+
+        let (rules, ttl) = self.fetch(&origin).await;
+        let rules = Arc::new(rules);
+
+        let mut cache = self.cache.lock().unwrap();
+        if cache.len() >= MAX_CACHED_ORIGINS {
+            let now = Instant::now();
+            cache.retain(|_, cached| cached.expires_at > now);
+        }
+        cache.insert(
+            origin,
+            CachedRules {
+                rules: rules.clone(),
+                expires_at: Instant::now() + ttl,
+            },
+        );
+        rules
+    }
+
+    async fn fetch(&self, origin: &str) -> (RobotsRules, Duration) {
+        let robots_url = format!("{}/robots.txt", origin);
+
+        let response = match self.http_client.get(&robots_url).send().await {
+            Ok(response) => response,
+            Err(e) => {
+                warn!(url = %robots_url, error = %e, "robots.txt unreachable, disallowing all");
+                return (RobotsRules::disallow_all(), UNREACHABLE_TTL);
+            }
+        };
+
+        let status = response.status();
+        if status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS {
+            warn!(url = %robots_url, %status, "robots.txt unavailable, disallowing all");
+            return (RobotsRules::disallow_all(), UNREACHABLE_TTL);
+        }
+        if !status.is_success() {
+            debug!(url = %robots_url, %status, "No robots.txt, allowing all");
+            return (RobotsRules::allow_all(), CACHE_TTL);
+        }
+
+        match read_prefix(response, MAX_ROBOTS_BYTES).await {
+            Ok(body) => (RobotsRules::parse(&body, &self.user_agent), CACHE_TTL),
+            Err(e) => {
+                warn!(url = %robots_url, error = %e, "Failed to read robots.txt, disallowing all");
+                (RobotsRules::disallow_all(), UNREACHABLE_TTL)
+            }
+        }
+    }
+}
+
+/// Reads at most `limit` bytes of the body without buffering the rest. A cut-off
+/// body loses its last, partial line so a truncated rule is never applied
+pub(crate) async fn read_prefix(response: Response, limit: usize) -> reqwest::Result<String> {
+    let mut body = Vec::new();
+    let mut stream = response.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        body.extend_from_slice(&chunk?);
+        if body.len() >= limit {
+            body.truncate(limit);
+            let end = body.iter().rposition(|b| *b == b'\n').map_or(0, |i| i + 1);
+            body.truncate(end);
+            break;
+        }
+    }
+    Ok(String::from_utf8_lossy(&body).into_owned())
+}
+
+/// `Crawl-delay` pacing for the pages of one batch, crawl or sitemap scrape. Slots
+/// live only as long as that request, so a cancelled request leaves no waits behind
+/// and single scrapes are never held up
+#[derive(Default)]
+pub struct CrawlPacer {
+    next_slot: Mutex<HashMap<String, Instant>>,
+}
+
+impl CrawlPacer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Waits for the next `delay`-spaced slot on `url`'s host. A slot further out than
+    /// `max_wait` is not booked; the page fails at once with a navigation timeout
+    pub async fn wait(&self, url: &Url, delay: Duration, max_wait: Duration) -> AppResult<()> {
+        let Some(host) = url.host_str() else {
+            return Ok(());
+        };
+
+        let now = Instant::now();
+        let booked = reserve_slot(&mut self.next_slot.lock().unwrap(), host, now, delay, max_wait);
+        let start = booked.map_err(|wait| {
+            AppError::Timeout(
+                TimeoutPhase::Navigation,
+                format!(
+                    "Crawl-delay for {} needs a {:.1}s wait, longer than the {:.1}s navigation timeout",
+                    host,
+                    wait.as_secs_f64(),
+                    max_wait.as_secs_f64()
+                ),
+            )
+        })?;
+        if start > now {
+            debug!(host, wait_ms = (start - now).as_millis() as u64, "Honoring Crawl-delay");
+            tokio::time::sleep_until(start.into()).await;
+        }
+        Ok(())
+    }
+}
+
+/// Books the next request slot for `host` and returns when that request may start,
+/// or the wait it would need when that is over `max_wait`
+fn reserve_slot(
+    slots: &mut HashMap<String, Instant>,
+    host: &str,
+    now: Instant,
+    delay: Duration,
+    max_wait: Duration,
+) -> Result<Instant, Duration> {
+    if slots.len() >= MAX_CACHED_ORIGINS {
+        slots.retain(|_, next| *next > now);
+    }
+    let start = slots.get(host).copied().filter(|next| *next > now).unwrap_or(now);
+    if start - now > max_wait {
+        return Err(start - now);
+    }
+    slots.insert(host.to_string(), start + delay);
+    Ok(start)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROBOTS: &str = "\
+# Example robots.txt
+User-agent: *
+Disallow: /private/
+Allow: /private/public
+Crawl-delay: 2
+
+User-agent: distill
+User-agent: other-bot
+Disallow: /internal   # trailing comment
+Disallow: /*.json$
+Allow: /internal/docs
+Crawl-delay: 0.5
+
+User-agent: blocked-bot
+Disallow: /
+";
+
+    // ==================== group selection ====================
+
+    #[test]
+    fn parse_specific_group() {
+        let rules = RobotsRules::parse(ROBOTS, "distill");
+        assert!(!rules.is_allowed("/internal/secret"));
+        assert!(rules.is_allowed("/private/page"));
+        assert_eq!(rules.crawl_delay(), Some(Duration::from_millis(500)));
+    }
+
+    #[test]
+    fn parse_agent_token_ignores_version_and_case() {
+        let rules = RobotsRules::parse(ROBOTS, "Distill/1.0");
+        assert!(!rules.is_allowed("/internal"));
+    }
+
+    #[test]
+    fn parse_falls_back_to_wildcard() {
+        let rules = RobotsRules::parse(ROBOTS, "unknown-bot");
+        assert!(!rules.is_allowed("/private/page"));
+        assert!(rules.is_allowed("/internal"));
+        assert_eq!(rules.crawl_delay(), Some(Duration::from_secs(2)));
+    }
+
+    #[test]
+    fn parse_block_all() {
+        let rules = RobotsRules::parse(ROBOTS, "blocked-bot");
+        assert!(!rules.is_allowed("/"));
+        assert!(!rules.is_allowed("/anything"));
+        assert!(rules.is_allowed("/robots.txt"));
+    }
+
+    #[test]
+    fn parse_empty_disallow_allows_all() {
+        let rules = RobotsRules::parse("User-agent: *\nDisallow:\n", "distill");
+        assert!(rules.is_allowed("/anything"));
+    }
+
+    #[test]
+    fn parse_no_groups_allows_all() {
+        let rules = RobotsRules::parse("Sitemap: https://example.com/sitemap.xml\n", "distill");
+        assert!(rules.is_allowed("/"));
+        assert!(rules.crawl_delay().is_none());
+    }
+
+    #[test]
+    fn parse_crawl_delay_capped() {
+        let rules = RobotsRules::parse("User-agent: *\nCrawl-delay: 3600\n", "distill");
+        assert_eq!(rules.crawl_delay(), Some(MAX_CRAWL_DELAY));
+    }
+
+    // ==================== rule precedence ====================
+
+    #[test]
+    fn longest_match_wins() {
+        let rules = RobotsRules::parse(ROBOTS, "distill");
+        assert!(rules.is_allowed("/internal/docs/page"));
+        let rules = RobotsRules::parse(ROBOTS, "*");
+        assert!(rules.is_allowed("/private/public/page"));
+    }
+
+    #[test]
+    fn allow_wins_tie() {
+        let rules = RobotsRules::parse("User-agent: *\nDisallow: /page\nAllow: /page\n", "distill");
+        assert!(rules.is_allowed("/page"));
+    }
+
+    // ==================== pattern_matches ====================
+
+    #[test]
+    fn pattern_prefix() {
+        assert!(pattern_matches("/private", "/private"));
+        assert!(pattern_matches("/private", "/private/page"));
+        assert!(!pattern_matches("/private", "/pub"));
+    }
+
+    #[test]
+    fn pattern_wildcard() {
+        assert!(pattern_matches("/*/edit", "/docs/page/edit"));
+        assert!(pattern_matches("/search*q=", "/search?lang=en&q=rust"));
+        assert!(!pattern_matches("/*/edit", "/docs"));
+    }
+
+    #[test]
+    fn pattern_end_anchor() {
+        assert!(pattern_matches("/*.json$", "/data/items.json"));
+        assert!(!pattern_matches("/*.json$", "/data/items.json?page=2"));
+        assert!(pattern_matches("/$", "/"));
+        assert!(!pattern_matches("/$", "/page"));
+    }
+
+    #[test]
+    fn pattern_question_mark_is_literal() {
+        assert!(pattern_matches("/page?id=", "/page?id=1"));
+        assert!(!pattern_matches("/page?id=", "/pagexid=1"));
+    }
+
+    // ==================== sitemap_urls ====================
+
+    #[test]
+    fn sitemap_urls_any_position() {
+        let body = "Sitemap: https://example.com/sitemap.xml\nUser-agent: *\nDisallow: /x\nsitemap:https://example.com/news.xml.gz\n";
+        assert_eq!(
+            sitemap_urls(body),
+            vec!["https://example.com/sitemap.xml", "https://example.com/news.xml.gz"]
+        );
+        assert!(sitemap_urls(ROBOTS).is_empty());
+    }
+
+    // ==================== read_prefix ====================
+
+    /// Chunked response of `chunks` copies of `line`, with no Content-Length
+    fn streamed(line: &'static str, chunks: usize) -> Response {
+        let body = futures_util::stream::iter(
+            (0..chunks).map(move |_| Ok::<_, std::io::Error>(line.as_bytes().to_vec())),
+        );
+        Response::from(axum::http::Response::new(reqwest::Body::wrap_stream(body)))
+    }
+
+    #[tokio::test]
+    async fn read_prefix_reads_small_body() {
+        let body = read_prefix(streamed("Disallow: /a\n", 3), MAX_ROBOTS_BYTES).await.unwrap();
+        assert_eq!(body, "Disallow: /a\n".repeat(3));
+    }
+
+    #[tokio::test]
+    async fn read_prefix_stops_at_limit_on_line_boundary() {
+        // ~1.3 MiB streamed, well past the cap
+        let body = read_prefix(streamed("Disallow: /private/\n", 70_000), MAX_ROBOTS_BYTES)
+            .await
+            .unwrap();
+        assert!(body.len() <= MAX_ROBOTS_BYTES);
+        assert!(body.len() > MAX_ROBOTS_BYTES - 64);
+        assert!(body.ends_with("Disallow: /private/\n"));
+    }
+
+    // ==================== reserve_slot ====================
+
+    const MAX_WAIT: Duration = Duration::from_secs(60);
+
+    #[test]
+    fn reserve_slot_spaces_requests() {
+        let mut slots = HashMap::new();
+        let now = Instant::now();
+        let delay = Duration::from_secs(2);
+
+        assert_eq!(reserve_slot(&mut slots, "example.com", now, delay, MAX_WAIT), Ok(now));
+        assert_eq!(reserve_slot(&mut slots, "example.com", now, delay, MAX_WAIT), Ok(now + delay));
+        assert_eq!(reserve_slot(&mut slots, "example.com", now, delay, MAX_WAIT), Ok(now + delay * 2));
+    }
+
+    #[test]
+    fn reserve_slot_per_host() {
+        let mut slots = HashMap::new();
+        let now = Instant::now();
+        let delay = Duration::from_secs(2);
+
+        reserve_slot(&mut slots, "a.example.com", now, delay, MAX_WAIT).unwrap();
+        assert_eq!(reserve_slot(&mut slots, "b.example.com", now, delay, MAX_WAIT), Ok(now));
+    }
+
+    #[test]
+    fn reserve_slot_after_delay_elapsed() {
+        let mut slots = HashMap::new();
+        let now = Instant::now();
+        let delay = Duration::from_secs(2);
+
+        reserve_slot(&mut slots, "example.com", now, delay, MAX_WAIT).unwrap();
+        let later = now + Duration::from_secs(5);
+        assert_eq!(reserve_slot(&mut slots, "example.com", later, delay, MAX_WAIT), Ok(later));
+    }
+
+    #[test]
+    fn reserve_slot_refuses_waits_past_max() {
+        let mut slots = HashMap::new();
+        let now = Instant::now();
+        let delay = Duration::from_secs(30);
+        let max_wait = Duration::from_secs(10);
+
+        assert_eq!(reserve_slot(&mut slots, "example.com", now, delay, max_wait), Ok(now));
+        assert_eq!(reserve_slot(&mut slots, "example.com", now, delay, max_wait), Err(delay));
+        // A refused slot is not booked, so it does not push later requests back
+        let later = now + Duration::from_secs(25);
+        assert_eq!(
+            reserve_slot(&mut slots, "example.com", later, delay, max_wait),
+            Ok(now + delay)
+        );
+    }
+
+    // ==================== CrawlPacer ====================
+
+    #[tokio::test]
+    async fn pacer_fails_fast_past_max_wait() {
+        let pacer = CrawlPacer::new();
+        let url = Url::parse("https://example.com/a").unwrap();
+        let (delay, max_wait) = (Duration::from_secs(30), Duration::from_secs(10));
+
+        assert!(pacer.wait(&url, delay, max_wait).await.is_ok());
+        match pacer.wait(&url, delay, max_wait).await {
+            Err(AppError::Timeout(TimeoutPhase::Navigation, message)) => {
+                assert!(message.contains("Crawl-delay for example.com"), "{}", message)
+            }
+            other => panic!("expected navigation timeout, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn pacers_do_not_share_slots() {
+        let url = Url::parse("https://example.com/a").unwrap();
+        let (delay, max_wait) = (Duration::from_secs(30), Duration::ZERO);
+
+        let batch = CrawlPacer::new();
+        assert!(batch.wait(&url, delay, max_wait).await.is_ok());
+        assert!(CrawlPacer::new().wait(&url, delay, max_wait).await.is_ok());
+    }
+}
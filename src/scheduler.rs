@@ -0,0 +1,463 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use tokio::sync::oneshot;
+
+use crate::error::{AppError, AppResult};
+use crate::models::Priority;
+
+/// Per-key share of the browser slots, from the key config
+#[derive(Debug, Clone, Deserialize)]
+pub struct TenantPolicy {
+    /// Slots this key may hold at once; unset means the global limit
+    pub max_concurrent: Option<usize>,
+    /// Relative share when several keys are waiting
+    #[serde(default = "default_weight")]
+    pub weight: u32,
+}
+
+impl Default for TenantPolicy {
+    fn default() -> Self {
+        Self {
+            max_concurrent: None,
+            weight: default_weight(),
+        }
+    }
+}
+
+fn default_weight() -> u32 {
+    1
+}
+
+/// Who is asking for a slot
+#[derive(Debug, Clone)]
+pub struct SlotClaim {
+    pub tenant: String,
+    pub policy: TenantPolicy,
+    pub priority: Priority,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SchedulerStats {
+    pub capacity: usize,
+    pub active: usize,
+    pub queued: usize,
+    /// Busy or waiting keys only
+    pub tenants: BTreeMap<String, TenantStats>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TenantStats {
+    pub active: usize,
+    pub queued: usize,
+}
+
+/// Hands out a fixed number of browser slots with weighted fair queuing between
+/// keys and priority order within a key.
+///
+/// Each key has a virtual clock that advances by `1 / weight` per granted slot; a
+/// free slot goes to the waiting key with the smallest clock that is under its
+/// `max_concurrent` cap. A key that goes idle is forgotten, and a key that starts
+/// waiting joins at the smallest clock of the busy keys, so idle time earns no credit.
+pub struct SlotScheduler {
+    shared: Arc<Shared>,
+}
+
+struct Shared {
+    capacity: usize,
+    state: Mutex<State>,
+}
+
+#[derive(Default)]
+struct State {
+    active: usize,
+    tenants: HashMap<String, Tenant>,
+    next_seq: u64,
+}
+
+struct Tenant {
+    policy: TenantPolicy,
+    active: usize,
+    waiters: Vec<Waiter>,
+    clock: f64,
+}
+
+impl Tenant {
+    fn cap(&self, capacity: usize) -> usize {
+        self.policy.max_concurrent.unwrap_or(capacity).max(1)
+    }
+
+    fn step(&self) -> f64 {
+        1.0 / self.policy.weight.max(1) as f64
+    }
+
+    fn live_waiters(&self) -> usize {
+        self.waiters.iter().filter(|w| !w.tx.is_closed()).count()
+    }
+
+    /// Highest priority first, FIFO within a priority
+    fn take_next(&mut self) -> Waiter {
+        let index = self
+            .waiters
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, w)| (std::cmp::Reverse(w.priority), w.seq))
+            .map(|(index, _)| index)
+            .expect("take_next on empty queue");
+        self.waiters.swap_remove(index)
+    }
+
+    fn oldest_seq(&self) -> u64 {
+        self.waiters.iter().map(|w| w.seq).min().unwrap_or(u64::MAX)
+    }
+
+    fn is_idle(&self) -> bool {
+        self.active == 0 && self.waiters.is_empty()
+    }
+}
+
+struct Waiter {
+    priority: Priority,
+    seq: u64,
+    tx: oneshot::Sender<SlotPermit>,
+}
+
+/// A held slot; dropping it frees the slot for the next waiter
+pub struct SlotPermit {
+    shared: Arc<Shared>,
+    tenant: String,
+    armed: bool,
+}
+
+impl Drop for SlotPermit {
+    fn drop(&mut self) {
+        if !self.armed {
+            return;
+        }
+        let mut state = self.shared.state.lock().unwrap();
+        state.active -= 1;
+        if let Some(tenant) = state.tenants.get_mut(&self.tenant) {
+            tenant.active -= 1;
+            if tenant.is_idle() {
+                state.tenants.remove(&self.tenant);
+            }
+        }
+        dispatch(&self.shared, &mut state);
+    }
+}
+
+impl SlotScheduler {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            shared: Arc::new(Shared {
+                capacity: capacity.max(1),
+                state: Mutex::new(State::default()),
+            }),
+        }
+    }
+
+    /// Waits for a slot; cancelling the returned future gives up the place in line
+    pub async fn acquire(&self, claim: &SlotClaim) -> AppResult<SlotPermit> {
+        let rx = {
+            let mut state = self.shared.state.lock().unwrap();
+            let seq = state.next_seq;
+            state.next_seq += 1;
+
+            let join_clock = state
+                .tenants
+                .values()
+                .map(|t| t.clock)
+                .min_by(f64::total_cmp)
+                .unwrap_or(0.0);
+            let tenant = state
+                .tenants
+                .entry(claim.tenant.clone())
+                .or_insert_with(|| Tenant {
+                    policy: claim.policy.clone(),
+                    active: 0,
+                    waiters: Vec::new(),
+                    clock: join_clock,
+                });
+            // Policies can change on key reload
+            tenant.policy = claim.policy.clone();
+
+            let (tx, rx) = oneshot::channel();
+            tenant.waiters.push(Waiter {
+                priority: claim.priority,
+                seq,
+                tx,
+            });
+            dispatch(&self.shared, &mut state);
+            rx
+        };
+
+        rx.await
+            .map_err(|_| AppError::Browser("Slot scheduler closed".to_string()))
+    }
+
+    pub fn stats(&self) -> SchedulerStats {
+        let state = self.shared.state.lock().unwrap();
+        let tenants: BTreeMap<String, TenantStats> = state
+            .tenants
+            .iter()
+            .map(|(name, tenant)| {
+                (
+                    name.clone(),
+                    TenantStats {
+                        active: tenant.active,
+                        queued: tenant.live_waiters(),
+                    },
+                )
+            })
+            .filter(|(_, stats)| stats.active > 0 || stats.queued > 0)
+            .collect();
+
+        SchedulerStats {
+            capacity: self.shared.capacity,
+            active: state.active,
+            queued: tenants.values().map(|t| t.queued).sum(),
+            tenants,
+        }
+    }
+}
+
+/// Grants free slots to waiters in fair order; abandoned waiters are dropped on the way
+fn dispatch(shared: &Arc<Shared>, state: &mut State) {
+    let capacity = shared.capacity;
+    while state.active < capacity {
+        let next = state
+            .tenants
+            .iter()
+            .filter(|(_, t)| !t.waiters.is_empty() && t.active < t.cap(capacity))
+            .min_by(|(_, a), (_, b)| {
+                a.clock
+                    .total_cmp(&b.clock)
+                    .then_with(|| a.oldest_seq().cmp(&b.oldest_seq()))
+            })
+            .map(|(name, _)| name.clone());
+        let Some(name) = next else {
+            break;
+        };
+
+        let tenant = state.tenants.get_mut(&name).expect("tenant picked above");
+        let waiter = tenant.take_next();
+        let permit = SlotPermit {
+            shared: shared.clone(),
+            tenant: name.clone(),
+            armed: true,
+        };
+
+        match waiter.tx.send(permit) {
+            Ok(()) => {
+                tenant.active += 1;
+                tenant.clock += tenant.step();
+                state.active += 1;
+            }
+            Err(mut permit) => {
+                // Waiter was cancelled; never counted, so nothing to release
+                permit.armed = false;
+                if tenant.is_idle() {
+                    state.tenants.remove(&name);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn claim(tenant: &str, priority: Priority) -> SlotClaim {
+        SlotClaim {
+            tenant: tenant.to_string(),
+            policy: TenantPolicy::default(),
+            priority,
+        }
+    }
+
+    fn weighted(tenant: &str, weight: u32) -> SlotClaim {
+        SlotClaim {
+            policy: TenantPolicy {
+                max_concurrent: None,
+                weight,
+            },
+            ..claim(tenant, Priority::Normal)
+        }
+    }
+
+    /// Queues labelled claims behind a full scheduler, then frees slots one at a
+    /// time and returns the labels in the order they were served
+    async fn serve_order(scheduler: Arc<SlotScheduler>, claims: Vec<(&'static str, SlotClaim)>) -> Vec<&'static str> {
+        let blocker = scheduler.acquire(&claim("blocker", Priority::Normal)).await.unwrap();
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+
+        for (label, claim) in claims {
+            let scheduler = scheduler.clone();
+            let tx = tx.clone();
+            tokio::spawn(async move {
+                let permit = scheduler.acquire(&claim).await.unwrap();
+                tx.send((label, permit)).unwrap();
+            });
+            // Keep enqueue order deterministic
+            tokio::task::yield_now().await;
+        }
+        drop(tx);
+        drop(blocker);
+
+        let mut order = Vec::new();
+        while let Some((label, permit)) = rx.recv().await {
+            order.push(label);
+            drop(permit);
+        }
+        order
+    }
+
+    // ==================== acquire / release ====================
+
+    #[tokio::test]
+    async fn grants_up_to_capacity() {
+        let scheduler = SlotScheduler::new(2);
+        let _a = scheduler.acquire(&claim("a", Priority::Normal)).await.unwrap();
+        let _b = scheduler.acquire(&claim("a", Priority::Normal)).await.unwrap();
+
+        let third = tokio::time::timeout(
+            Duration::from_millis(50),
+            scheduler.acquire(&claim("b", Priority::Normal)),
+        )
+        .await;
+        assert!(third.is_err());
+        assert_eq!(scheduler.stats().active, 2);
+    }
+
+    #[tokio::test]
+    async fn release_wakes_waiter() {
+        let scheduler = Arc::new(SlotScheduler::new(1));
+        let first = scheduler.acquire(&claim("a", Priority::Normal)).await.unwrap();
+
+        let waiting = {
+            let scheduler = scheduler.clone();
+            tokio::spawn(async move { scheduler.acquire(&claim("b", Priority::Normal)).await.is_ok() })
+        };
+        tokio::task::yield_now().await;
+        assert_eq!(scheduler.stats().queued, 1);
+
+        drop(first);
+        assert!(waiting.await.unwrap());
+        assert_eq!(scheduler.stats().active, 0);
+        assert!(scheduler.stats().tenants.is_empty());
+    }
+
+    #[tokio::test]
+    async fn cancelled_waiter_does_not_leak_slot() {
+        let scheduler = SlotScheduler::new(1);
+        let first = scheduler.acquire(&claim("a", Priority::Normal)).await.unwrap();
+
+        let cancelled = tokio::time::timeout(
+            Duration::from_millis(10),
+            scheduler.acquire(&claim("b", Priority::Normal)),
+        )
+        .await;
+        assert!(cancelled.is_err());
+        assert_eq!(scheduler.stats().queued, 0);
+
+        drop(first);
+        assert_eq!(scheduler.stats().active, 0);
+        assert!(scheduler.acquire(&claim("c", Priority::Normal)).await.is_ok());
+    }
+
+    // ==================== fairness ====================
+
+    #[tokio::test]
+    async fn bulk_key_does_not_starve_others() {
+        let scheduler = Arc::new(SlotScheduler::new(1));
+        let mut claims: Vec<_> = (0..4).map(|_| ("bulk", claim("bulk", Priority::Normal))).collect();
+        claims.push(("interactive", claim("interactive", Priority::Normal)));
+
+        let order = serve_order(scheduler, claims).await;
+        // FIFO would serve "interactive" last
+        assert_eq!(order, vec!["bulk", "interactive", "bulk", "bulk", "bulk"]);
+    }
+
+    #[tokio::test]
+    async fn weights_split_slots() {
+        let scheduler = Arc::new(SlotScheduler::new(1));
+        let mut claims = Vec::new();
+        for _ in 0..6 {
+            claims.push(("heavy", weighted("heavy", 2)));
+        }
+        for _ in 0..3 {
+            claims.push(("light", weighted("light", 1)));
+        }
+
+        let order = serve_order(scheduler, claims).await;
+        let heavy_in_first_six = order[..6].iter().filter(|t| **t == "heavy").count();
+        assert_eq!(heavy_in_first_six, 4);
+    }
+
+    #[tokio::test]
+    async fn priority_within_key() {
+        let scheduler = Arc::new(SlotScheduler::new(1));
+        let claims = vec![
+            ("low", claim("a", Priority::Low)),
+            ("normal", claim("a", Priority::Normal)),
+            ("high", claim("a", Priority::High)),
+            ("normal-2", claim("a", Priority::Normal)),
+        ];
+
+        let order = serve_order(scheduler, claims).await;
+        assert_eq!(order, vec!["high", "normal", "normal-2", "low"]);
+    }
+
+    #[tokio::test]
+    async fn per_key_cap() {
+        let scheduler = SlotScheduler::new(4);
+        let capped = SlotClaim {
+            policy: TenantPolicy {
+                max_concurrent: Some(1),
+                weight: 1,
+            },
+            ..claim("capped", Priority::Normal)
+        };
+        let _held = scheduler.acquire(&capped).await.unwrap();
+
+        let second = tokio::time::timeout(Duration::from_millis(50), scheduler.acquire(&capped)).await;
+        assert!(second.is_err());
+        // Other keys still get the free slots
+        assert!(scheduler.acquire(&claim("other", Priority::Normal)).await.is_ok());
+    }
+
+    // ==================== stats ====================
+
+    #[tokio::test]
+    async fn stats_per_key_queue_depth() {
+        let scheduler = Arc::new(SlotScheduler::new(1));
+        let _held = scheduler.acquire(&claim("a", Priority::Normal)).await.unwrap();
+
+        for tenant in ["b", "b", "c"] {
+            let scheduler = scheduler.clone();
+            tokio::spawn(async move {
+                let _ = scheduler.acquire(&claim(tenant, Priority::Normal)).await;
+            });
+        }
+        while scheduler.stats().queued < 3 {
+            tokio::task::yield_now().await;
+        }
+
+        let stats = scheduler.stats();
+        assert_eq!(stats.capacity, 1);
+        assert_eq!(stats.active, 1);
+        assert_eq!(stats.queued, 3);
+        assert_eq!(stats.tenants["a"].active, 1);
+        assert_eq!(stats.tenants["b"].queued, 2);
+        assert_eq!(stats.tenants["c"].queued, 1);
+    }
+
+    #[test]
+    fn policy_parse_defaults() {
+        let policy: TenantPolicy = serde_json::from_str(r#"{"max_concurrent": 5}"#).unwrap();
+        assert_eq!(policy.max_concurrent, Some(5));
+        assert_eq!(policy.weight, 1);
+    }
+}
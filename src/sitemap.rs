@@ -0,0 +1,382 @@
+use flate2::read::GzDecoder;
+use futures_util::StreamExt;
+use reqwest::{Client, Response};
+use std::collections::{HashSet, VecDeque};
+use std::io::Read;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, warn};
+use url::Url;
+
+use crate::domains::DomainPolicy;
+use crate::error::{AppError, AppResult};
+use crate::models::{SitemapEntry, SitemapError};
+use crate::robots;
+use crate::ssrf::UrlGuard;
+
+const FETCH_TIMEOUT: Duration = Duration::from_secs(15);
+/// Protocol limit for one sitemap file, uncompressed
+const MAX_SITEMAP_BYTES: u64 = 50 * 1024 * 1024;
+const MAX_SITEMAP_FILES: usize = 50;
+
+#[derive(Debug, PartialEq)]
+enum SitemapDocument {
+    Urlset(Vec<SitemapEntry>),
+    Index(Vec<String>),
+}
+
+/// URLs collected from a sitemap tree
+#[derive(Debug, Default)]
+pub struct SitemapExpansion {
+    pub sitemaps: Vec<String>,
+    pub urls: Vec<SitemapEntry>,
+    pub errors: Vec<SitemapError>,
+    /// Entries dropped by `validate_url`
+    pub rejected: usize,
+    pub truncated: bool,
+}
+
+pub struct SitemapFetcher {
+    http_client: Client,
+    url_guard: UrlGuard,
+}
+
+impl SitemapFetcher {
+    pub fn new(url_guard: UrlGuard) -> Self {
+        let http_client = Client::builder()
+            .timeout(FETCH_TIMEOUT)
+            .redirect(url_guard.guarded_redirects())
+            .dns_resolver(Arc::new(url_guard.clone()))
+            .build()
+            .expect("Failed to create HTTP client");
+
+        Self {
+            http_client,
+            url_guard,
+        }
+    }
+
+    /// Expands a sitemap (or the sitemaps advertised by a site) into page URLs.
+    /// Every sitemap and page URL passes `validate_url` and `domains` before use.
+    pub async fn expand(
+        &self,
+        start: &Url,
+        domains: &DomainPolicy,
+        max_urls: usize,
+    ) -> AppResult<SitemapExpansion> {
+        let mut expansion = SitemapExpansion::default();
+        let mut queue: VecDeque<Url> = self.seed_sitemaps(start, domains).await.into();
+        let mut seen: HashSet<String> = queue.iter().map(|u| u.to_string()).collect();
+
+        while let Some(sitemap_url) = queue.pop_front() {
+            if expansion.sitemaps.len() >= MAX_SITEMAP_FILES {
+                expansion.truncated = true;
+                break;
+            }
+
+            let document = match self.fetch(&sitemap_url, domains).await {
+                Ok(document) => document,
+                Err(error) => {
+                    warn!(url = %sitemap_url, error, "Sitemap fetch failed");
+                    expansion.errors.push(SitemapError {
+                        url: sitemap_url.to_string(),
+                        error,
+                    });
+                    continue;
+                }
+            };
+            expansion.sitemaps.push(sitemap_url.to_string());
+
+            match document {
+                SitemapDocument::Index(children) => {
+                    for child in children {
+                        match self.validate_url(&child, domains) {
+                            Ok(url) if seen.insert(url.to_string()) => queue.push_back(url),
+                            Ok(_) => {}
+                            Err(_) => expansion.rejected += 1,
+                        }
+                    }
+                }
+                SitemapDocument::Urlset(entries) => {
+                    for mut entry in entries {
+                        let Ok(url) = self.validate_url(&entry.loc, domains) else {
+                            expansion.rejected += 1;
+                            continue;
+                        };
+                        if expansion.urls.len() >= max_urls {
+                            expansion.truncated = true;
+                            break;
+                        }
+                        entry.loc = url.to_string();
+                        expansion.urls.push(entry);
+                    }
+                }
+            }
+
+            if expansion.truncated {
+                break;
+            }
+        }
+
+        if expansion.sitemaps.is_empty() {
+            return Err(AppError::NotFound(format!("No readable sitemap at {}", start)));
+        }
+        Ok(expansion)
+    }
+
+    /// A bare origin is expanded via robots.txt `Sitemap:` lines, then `/sitemap.xml`
+    async fn seed_sitemaps(&self, start: &Url, domains: &DomainPolicy) -> Vec<Url> {
+        if start.path() != "/" || start.query().is_some() {
+            return vec![start.clone()];
+        }
+
+        let robots_url = start.join("/robots.txt").expect("static path joins");
+        let mut seeds: Vec<Url> = match self.http_client.get(robots_url).send().await {
+            Ok(resp) if resp.status().is_success() && domains.check(resp.url()).is_ok() => {
+                robots::read_prefix(resp, robots::MAX_ROBOTS_BYTES)
+                    .await
+                    .map(|body| robots::sitemap_urls(&body))
+                    .unwrap_or_default()
+                    .iter()
+                    .filter_map(|u| self.validate_url(u, domains).ok())
+                    .collect()
+            }
+            _ => Vec::new(),
+        };
+
+        if seeds.is_empty() {
+            seeds.push(start.join("/sitemap.xml").expect("static path joins"));
+        }
+        debug!(start = %start, count = seeds.len(), "Discovered sitemaps");
+        seeds
+    }
+
+    /// Sitemaps are fetched server-side on the key's behalf, so they and the URLs they
+    /// list are held to the key's domain policy as well as the SSRF checks
+    fn validate_url(&self, url: &str, domains: &DomainPolicy) -> AppResult<Url> {
+        self.url_guard.validate_url(url, domains)
+    }
+
+    async fn fetch(&self, url: &Url, domains: &DomainPolicy) -> Result<SitemapDocument, String> {
+        let response = self
+            .http_client
+            .get(url.as_str())
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+
+        // Redirects are SSRF-checked per hop; the final host must also be allowed for the key
+        domains.check(response.url()).map_err(|e| e.to_string())?;
+
+        let status = response.status();
+        if !status.is_success() {
+            return Err(format!("HTTP {}", status));
+        }
+        if response.content_length().is_some_and(|len| len > MAX_SITEMAP_BYTES) {
+            return Err("Sitemap too large".to_string());
+        }
+
+        let bytes = read_capped(response).await?;
+        // Gunzipping and parsing up to MAX_SITEMAP_BYTES is CPU-bound, keep it off the async workers
+        tokio::task::spawn_blocking(move || parse_sitemap(&decode_body(&bytes)?))
+            .await
+            .map_err(|e| format!("Task join error: {}", e))?
+    }
+}
+
+/// Reads the body chunk by chunk, giving up as soon as it passes `MAX_SITEMAP_BYTES`
+/// (a missing or false Content-Length does not get around the limit)
+async fn read_capped(response: Response) -> Result<Vec<u8>, String> {
+    let mut body = Vec::new();
+    let mut stream = response.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        body.extend_from_slice(&chunk.map_err(|e| e.to_string())?);
+        if body.len() as u64 > MAX_SITEMAP_BYTES {
+            return Err("Sitemap too large".to_string());
+        }
+    }
+    Ok(body)
+}
+
+/// Decompresses gzip bodies (by magic bytes, not file name) and decodes UTF-8
+fn decode_body(bytes: &[u8]) -> Result<String, String> {
+    let raw = if bytes.starts_with(&[0x1f, 0x8b]) {
+        let mut decoded = Vec::new();
+        GzDecoder::new(bytes)
+            .take(MAX_SITEMAP_BYTES + 1)
+            .read_to_end(&mut decoded)
+            .map_err(|e| format!("Invalid gzip: {}", e))?;
+        decoded
+    } else {
+        bytes.to_vec()
+    };
+
+    if raw.len() as u64 > MAX_SITEMAP_BYTES {
+        return Err("Sitemap too large".to_string());
+    }
+    String::from_utf8(raw).map_err(|_| "Sitemap is not valid UTF-8".to_string())
+}
+
+fn parse_sitemap(xml: &str) -> Result<SitemapDocument, String> {
+    let doc = roxmltree::Document::parse(xml).map_err(|e| format!("Invalid XML: {}", e))?;
+    let root = doc.root_element();
+
+    let child_text = |node: roxmltree::Node, name: &str| {
+        node.children()
+            .find(|c| c.is_element() && c.tag_name().name() == name)
+            .and_then(|c| c.text())
+            .map(|t| t.trim().to_string())
+            .filter(|t| !t.is_empty())
+    };
+    let elements = |name: &'static str| {
+        root.children()
+            .filter(move |c| c.is_element() && c.tag_name().name() == name)
+    };
+
+    match root.tag_name().name() {
+        "urlset" => Ok(SitemapDocument::Urlset(
+            elements("url")
+                .filter_map(|node| {
+                    Some(SitemapEntry {
+                        loc: child_text(node, "loc")?,
+                        lastmod: child_text(node, "lastmod"),
+                        changefreq: child_text(node, "changefreq"),
+                        priority: child_text(node, "priority").and_then(|p| p.parse().ok()),
+                    })
+                })
+                .collect(),
+        )),
+        "sitemapindex" => Ok(SitemapDocument::Index(
+            elements("sitemap")
+                .filter_map(|node| child_text(node, "loc"))
+                .collect(),
+        )),
+        other => Err(format!("Unexpected root element <{}>", other)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ssrf::{IpPolicy, SystemResolver};
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    const URLSET: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+  <url>
+    <loc>https://example.com/</loc>
+    <lastmod>2024-05-01</lastmod>
+    <changefreq>daily</changefreq>
+    <priority>1.0</priority>
+  </url>
+  <url>
+    <loc>
+      https://example.com/about
+    </loc>
+  </url>
+  <url>
+    <lastmod>2024-05-01</lastmod>
+  </url>
+</urlset>"#;
+
+    const INDEX: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<sitemapindex xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+  <sitemap><loc>https://example.com/sitemap-posts.xml.gz</loc></sitemap>
+  <sitemap><loc>https://example.com/sitemap-pages.xml</loc><lastmod>2024-05-01</lastmod></sitemap>
+</sitemapindex>"#;
+
+    // ==================== parse_sitemap ====================
+
+    #[test]
+    fn parse_urlset() {
+        let SitemapDocument::Urlset(entries) = parse_sitemap(URLSET).unwrap() else {
+            panic!("expected urlset");
+        };
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].loc, "https://example.com/");
+        assert_eq!(entries[0].lastmod.as_deref(), Some("2024-05-01"));
+        assert_eq!(entries[0].changefreq.as_deref(), Some("daily"));
+        assert_eq!(entries[0].priority, Some(1.0));
+        assert_eq!(entries[1].loc, "https://example.com/about");
+        assert!(entries[1].priority.is_none());
+    }
+
+    #[test]
+    fn parse_index() {
+        assert_eq!(
+            parse_sitemap(INDEX).unwrap(),
+            SitemapDocument::Index(vec![
+                "https://example.com/sitemap-posts.xml.gz".to_string(),
+                "https://example.com/sitemap-pages.xml".to_string(),
+            ])
+        );
+    }
+
+    #[test]
+    fn parse_without_namespace() {
+        let xml = "<urlset><url><loc>https://example.com/a</loc></url></urlset>";
+        assert!(matches!(parse_sitemap(xml), Ok(SitemapDocument::Urlset(e)) if e.len() == 1));
+    }
+
+    #[test]
+    fn parse_rejects_other_documents() {
+        assert!(parse_sitemap("<html><body/></html>").unwrap_err().contains("<html>"));
+        assert!(parse_sitemap("not xml").unwrap_err().contains("Invalid XML"));
+    }
+
+    // ==================== decode_body ====================
+
+    #[test]
+    fn decode_plain() {
+        assert_eq!(decode_body(URLSET.as_bytes()).unwrap(), URLSET);
+    }
+
+    #[test]
+    fn decode_gzip() {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(INDEX.as_bytes()).unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        assert_eq!(decode_body(&compressed).unwrap(), INDEX);
+    }
+
+    #[test]
+    fn decode_corrupt_gzip() {
+        assert!(decode_body(&[0x1f, 0x8b, 0x00, 0x01]).unwrap_err().contains("gzip"));
+    }
+
+    // ==================== read_capped ====================
+
+    /// Chunked response of `chunks` 1 MiB chunks, with no Content-Length
+    fn streamed(chunks: usize) -> Response {
+        let body = futures_util::stream::iter(
+            (0..chunks).map(|_| Ok::<_, std::io::Error>(vec![b' '; 1024 * 1024])),
+        );
+        Response::from(axum::http::Response::new(reqwest::Body::wrap_stream(body)))
+    }
+
+    #[tokio::test]
+    async fn read_capped_within_limit() {
+        assert_eq!(read_capped(streamed(2)).await.unwrap().len(), 2 * 1024 * 1024);
+    }
+
+    #[tokio::test]
+    async fn read_capped_rejects_oversized_stream() {
+        assert_eq!(read_capped(streamed(60)).await.unwrap_err(), "Sitemap too large");
+    }
+
+    // ==================== validate_url ====================
+
+    #[test]
+    fn validate_url_applies_key_domains() {
+        let fetcher = SitemapFetcher::new(UrlGuard::new(Arc::new(SystemResolver), IpPolicy::default()));
+        let domains: DomainPolicy = serde_json::from_str(r#"{"allow": ["*.acme.com"]}"#).unwrap();
+
+        assert!(fetcher.validate_url("https://docs.acme.com/sitemap.xml", &domains).is_ok());
+        let err = fetcher.validate_url("https://evil.com/sitemap.xml", &domains).unwrap_err();
+        assert!(err.to_string().contains("matches no allow rule"));
+        assert!(fetcher.validate_url("http://10.0.0.1/sitemap.xml", &domains).is_err());
+    }
+}
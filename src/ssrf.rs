@@ -0,0 +1,682 @@
+use ipnet::IpNet;
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use std::collections::HashMap;
+use std::io;
+use std::net::{IpAddr, SocketAddr, ToSocketAddrs};
+use std::sync::{Arc, Mutex, OnceLock};
+use url::{Host, Url};
+
+use crate::domains::DomainPolicy;
+use crate::error::{AppError, AppResult};
+
+const MAX_REDIRECTS: usize = 5;
+
+/// Special-purpose ranges (IANA registries) that are never fetched unless allow-listed
+const DEFAULT_BLOCKLIST: &[&str] = &[
+    "0.0.0.0/8",       // "this network"
+    "10.0.0.0/8",      // private
+    "100.64.0.0/10",   // CGNAT shared address space
+    "127.0.0.0/8",     // loopback
+    "169.254.0.0/16",  // link-local, cloud metadata
+    "172.16.0.0/12",   // private
+    "192.0.0.0/24",    // IETF protocol assignments
+    "192.0.2.0/24",    // TEST-NET-1
+    "192.88.99.0/24",  // 6to4 relay anycast
+    "192.168.0.0/16",  // private
+    "198.18.0.0/15",   // benchmarking
+    "198.51.100.0/24", // TEST-NET-2
+    "203.0.113.0/24",  // TEST-NET-3
+    "224.0.0.0/4",     // multicast
+    "240.0.0.0/4",     // reserved, limited broadcast
+    "::/96",           // unspecified, loopback, IPv4-compatible
+    "64:ff9b::/96",    // NAT64 well-known prefix
+    "64:ff9b:1::/48",  // NAT64 local use
+    "100::/64",        // discard-only
+    "2001::/23",       // IETF protocol assignments, Teredo
+    "2001:db8::/32",   // documentation
+    "2002::/16",       // 6to4
+    "fc00::/7",        // unique local
+    "fe80::/10",       // link-local
+    "fec0::/10",       // site-local (deprecated)
+    "ff00::/8",        // multicast
+];
+
+fn default_blocklist() -> &'static [IpNet] {
+    static BLOCKLIST: OnceLock<Vec<IpNet>> = OnceLock::new();
+    BLOCKLIST.get_or_init(|| {
+        DEFAULT_BLOCKLIST
+            .iter()
+            .map(|cidr| cidr.parse().expect("valid default CIDR"))
+            .collect()
+    })
+}
+
+/// IPv4-mapped IPv6 addresses (`::ffff:a.b.c.d`) are judged as the IPv4 address
+fn canonical(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V6(v6) => v6.to_ipv4_mapped().map_or(ip, IpAddr::V4),
+        IpAddr::V4(_) => ip,
+    }
+}
+
+/// SSRF protection: whether `ip` is in the default blocklist
+pub(crate) fn is_private_ip(ip: &IpAddr) -> bool {
+    let ip = canonical(*ip);
+    default_blocklist().iter().any(|net| net.contains(&ip))
+}
+
+/// Operator CIDR lists on top of the default blocklist. `deny` wins over `allow`,
+/// which wins over the defaults, so `allow` can open up e.g. one internal subnet.
+#[derive(Debug, Clone, Default)]
+pub struct IpPolicy {
+    allow: Vec<IpNet>,
+    deny: Vec<IpNet>,
+}
+
+impl IpPolicy {
+    pub fn new(allow: Vec<IpNet>, deny: Vec<IpNet>) -> Self {
+        Self { allow, deny }
+    }
+
+    /// Reads comma-separated CIDRs (or bare IPs) from `SSRF_ALLOW_CIDRS` and `SSRF_DENY_CIDRS`
+    pub fn from_env() -> AppResult<Self> {
+        let list = |var: &str| match std::env::var(var) {
+            Ok(value) => parse_cidrs(var, &value),
+            Err(_) => Ok(Vec::new()),
+        };
+        Ok(Self::new(list("SSRF_ALLOW_CIDRS")?, list("SSRF_DENY_CIDRS")?))
+    }
+
+    pub fn blocks(&self, ip: IpAddr) -> bool {
+        let ip = canonical(ip);
+        if self.deny.iter().any(|net| net.contains(&ip)) {
+            return true;
+        }
+        if self.allow.iter().any(|net| net.contains(&ip)) {
+            return false;
+        }
+        is_private_ip(&ip)
+    }
+}
+
+fn parse_cidrs(var: &str, value: &str) -> AppResult<Vec<IpNet>> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            entry
+                .parse::<IpNet>()
+                .or_else(|_| entry.parse::<IpAddr>().map(IpNet::from))
+                .map_err(|_| AppError::Internal(format!("{}: invalid CIDR '{}'", var, entry)))
+        })
+        .collect()
+}
+
+/// Hostname lookup; tests swap in a stub so no real DNS is needed
+pub trait HostResolver: Send + Sync {
+    /// Blocking lookup of every address for `host`
+    fn lookup(&self, host: &str, port: u16) -> io::Result<Vec<IpAddr>>;
+}
+
+/// The OS resolver (`getaddrinfo`)
+pub struct SystemResolver;
+
+impl HostResolver for SystemResolver {
+    fn lookup(&self, host: &str, port: u16) -> io::Result<Vec<IpAddr>> {
+        Ok((host, port).to_socket_addrs()?.map(|addr| addr.ip()).collect())
+    }
+}
+
+fn ensure_not_localhost(domain: &str) -> AppResult<()> {
+    let domain = domain.to_lowercase();
+    if domain == "localhost" || domain.ends_with(".localhost") {
+        return Err(AppError::InvalidRequest("Localhost not allowed".to_string()));
+    }
+    Ok(())
+}
+
+/// Validates URLs against the [`IpPolicy`], resolving hostnames and rejecting any that
+/// has a blocked address among its records
+#[derive(Clone)]
+pub struct UrlGuard {
+    resolver: Arc<dyn HostResolver>,
+    ips: Arc<IpPolicy>,
+}
+
+impl UrlGuard {
+    pub fn new(resolver: Arc<dyn HostResolver>, ips: IpPolicy) -> Self {
+        Self {
+            resolver,
+            ips: Arc::new(ips),
+        }
+    }
+
+    /// System resolver with the `SSRF_*_CIDRS` policy
+    pub fn from_env() -> AppResult<Self> {
+        Ok(Self::new(Arc::new(SystemResolver), IpPolicy::from_env()?))
+    }
+
+    /// Validates URL and blocks SSRF attempts that are visible without DNS: bad schemes,
+    /// literal blocked IPs and localhost. Then applies the key's `domains` policy.
+    /// [`resolve`](Self::resolve) checks hostnames.
+    pub fn validate_url(&self, url_str: &str, domains: &DomainPolicy) -> AppResult<Url> {
+        let url = Url::parse(url_str)
+            .map_err(|e| AppError::InvalidRequest(format!("Invalid URL: {}", e)))?;
+
+        match url.scheme() {
+            "http" | "https" => {}
+            s => return Err(AppError::InvalidRequest(format!("Invalid scheme: {}", s))),
+        }
+
+        match url.host() {
+            None => return Err(AppError::InvalidRequest("Missing host".to_string())),
+            Some(Host::Ipv4(ip)) => self.ensure_public_literal(ip.into())?,
+            Some(Host::Ipv6(ip)) => self.ensure_public_literal(ip.into())?,
+            Some(Host::Domain(domain)) => ensure_not_localhost(domain)?,
+        }
+        domains.check(&url)?;
+
+        Ok(url)
+    }
+
+    /// Redirect policy for server-side fetches: at most 5 hops, each passing `validate_url`
+    pub fn guarded_redirects(&self) -> reqwest::redirect::Policy {
+        let guard = self.clone();
+        reqwest::redirect::Policy::custom(move |attempt| {
+            if attempt.previous().len() >= MAX_REDIRECTS
+                || guard.validate_url(attempt.url().as_str(), DomainPolicy::unrestricted()).is_err()
+            {
+                attempt.stop()
+            } else {
+                attempt.follow()
+            }
+        })
+    }
+
+    /// Addresses `url` may be fetched from. Blocking: resolves hostnames
+    pub fn resolve(&self, url: &Url) -> AppResult<Vec<IpAddr>> {
+        let port = url.port_or_known_default().unwrap_or(80);
+        match url.host() {
+            None => Err(AppError::InvalidRequest("Missing host".to_string())),
+            Some(Host::Ipv4(ip)) => self.ensure_public_literal(ip.into()).map(|_| vec![ip.into()]),
+            Some(Host::Ipv6(ip)) => self.ensure_public_literal(ip.into()).map(|_| vec![ip.into()]),
+            Some(Host::Domain(domain)) => {
+                ensure_not_localhost(domain)?;
+                self.resolve_host(domain, port)
+            }
+        }
+    }
+
+    /// [`resolve`](Self::resolve) off the async runtime, for checks before any fetch
+    pub async fn check(&self, url: &Url) -> AppResult<()> {
+        let guard = self.clone();
+        let url = url.clone();
+        tokio::task::spawn_blocking(move || guard.resolve(&url).map(|_| ()))
+            .await
+            .map_err(|e| AppError::Internal(format!("Task join error: {}", e)))?
+    }
+
+    fn ensure_public_literal(&self, ip: IpAddr) -> AppResult<()> {
+        if self.ips.blocks(ip) {
+            return Err(AppError::InvalidRequest(format!("Private IP not allowed: {}", ip)));
+        }
+        Ok(())
+    }
+
+    fn resolve_host(&self, host: &str, port: u16) -> AppResult<Vec<IpAddr>> {
+        let ips = self.resolver.lookup(host, port).map_err(|e| {
+            AppError::InvalidRequest(format!("Cannot resolve host {}: {}", host, e))
+        })?;
+
+        if ips.is_empty() {
+            return Err(AppError::InvalidRequest(format!("Host {} has no addresses", host)));
+        }
+        if let Some(ip) = ips.iter().find(|ip| self.ips.blocks(**ip)) {
+            return Err(AppError::InvalidRequest(format!(
+                "Host {} resolves to private address {}",
+                host, ip
+            )));
+        }
+        Ok(ips)
+    }
+}
+
+/// Server-side fetches connect only to the addresses that were checked, so a
+/// hostname cannot be re-resolved to a private address between check and connect
+impl Resolve for UrlGuard {
+    fn resolve(&self, name: Name) -> Resolving {
+        let guard = self.clone();
+        Box::pin(async move {
+            let host = name.as_str().to_string();
+            let ips = tokio::task::spawn_blocking(move || guard.resolve_host(&host, 0)).await??;
+            let addrs: Addrs = Box::new(ips.into_iter().map(|ip| SocketAddr::new(ip, 0)));
+            Ok(addrs)
+        })
+    }
+}
+
+/// Per-page-load verdict cache for the browser's request interception, so each host
+/// is looked up once per page. It does not pin what Chrome connects to; the egress
+/// proxy resolves every connection itself and is what stops DNS rebinding
+pub struct HostVerdicts {
+    guard: UrlGuard,
+    verdicts: Mutex<HashMap<String, Result<Vec<IpAddr>, String>>>,
+}
+
+impl HostVerdicts {
+    pub fn new(guard: UrlGuard) -> Self {
+        Self {
+            guard,
+            verdicts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Blocking on the first request to each host
+    pub fn check(&self, url: &Url) -> AppResult<Vec<IpAddr>> {
+        let host = url.host_str().unwrap_or_default();
+        let key = format!("{}:{}", host, url.port_or_known_default().unwrap_or(80));
+        if let Some(verdict) = self.verdicts.lock().unwrap().get(&key) {
+            return verdict.clone().map_err(AppError::InvalidRequest);
+        }
+
+        let verdict = self.guard.resolve(url).map_err(|e| match e {
+            AppError::InvalidRequest(message) => message,
+            other => other.to_string(),
+        });
+        self.verdicts.lock().unwrap().insert(key, verdict.clone());
+        verdict.map_err(AppError::InvalidRequest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Local DNS stub: fixed answers, anything else is NXDOMAIN
+    #[derive(Default)]
+    struct StubResolver {
+        records: HashMap<String, Vec<Vec<IpAddr>>>,
+        lookups: AtomicUsize,
+    }
+
+    impl StubResolver {
+        fn with(mut self, host: &str, answers: &[&[&str]]) -> Self {
+            let answers = answers
+                .iter()
+                .map(|ips| ips.iter().map(|ip| ip.parse().unwrap()).collect())
+                .collect();
+            self.records.insert(host.to_string(), answers);
+            self
+        }
+    }
+
+    impl HostResolver for StubResolver {
+        /// Successive lookups walk through the answers, repeating the last one
+        fn lookup(&self, host: &str, _port: u16) -> io::Result<Vec<IpAddr>> {
+            let n = self.lookups.fetch_add(1, Ordering::SeqCst);
+            let answers = self
+                .records
+                .get(host)
+                .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "NXDOMAIN"))?;
+            Ok(answers[n.min(answers.len() - 1)].clone())
+        }
+    }
+
+    fn guard(resolver: StubResolver) -> (UrlGuard, Arc<StubResolver>) {
+        let resolver = Arc::new(resolver);
+        (UrlGuard::new(resolver.clone(), IpPolicy::default()), resolver)
+    }
+
+    fn validate_url(url_str: &str) -> AppResult<Url> {
+        guard(StubResolver::default()).0.validate_url(url_str, DomainPolicy::unrestricted())
+    }
+
+    fn cidrs(list: &[&str]) -> Vec<IpNet> {
+        list.iter().map(|cidr| cidr.parse().unwrap()).collect()
+    }
+
+    fn url(s: &str) -> Url {
+        Url::parse(s).unwrap()
+    }
+
+    fn message(result: AppResult<Vec<IpAddr>>) -> String {
+        match result {
+            Err(AppError::InvalidRequest(message)) => message,
+            other => panic!("expected InvalidRequest, got {:?}", other),
+        }
+    }
+
+    // ==================== is_private_ip ====================
+
+    #[test]
+    fn private_ip_loopback_v4() {
+        let ip: IpAddr = "127.0.0.1".parse().unwrap();
+        assert!(is_private_ip(&ip));
+    }
+
+    #[test]
+    fn private_ip_loopback_v6() {
+        let ip: IpAddr = "::1".parse().unwrap();
+        assert!(is_private_ip(&ip));
+    }
+
+    #[test]
+    fn private_ip_class_a() {
+        let ip: IpAddr = "10.0.0.1".parse().unwrap();
+        assert!(is_private_ip(&ip));
+    }
+
+    #[test]
+    fn private_ip_class_b() {
+        let ip: IpAddr = "172.16.0.1".parse().unwrap();
+        assert!(is_private_ip(&ip));
+    }
+
+    #[test]
+    fn private_ip_class_c() {
+        let ip: IpAddr = "192.168.1.1".parse().unwrap();
+        assert!(is_private_ip(&ip));
+    }
+
+    #[test]
+    fn private_ip_link_local() {
+        let ip: IpAddr = "169.254.1.1".parse().unwrap();
+        assert!(is_private_ip(&ip));
+    }
+
+    #[test]
+    fn public_ip_allowed() {
+        let ip: IpAddr = "8.8.8.8".parse().unwrap();
+        assert!(!is_private_ip(&ip));
+    }
+
+    #[test]
+    fn default_blocklist_ipv4_ranges() {
+        let cases = [
+            // (address, blocked)
+            ("0.0.0.0", true),
+            ("0.255.255.255", true),
+            ("1.0.0.0", false),
+            ("10.255.255.255", true),
+            ("100.63.255.255", false),
+            ("100.64.0.0", true),
+            ("100.127.255.255", true),
+            ("100.128.0.0", false),
+            ("127.255.255.254", true),
+            ("169.254.169.254", true),
+            ("172.15.255.255", false),
+            ("172.31.255.255", true),
+            ("172.32.0.0", false),
+            ("192.0.0.8", true),
+            ("192.0.2.1", true),
+            ("192.88.99.1", true),
+            ("198.17.255.255", false),
+            ("198.18.0.0", true),
+            ("198.19.255.255", true),
+            ("198.20.0.0", false),
+            ("198.51.100.7", true),
+            ("203.0.113.9", true),
+            ("224.0.0.1", true),
+            ("239.255.255.250", true),
+            ("240.0.0.1", true),
+            ("255.255.255.255", true),
+            ("93.184.216.34", false),
+            ("1.1.1.1", false),
+        ];
+        for (ip, blocked) in cases {
+            assert_eq!(is_private_ip(&ip.parse().unwrap()), blocked, "{}", ip);
+        }
+    }
+
+    #[test]
+    fn default_blocklist_ipv6_ranges() {
+        let cases = [
+            // (address, blocked)
+            ("::", true),
+            ("::1", true),
+            ("::10.0.0.1", true),
+            ("::ffff:10.0.0.1", true),
+            ("::ffff:127.0.0.1", true),
+            ("::ffff:169.254.169.254", true),
+            ("::ffff:8.8.8.8", false),
+            ("64:ff9b::a00:1", true),
+            ("64:ff9b:1::1", true),
+            ("100::1", true),
+            ("2001::1", true),
+            ("2001:db8::1", true),
+            ("2002:a00:1::1", true),
+            ("fc00::1", true),
+            ("fd12:3456:789a::1", true),
+            ("fe80::1", true),
+            ("febf:ffff::1", true),
+            ("fec0::1", true),
+            ("ff02::1", true),
+            ("2606:4700:4700::1111", false),
+            ("2001:4860:4860::8888", false),
+        ];
+        for (ip, blocked) in cases {
+            assert_eq!(is_private_ip(&ip.parse().unwrap()), blocked, "{}", ip);
+        }
+    }
+
+    // ==================== IpPolicy ====================
+
+    #[test]
+    fn policy_allow_and_deny_lists() {
+        let policy = IpPolicy::new(
+            cidrs(&["10.1.2.0/24", "100.64.0.0/10"]),
+            cidrs(&["10.1.2.99/32", "203.0.114.0/24", "2606:4700::/32"]),
+        );
+        let cases = [
+            // (address, blocked)
+            ("10.1.2.3", false),
+            ("::ffff:10.1.2.3", false),
+            ("10.1.2.99", true),
+            ("10.1.3.1", true),
+            ("100.100.0.1", false),
+            ("203.0.114.5", true),
+            ("2606:4700:4700::1111", true),
+            ("8.8.8.8", false),
+            ("127.0.0.1", true),
+        ];
+        for (ip, blocked) in cases {
+            assert_eq!(policy.blocks(ip.parse().unwrap()), blocked, "{}", ip);
+        }
+    }
+
+    #[test]
+    fn policy_parses_cidr_lists() {
+        let nets = parse_cidrs("SSRF_ALLOW_CIDRS", " 10.0.0.0/8, 192.168.1.5 ,fd00::/8,").unwrap();
+        assert_eq!(nets, cidrs(&["10.0.0.0/8", "192.168.1.5/32", "fd00::/8"]));
+
+        let err = parse_cidrs("SSRF_DENY_CIDRS", "10.0.0.0/33").unwrap_err();
+        assert_eq!(err.to_string(), "Internal error: SSRF_DENY_CIDRS: invalid CIDR '10.0.0.0/33'");
+    }
+
+    #[test]
+    fn allowed_cidr_opens_literal_and_resolved_addresses() {
+        let resolver = Arc::new(StubResolver::default().with("wiki.corp.example", &[&["10.1.2.3"]]));
+        let guard = UrlGuard::new(resolver, IpPolicy::new(cidrs(&["10.1.2.0/24"]), Vec::new()));
+
+        assert!(guard.validate_url("http://10.1.2.3/", DomainPolicy::unrestricted()).is_ok());
+        assert!(guard.validate_url("http://10.1.3.3/", DomainPolicy::unrestricted()).is_err());
+        assert!(guard.resolve(&url("http://wiki.corp.example/")).is_ok());
+    }
+
+    // ==================== validate_url ====================
+
+    #[test]
+    fn validate_url_https() {
+        assert!(validate_url("https://example.com").is_ok());
+    }
+
+    #[test]
+    fn validate_url_http() {
+        assert!(validate_url("http://example.com").is_ok());
+    }
+
+    #[test]
+    fn validate_url_with_path() {
+        assert!(validate_url("https://example.com/path/to/page").is_ok());
+    }
+
+    #[test]
+    fn validate_url_with_query() {
+        assert!(validate_url("https://example.com?q=test").is_ok());
+    }
+
+    #[test]
+    fn validate_url_invalid_scheme_ftp() {
+        assert!(validate_url("ftp://example.com").is_err());
+    }
+
+    #[test]
+    fn validate_url_invalid_scheme_file() {
+        assert!(validate_url("file:///etc/passwd").is_err());
+    }
+
+    #[test]
+    fn validate_url_invalid_format() {
+        assert!(validate_url("not-a-url").is_err());
+    }
+
+    #[test]
+    fn validate_url_localhost_blocked() {
+        assert!(validate_url("http://localhost").is_err());
+        assert!(validate_url("http://localhost:8080").is_err());
+    }
+
+    #[test]
+    fn validate_url_localhost_subdomain_blocked() {
+        assert!(validate_url("http://api.localhost").is_err());
+    }
+
+    #[test]
+    fn validate_url_private_ip_blocked() {
+        assert!(validate_url("http://127.0.0.1").is_err());
+        assert!(validate_url("http://10.0.0.1").is_err());
+        assert!(validate_url("http://192.168.1.1").is_err());
+        assert!(validate_url("http://172.16.0.1").is_err());
+    }
+
+    #[test]
+    fn validate_url_public_ip_allowed() {
+        assert!(validate_url("http://8.8.8.8").is_ok());
+    }
+
+    #[test]
+    fn validate_url_private_ipv6_literal_blocked() {
+        assert!(validate_url("http://[::1]:8080").is_err());
+        assert!(validate_url("http://[::ffff:169.254.169.254]/").is_err());
+        assert!(validate_url("http://[fd00::1]/").is_err());
+    }
+
+    #[test]
+    fn validate_url_applies_domain_policy() {
+        let (guard, _) = guard(StubResolver::default());
+        let domains: DomainPolicy = serde_json::from_str(r#"{"allow": ["*.acme.com"]}"#).unwrap();
+
+        assert!(guard.validate_url("https://docs.acme.com/", &domains).is_ok());
+        let err = guard.validate_url("https://example.com/", &domains).unwrap_err();
+        assert_eq!(err.code(), "INVALID_REQUEST");
+        assert!(err.to_string().contains("matches no allow rule (`*.acme.com`)"));
+        // SSRF checks still run first
+        let err = guard.validate_url("http://127.0.0.1/", &domains).unwrap_err();
+        assert!(err.to_string().contains("Private IP not allowed"));
+    }
+
+    // ==================== UrlGuard ====================
+
+    #[test]
+    fn public_host_resolves() {
+        let (guard, _) = guard(StubResolver::default().with("example.com", &[&["93.184.216.34"]]));
+        let ips = guard.resolve(&url("https://example.com/page")).unwrap();
+        assert_eq!(ips, vec!["93.184.216.34".parse::<IpAddr>().unwrap()]);
+    }
+
+    #[test]
+    fn host_resolving_to_private_blocked() {
+        let (guard, _) = guard(StubResolver::default().with("intranet.example.com", &[&["10.0.0.5"]]));
+        assert_eq!(
+            message(guard.resolve(&url("https://intranet.example.com"))),
+            "Host intranet.example.com resolves to private address 10.0.0.5"
+        );
+    }
+
+    #[test]
+    fn any_private_record_blocks_host() {
+        let (guard, _) = guard(
+            StubResolver::default().with("mixed.example.com", &[&["93.184.216.34", "127.0.0.1"]]),
+        );
+        assert!(message(guard.resolve(&url("http://mixed.example.com"))).contains("127.0.0.1"));
+    }
+
+    #[test]
+    fn unresolvable_host_rejected() {
+        let (guard, _) = guard(StubResolver::default());
+        assert!(message(guard.resolve(&url("https://nope.invalid"))).starts_with("Cannot resolve host nope.invalid"));
+    }
+
+    #[test]
+    fn literal_ips_skip_dns() {
+        let (guard, resolver) = guard(StubResolver::default());
+        assert!(guard.resolve(&url("http://8.8.8.8")).is_ok());
+        assert!(guard.resolve(&url("http://169.254.169.254/latest/meta-data")).is_err());
+        assert_eq!(resolver.lookups.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn async_check_matches_resolve() {
+        let (guard, _) = guard(StubResolver::default().with("intranet.example.com", &[&["192.168.0.10"]]));
+        let err = guard.check(&url("https://intranet.example.com")).await.unwrap_err();
+        assert_eq!(err.code(), "INVALID_REQUEST");
+    }
+
+    // ==================== HostVerdicts ====================
+
+    #[test]
+    fn verdict_looked_up_once_per_host() {
+        let (guard, resolver) = guard(StubResolver::default().with("example.com", &[&["93.184.216.34"]]));
+        let verdicts = HostVerdicts::new(guard);
+
+        assert!(verdicts.check(&url("https://example.com/")).is_ok());
+        assert!(verdicts.check(&url("https://example.com/script.js")).is_ok());
+        assert_eq!(resolver.lookups.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn blocked_verdict_is_remembered() {
+        let (guard, resolver) = guard(StubResolver::default().with("internal.example.com", &[&["10.0.0.5"]]));
+        let verdicts = HostVerdicts::new(guard);
+
+        assert!(verdicts.check(&url("https://internal.example.com/a")).is_err());
+        assert!(verdicts.check(&url("https://internal.example.com/b")).is_err());
+        assert_eq!(resolver.lookups.load(Ordering::SeqCst), 1);
+    }
+
+    // ==================== reqwest resolver ====================
+
+    #[tokio::test]
+    async fn http_client_refuses_private_host() {
+        let (guard, _) = guard(StubResolver::default().with("internal.example.com", &[&["127.0.0.1"]]));
+        let client = reqwest::Client::builder()
+            .dns_resolver(Arc::new(guard))
+            .build()
+            .unwrap();
+
+        let err = client.get("http://internal.example.com/").send().await.unwrap_err();
+        assert!(err.is_connect());
+    }
+
+    #[tokio::test]
+    async fn http_resolver_returns_checked_addresses() {
+        let (guard, _) = guard(StubResolver::default().with("example.com", &[&["93.184.216.34"]]));
+        let addrs: Vec<SocketAddr> = Resolve::resolve(&guard, "example.com".parse().unwrap())
+            .await
+            .unwrap()
+            .collect();
+        assert_eq!(addrs, vec!["93.184.216.34:0".parse::<SocketAddr>().unwrap()]);
+    }
+}